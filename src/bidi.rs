@@ -0,0 +1,180 @@
+// WebDriver BiDi events for captures started with `--bidi`
+//
+// With the `webSocketUrl` capability ChromeDriver also serves a WebDriver BiDi
+// WebSocket for the session. WebLook subscribes there to console messages,
+// completed responses and page loads, so the console log, `--wait-responses`
+// and `--ready-state` follow what the browser reports instead of polling the
+// page with scripts.
+
+use anyhow::{Context, Result};
+use futures::{SinkExt, StreamExt};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Events subscribed to for every capture
+pub const EVENTS: [&str; 4] = [
+    "log.entryAdded",
+    "network.responseCompleted",
+    "browsingContext.domContentLoaded",
+    "browsingContext.load",
+];
+
+/// Longest to wait for ChromeDriver to confirm the subscription
+const SUBSCRIBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Address of the BiDi WebSocket that ChromeDriver on `port` serves for `session_id`
+pub fn websocket_url(port: u16, session_id: &str) -> String {
+    format!("ws://localhost:{}/session/{}", port, session_id)
+}
+
+/// What the browser has reported since the events were last cleared
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageEvents {
+    /// Console messages and uncaught errors, one `[timestamp] [level] message` line each
+    pub console: Vec<String>,
+    /// Network responses received in full, from the page and its frames
+    pub responses: usize,
+    /// Whether the top-level document has fired `DOMContentLoaded`
+    pub dom_content_loaded: bool,
+    /// Whether the top-level document has fired `load`
+    pub loaded: bool,
+}
+
+impl PageEvents {
+    /// Record `message` if it is one of the [`EVENTS`]
+    ///
+    /// Only loads of `context`, the top-level browsing context, count; frames
+    /// finish loading before their page does.
+    pub fn apply(&mut self, context: &str, message: &serde_json::Value) {
+        if message["type"] != "event" {
+            return;
+        }
+        let params = &message["params"];
+        let top_level = params["context"] == context;
+        match message["method"].as_str() {
+            Some("log.entryAdded") => self.console.push(console_line(params)),
+            Some("network.responseCompleted") => self.responses += 1,
+            Some("browsingContext.domContentLoaded") if top_level => self.dom_content_loaded = true,
+            Some("browsingContext.load") if top_level => {
+                self.dom_content_loaded = true;
+                self.loaded = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A `log.entryAdded` entry as a `[timestamp] [level] message` line
+fn console_line(entry: &serde_json::Value) -> String {
+    let timestamp = entry["timestamp"].as_i64()
+        .and_then(chrono::DateTime::from_timestamp_millis)
+        .map(|time| time.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .unwrap_or_else(|| "unknown".to_string());
+    let level = match entry["level"].as_str() {
+        Some("debug") => "DEBUG",
+        Some("warn") => "WARNING",
+        Some("error") => "ERROR",
+        _ => "INFO",
+    };
+    format!("[{}] [{}] {}", timestamp, level, entry["text"].as_str().unwrap_or(""))
+}
+
+/// A subscription to the [`EVENTS`] of one browser session
+///
+/// The events are read in the background until this is dropped.
+pub struct BidiEvents {
+    events: Arc<watch::Sender<PageEvents>>,
+    reader: tokio::task::JoinHandle<()>,
+}
+
+impl BidiEvents {
+    /// Connect to the BiDi WebSocket at `url` and subscribe to the [`EVENTS`]
+    ///
+    /// `context` is the top-level browsing context, which ChromeDriver names
+    /// after the WebDriver window handle.
+    pub async fn connect(url: &str, context: String) -> Result<Self> {
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await
+            .with_context(|| format!("Could not connect to the WebDriver BiDi endpoint {}", url))?;
+
+        let subscribe = serde_json::json!({
+            "id": 1,
+            "method": "session.subscribe",
+            "params": { "events": EVENTS },
+        });
+        socket.send(Message::Text(subscribe.to_string().into())).await
+            .context("Could not subscribe to WebDriver BiDi events")?;
+
+        let events = Arc::new(watch::Sender::new(PageEvents::default()));
+        tokio::time::timeout(SUBSCRIBE_TIMEOUT, async {
+            loop {
+                let message = match socket.next().await {
+                    Some(message) => message?,
+                    None => return Err(anyhow::anyhow!("The WebDriver BiDi connection closed")),
+                };
+                let Some(message) = parse(&message) else {
+                    continue;
+                };
+                if message["id"] != 1 {
+                    events.send_modify(|events| events.apply(&context, &message));
+                    continue;
+                }
+                if message["type"] == "error" {
+                    return Err(anyhow::anyhow!(
+                        "{}: {}",
+                        message["error"].as_str().unwrap_or("unknown error"),
+                        message["message"].as_str().unwrap_or("")
+                    ));
+                }
+                return Ok(());
+            }
+        }).await
+            .map_err(|_| anyhow::anyhow!("ChromeDriver did not answer within {:?}", SUBSCRIBE_TIMEOUT))
+            .and_then(|subscribed| subscribed)
+            .context("Could not subscribe to WebDriver BiDi events")?;
+
+        let reader = tokio::spawn({
+            let events = events.clone();
+            async move {
+                while let Some(Ok(message)) = socket.next().await {
+                    if let Some(message) = parse(&message) {
+                        events.send_modify(|events| events.apply(&context, &message));
+                    }
+                }
+            }
+        });
+
+        Ok(BidiEvents { events, reader })
+    }
+
+    /// Forget the events so far, e.g. before navigating to the next page
+    pub fn clear(&self) {
+        self.events.send_replace(PageEvents::default());
+    }
+
+    /// The events since they were last cleared
+    pub fn snapshot(&self) -> PageEvents {
+        self.events.borrow().clone()
+    }
+
+    /// Wait until `done` holds for the events, or return false after `timeout`
+    pub async fn wait_for(&self, timeout: Duration, done: impl FnMut(&PageEvents) -> bool) -> bool {
+        let mut events = self.events.subscribe();
+        matches!(tokio::time::timeout(timeout, events.wait_for(done)).await, Ok(Ok(_)))
+    }
+}
+
+impl Drop for BidiEvents {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+/// The JSON of a text message, if it is one
+fn parse(message: &Message) -> Option<serde_json::Value> {
+    match message {
+        Message::Text(text) => serde_json::from_str(text.as_str()).ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,55 @@
+// What this build of WebLook can do, for tools that drive it
+//
+// `weblook capabilities` prints this as JSON, so that scripts can check for a
+// feature or format at runtime instead of parsing `--help`. Everything here is
+// fixed when WebLook is compiled; `weblook list-browsers` reports what is
+// installed on the machine.
+
+use std::collections::BTreeMap;
+
+use crate::capture::{RecordingFormat, StdoutFormat};
+use crate::combine::CombineFormat;
+
+/// Browsers WebLook can drive
+const BACKENDS: &[&str] = &["chrome"];
+
+/// Version, features and formats of this build
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Capabilities {
+    pub name: &'static str,
+    pub version: &'static str,
+    /// Each optional Cargo feature, and whether this build has it
+    pub features: BTreeMap<&'static str, bool>,
+    pub formats: Formats,
+    pub backends: Vec<&'static str>,
+}
+
+/// Formats each kind of output can be written in
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Formats {
+    pub screenshot: Vec<&'static str>,
+    pub recording: Vec<&'static str>,
+    /// Formats accepted by `--stdout-format`
+    pub stdout: Vec<&'static str>,
+    /// Documents that `--combine` can assemble a manifest's captures into
+    pub combine: Vec<&'static str>,
+}
+
+/// Describe this build
+pub fn capabilities() -> Capabilities {
+    let features = BTreeMap::from([("mcp_experimental", cfg!(feature = "mcp_experimental"))]);
+    let formats = Formats {
+        // A GIF screenshot is written as a PNG, since GIF is only used for recordings
+        screenshot: StdoutFormat::ALL.into_iter().filter(|&format| format != StdoutFormat::Gif).map(StdoutFormat::name).collect(),
+        recording: RecordingFormat::ALL.into_iter().map(RecordingFormat::extension).collect(),
+        stdout: StdoutFormat::ALL.into_iter().map(StdoutFormat::name).collect(),
+        combine: CombineFormat::ALL.into_iter().map(CombineFormat::extension).collect(),
+    };
+    Capabilities {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        features,
+        formats,
+        backends: BACKENDS.to_vec(),
+    }
+}
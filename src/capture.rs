@@ -1,330 +1,5053 @@
 use anyhow::{Context, Result};
 use colored::*;
+use rand::Rng;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::io::{self, Write};
-use std::path::PathBuf;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::{Child, Command, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
-use thirtyfour::{ChromeCapabilities, WebDriver, ChromiumLikeCapabilities};
-use tokio::time::sleep;
+use thirtyfour::{By, CapabilitiesHelper, ChromeCapabilities, WebDriver, ChromiumLikeCapabilities};
+use thirtyfour::common::command::{Command as WebDriverCommand, ExtensionCommand};
+use thirtyfour::common::print::{PrintPage, PrintParameters};
+use thirtyfour::extensions::cdp::ChromeDevTools;
+use tokio::time::{sleep, sleep_until, Instant};
 use url::Url;
+use crate::bidi::{self, BidiEvents, PageEvents};
+use crate::label::{self, Label, LabelPosition};
+use crate::montage;
+use crate::polish::Polish;
+use crate::warm::WarmSession;
 use std::net::TcpStream;
 use std::fs;
 
 /// Options for capturing web content
+#[derive(Clone)]
 pub struct CaptureOptions {
     pub url: String,
+    /// Referer sent with the request for `url`
+    pub referer: Option<String>,
     pub output_path: PathBuf,
-    pub wait: u64,
+    /// How long to let the page settle after loading
+    pub wait: Duration,
+    /// Add a random extra delay from this range to `wait`
+    pub jitter: Option<Jitter>,
+    /// Before the wait, wait until `document.readyState` has reached this state
+    pub ready_state: Option<ReadyState>,
+    /// How to answer JavaScript dialogs the page opens, which would otherwise block the capture
+    pub dialog: DialogAction,
+    /// Abort the whole capture if it takes longer than this
+    pub max_wait: Option<Duration>,
+    /// When `max_wait` runs out mid-capture, save a screenshot, the console log
+    /// and the current URL to this directory before failing
+    pub on_timeout_dump: Option<PathBuf>,
     pub size: String,
     pub js: Option<String>,
+    /// Expand `${VAR}` environment variables in `js` (see [`expand_env`]),
+    /// treating unset variables as given
+    pub expand_env: Option<MissingVar>,
     pub debug: bool,
     pub is_recording: bool,
     pub recording_length: Option<u64>,
+    /// Frames captured per second while recording (default: 10)
+    pub capture_fps: Option<u32>,
+    /// Frames shown per second when the GIF plays back (default: same as `capture_fps`)
+    ///
+    /// A playback rate below the capture rate gives a slow-motion GIF, a higher
+    /// one a time-lapse.
+    pub playback_fps: Option<u32>,
+    /// Downscale recorded frames to this width, keeping the aspect ratio
+    pub gif_width: Option<u32>,
+    /// Number of colours in each GIF frame's palette, 2 to 256 (default: 256)
+    pub colors: Option<u16>,
+    /// Resampling filter used when frames are downscaled
+    pub resize_filter: ResizeFilter,
+    /// How to handle recorded frames whose size differs
+    pub frame_fit: FrameFit,
+    /// Animation format written for recordings
+    pub recording_format: RecordingFormat,
+    /// Offsets into a recording at which to also save a full-resolution PNG
+    pub keyframes: Vec<Duration>,
+    /// Only encode the part of a recording between these offsets
+    pub clip_time: Option<ClipTime>,
+    /// Stop a recording after this many frames, whatever its duration and rate
+    pub max_frames: Option<u64>,
+    /// How the delay between animation frames varies across a recording
+    pub delay_ramp: DelayRamp,
+    /// Only keep a recorded frame when the page changed since the last one,
+    /// delaying each by the real time until the next
+    pub record_on_change: bool,
+    /// Where recorded frames are kept until the animation is encoded
+    pub frame_storage: FrameStorage,
+    /// Save the browser console's messages to this file (see [`ConsoleLogMode`])
     pub console_log: Option<String>,
+    /// How captures share `console_log`
+    pub console_log_mode: ConsoleLogMode,
+    /// Export the page as a PDF with the given paper size instead of an image
+    pub pdf: Option<String>,
+    /// Attach to the browser kept open by `weblook serve`, if there is a live one
+    /// and the capture needs no browser launch options it wasn't started with
+    pub reuse_session: bool,
+    /// Called after each recorded frame
+    pub on_frame: Option<FrameCallback>,
+    /// Called as the capture progresses (see [`CaptureEvent`])
+    pub on_event: Option<EventCallback>,
+    /// When set to true mid-recording, stop capturing and encode the frames captured so far
+    pub stop_recording: Option<Arc<AtomicBool>>,
+    /// Capture only the given iframe (see [`FramePath`])
+    pub frame: Option<String>,
+    /// Capture only the element matching this CSS selector
+    pub selector: Option<String>,
+    /// With `selector`, capture every matching element into its own numbered
+    /// file (see [`numbered_path`])
+    pub each: bool,
+    /// Capture this many states of the page into one horizontal sprite sheet,
+    /// running `state_js` before each
+    pub states: Option<u32>,
+    /// JavaScript that puts the page into the next state; it is passed the
+    /// state's index, from 0, as `arguments[0]`
+    pub state_js: Option<String>,
+    /// Also save each state's frame to its own numbered file
+    pub state_frames: bool,
+    /// After loading the page once, resize the window to each of these sizes in
+    /// turn and take a screenshot at each (see [`size_path`])
+    pub resize_sequence: Vec<ViewportSize>,
+    /// How long to let the page settle after each resize of `resize_sequence` (default: 500ms)
+    pub resize_settle: Option<Duration>,
+    /// Capture only the region from the top of the first selector's element to
+    /// the bottom of the second's
+    pub clip_between: Option<(String, String)>,
+    /// Steps to run before navigating to `url`, e.g. a login flow
+    pub flow: Option<Vec<FlowStep>>,
+    /// Let Chrome use hardware acceleration instead of passing `--disable-gpu`
+    pub enable_gpu: bool,
+    /// Run Chrome without its sandbox, as containers often require; always done as root
+    pub no_sandbox: bool,
+    /// Accept invalid TLS certificates, e.g. self-signed ones on staging servers
+    pub ignore_cert_errors: bool,
+    /// Chrome or Chromium executable to launch instead of the one ChromeDriver finds
+    pub chrome_binary: Option<PathBuf>,
+    /// Newline-delimited list of user agents to pick from instead of the built-in ones
+    pub user_agents_file: Option<PathBuf>,
+    /// Chrome preferences to launch the browser with, e.g. to block notification prompts
+    pub prefs: Vec<ChromePref>,
+    /// Run a headful Chrome on this X display (e.g. `:99` for Xvfb) instead of headless
+    pub display: Option<String>,
+    /// Where to place the browser window, e.g. on a second monitor (default: 0,0)
+    pub window_position: WindowPosition,
+    /// Grow the window until the page's viewport is exactly `size`, making up
+    /// for the room a headful browser's tabs and toolbars take
+    pub exact_viewport: bool,
+    /// Render at this many device pixels per CSS pixel, e.g. 2 for a crisp
+    /// screenshot twice the size of `size`, leaving the viewport and user agent alone
+    pub dpr: Option<f64>,
+    /// After the wait, also wait until no element matching this CSS selector is visible
+    pub wait_gone: Option<String>,
+    /// After the wait, also wait until the page has more than this many characters of visible text
+    pub wait_min_chars: Option<usize>,
+    /// After the wait, also wait until at least this many network responses have completed
+    pub wait_responses: Option<usize>,
+    /// Freeze the page's clocks, and pause its CSS animations, this far into the page's timeline
+    pub freeze_time: Option<Duration>,
+    /// Make the page believe its tab is visible and focused, so pages that
+    /// pause while hidden keep rendering
+    pub force_visible: bool,
+    /// Font families to render with another, installed font, e.g. a brand
+    /// font missing on this machine
+    pub font_substitutes: Vec<FontSubstitute>,
+    /// Before a screenshot, wait until consecutive screenshots are identical
+    pub stable: bool,
+    /// Only let the browser contact these hosts (see [`DomainPattern`])
+    pub allow_domains: Vec<String>,
+    /// Never let the browser contact these hosts (see [`DomainPattern`])
+    pub deny_domains: Vec<String>,
+    /// Render the page for this CSS media type instead of its normal screen styles
+    pub emulate_media: Option<MediaType>,
+    /// Tell the page the user prefers reduced motion (`prefers-reduced-motion: reduce`)
+    pub reduced_motion: bool,
+    /// Present the browser as using this language, e.g. `fr-CA`: it is sent
+    /// in `Accept-Language`, reported by `navigator.language` and used by `Intl`
+    pub locale: Option<String>,
+    /// Render pages without a background of their own over transparency instead of white
+    pub transparent: bool,
+    /// Encoding of output written to stdout, instead of PNG (screenshots) or `recording_format`
+    pub stdout_format: Option<StdoutFormat>,
+    /// Scroll the page here before capturing (see [`ScrollTarget`])
+    pub scroll_to: Option<String>,
+    /// Fail instead of replacing output files that already exist
+    pub no_clobber: bool,
+    /// Name the output file after the page's final URL (see [`file_name_for_url`])
+    pub name_by_final_url: bool,
+    /// Name the output file after the page's title (see [`file_name_for_title`]),
+    /// adding a number instead of replacing an existing file
+    pub name_by_title: bool,
+    /// Record every URL the page was redirected through on its way to the one
+    /// captured (see [`CaptureInfo::redirects`])
+    pub capture_redirects: bool,
+    /// Follow console messages, completed responses and page loads through
+    /// WebDriver BiDi events (see [`crate::bidi`]) instead of polling the page
+    pub bidi: bool,
+    /// Save the page's favicon next to the output (see [`favicon_path`])
+    pub favicon: bool,
+    /// Save these elements' computed styles next to the output (see [`styles_path`])
+    pub styles: Vec<StyleQuery>,
+    /// Save the page's visible text to this file (see [`normalize_text`])
+    pub text_path: Option<PathBuf>,
+    /// Only save the text of the element matching this CSS selector
+    pub text_selector: Option<String>,
+    /// Save the page's performance metrics as JSON to this file, once it is ready
+    pub perf_path: Option<PathBuf>,
+    /// Colour transforms applied to the screenshot or every recorded frame, before any label
+    pub effects: ImageEffects,
+    /// Frame screenshots for presentation, after any label (see [`Polish`])
+    pub polish: Option<Polish>,
+    /// Cover the elements matching these CSS selectors with solid boxes, e.g. to redact personal data
+    pub masks: Vec<String>,
+    /// Colour of the masks (`#rgb`, `#rrggbb` or `#rrggbbaa`, default: black)
+    pub mask_color: Option<String>,
+    /// Text to stamp onto screenshots
+    pub label: Option<String>,
+    /// Stamp the capture time onto screenshots, after any `label` text
+    pub label_timestamp: bool,
+    /// Corner of the screenshot the label is drawn in
+    pub label_position: LabelPosition,
+    /// Background colour of the label (`#rgb`, `#rrggbb` or `#rrggbbaa`, default: translucent black)
+    pub label_background: Option<String>,
 }
 
-/// Viewport size representation
-pub struct ViewportSize {
-    pub width: u32,
-    pub height: u32,
+impl Default for CaptureOptions {
+    /// The command line's defaults: a 1280x720 screenshot to `weblook.png`
+    /// after a 10 second wait, with no URL set
+    fn default() -> Self {
+        CaptureOptions {
+            url: String::new(),
+            referer: None,
+            output_path: PathBuf::from("weblook.png"),
+            wait: Duration::from_secs(10),
+            jitter: None,
+            ready_state: None,
+            dialog: DialogAction::default(),
+            max_wait: None,
+            on_timeout_dump: None,
+            size: "1280x720".to_string(),
+            js: None,
+            expand_env: None,
+            debug: false,
+            is_recording: false,
+            recording_length: None,
+            capture_fps: None,
+            playback_fps: None,
+            gif_width: None,
+            colors: None,
+            resize_filter: ResizeFilter::default(),
+            frame_fit: FrameFit::default(),
+            recording_format: RecordingFormat::default(),
+            keyframes: Vec::new(),
+            clip_time: None,
+            max_frames: None,
+            delay_ramp: DelayRamp::default(),
+            record_on_change: false,
+            frame_storage: FrameStorage::default(),
+            console_log: None,
+            console_log_mode: ConsoleLogMode::default(),
+            pdf: None,
+            on_frame: None,
+            on_event: None,
+            stop_recording: None,
+            reuse_session: false,
+            frame: None,
+            selector: None,
+            each: false,
+            states: None,
+            state_js: None,
+            state_frames: false,
+            resize_sequence: Vec::new(),
+            resize_settle: None,
+            clip_between: None,
+            flow: None,
+            enable_gpu: false,
+            no_sandbox: false,
+            ignore_cert_errors: false,
+            chrome_binary: None,
+            user_agents_file: None,
+            prefs: Vec::new(),
+            display: None,
+            window_position: Default::default(),
+            exact_viewport: false,
+            dpr: None,
+            wait_gone: None,
+            wait_min_chars: None,
+            wait_responses: None,
+            freeze_time: None,
+            force_visible: false,
+            font_substitutes: Vec::new(),
+            stable: false,
+            allow_domains: Vec::new(),
+            deny_domains: Vec::new(),
+            emulate_media: None,
+            reduced_motion: false,
+            locale: None,
+            transparent: false,
+            stdout_format: None,
+            scroll_to: None,
+            no_clobber: false,
+            name_by_final_url: false,
+            name_by_title: false,
+            capture_redirects: false,
+            bidi: false,
+            favicon: false,
+            styles: Vec::new(),
+            text_path: None,
+            text_selector: None,
+            perf_path: None,
+            effects: ImageEffects::default(),
+            polish: None,
+            masks: Vec::new(),
+            mask_color: None,
+            label: None,
+            label_timestamp: false,
+            label_position: LabelPosition::default(),
+            label_background: None,
+        }
+    }
 }
 
-impl std::str::FromStr for ViewportSize {
+/// Callback invoked after each recorded frame with (frames captured, total frames)
+pub type FrameCallback = Arc<dyn Fn(u64, u64) + Send + Sync>;
+
+/// Callback invoked with each [`CaptureEvent`] of a capture
+pub type EventCallback = Arc<dyn Fn(CaptureEvent) + Send + Sync>;
+
+/// Progress of a capture, for embedders driving their own progress display
+///
+/// Events are reported whether or not weblook prints its own progress to stderr.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureEvent {
+    /// Navigation to the page is starting
+    Navigating { url: String },
+    /// The page has loaded and every wait is over; `url` is where any redirects ended up
+    Loaded { url: String },
+    /// A recording frame was captured
+    Frame { captured: u64, total: u64 },
+    /// An output file was written (`-` for stdout)
+    Saved { path: PathBuf },
+}
+
+/// Report `event` to the capture's listener, if any
+fn emit(options: &CaptureOptions, event: CaptureEvent) {
+    if let Some(callback) = &options.on_event {
+        callback(event);
+    }
+}
+
+/// Timing of a recording
+///
+/// `capture_fps` controls how often a frame is grabbed from the page, while
+/// `playback_fps` controls the delay written for each GIF frame. With equal rates
+/// the GIF plays in real time; capturing at 10fps and playing back at 5fps makes
+/// it run at half speed and twice as long.
+pub struct RecordingSettings {
+    pub duration_secs: u64,
+    pub capture_fps: u32,
+    pub playback_fps: u32,
+    /// Target GIF width in pixels; frames are never upscaled
+    pub gif_width: Option<u32>,
+    /// Palette size of each GIF frame; fewer colours give smaller files
+    pub colors: u16,
+    /// Resampling filter used to downscale frames
+    pub resize_filter: ResizeFilter,
+    /// How to handle frames whose size differs
+    pub frame_fit: FrameFit,
+    /// Animation format to encode the frames to
+    pub format: RecordingFormat,
+    /// Offsets at which to also save the full-resolution frame as a PNG
+    pub keyframes: Vec<Duration>,
+    /// Part of the recording to encode; the rest is captured but left out
+    pub clip: Option<ClipTime>,
+    /// Cap on the number of frames captured
+    pub max_frames: Option<u64>,
+    /// How the frame delay varies across the animation
+    pub delay_ramp: DelayRamp,
+    /// Keep only frames where the page changed, each shown for as long as it
+    /// really lasted
+    pub on_change: bool,
+    /// Colour transforms applied to every frame
+    pub effects: ImageEffects,
+}
+
+/// Colour transforms applied to captures before they are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ImageEffects {
+    /// Convert to shades of grey, e.g. to review contrast
+    pub grayscale: bool,
+    /// Invert the colours, keeping transparency, e.g. to review a dark mode conversion
+    pub invert: bool,
+}
+
+impl ImageEffects {
+    pub fn is_none(self) -> bool {
+        !self.grayscale && !self.invert
+    }
+
+    /// Apply the effects to `image`, grayscale first
+    pub fn apply(self, image: image::DynamicImage) -> image::DynamicImage {
+        let mut image = if self.grayscale {
+            image::DynamicImage::ImageLumaA8(image.to_luma_alpha8())
+        } else {
+            image
+        };
+        if self.invert {
+            image.invert();
+        }
+        image
+    }
+
+    /// Apply the effects to a PNG screenshot, re-encoding it only if there are any
+    fn apply_png(self, png: Vec<u8>) -> Result<Vec<u8>> {
+        if self.is_none() {
+            return Ok(png);
+        }
+        let image = self.apply(image::load_from_memory(&png)?).to_rgba8();
+        let mut encoded = Vec::new();
+        image.write_to(&mut io::Cursor::new(&mut encoded), image::ImageFormat::Png)?;
+        Ok(encoded)
+    }
+}
+
+/// Animation format of a recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecordingFormat {
+    #[default]
+    Gif,
+    /// Animated WebP, usually much smaller than the equivalent GIF
+    Webp,
+}
+
+impl RecordingFormat {
+    pub const ALL: [RecordingFormat; 2] = [RecordingFormat::Gif, RecordingFormat::Webp];
+
+    /// File extension used for the default output path
+    pub fn extension(self) -> &'static str {
+        match self {
+            RecordingFormat::Gif => "gif",
+            RecordingFormat::Webp => "webp",
+        }
+    }
+
+    /// Human-readable name used in progress messages
+    fn label(self) -> &'static str {
+        match self {
+            RecordingFormat::Gif => "GIF",
+            RecordingFormat::Webp => "WebP",
+        }
+    }
+}
+
+/// Encoding of output written to stdout, or of a file (see [`output_format`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StdoutFormat {
+    Png,
+    Jpeg,
+    Webp,
+    Gif,
+}
+
+impl StdoutFormat {
+    pub const ALL: [StdoutFormat; 4] = [StdoutFormat::Png, StdoutFormat::Jpeg, StdoutFormat::Webp, StdoutFormat::Gif];
+
+    /// Name the format is given by on the command line, e.g. `jpeg`
+    pub fn name(self) -> &'static str {
+        match self {
+            StdoutFormat::Png => "png",
+            StdoutFormat::Jpeg => "jpeg",
+            StdoutFormat::Webp => "webp",
+            StdoutFormat::Gif => "gif",
+        }
+    }
+
+    /// Animation format to encode recordings in, for the formats that can animate
+    fn recording_format(self) -> Option<RecordingFormat> {
+        match self {
+            StdoutFormat::Gif => Some(RecordingFormat::Gif),
+            StdoutFormat::Webp => Some(RecordingFormat::Webp),
+            StdoutFormat::Png | StdoutFormat::Jpeg => None,
+        }
+    }
+
+    /// Human-readable name used in messages
+    fn label(self) -> &'static str {
+        match self {
+            StdoutFormat::Png => "PNG",
+            StdoutFormat::Jpeg => "JPEG",
+            StdoutFormat::Webp => "WebP",
+            StdoutFormat::Gif => "GIF",
+        }
+    }
+}
+
+impl From<RecordingFormat> for StdoutFormat {
+    fn from(format: RecordingFormat) -> Self {
+        match format {
+            RecordingFormat::Gif => StdoutFormat::Gif,
+            RecordingFormat::Webp => StdoutFormat::Webp,
+        }
+    }
+}
+
+/// Format named by the extension of `path`, if it is one weblook can write
+pub fn format_from_extension(path: &Path) -> Option<StdoutFormat> {
+    path.extension().and_then(|e| e.to_str())?.parse().ok()
+}
+
+/// Format a screenshot or recording is written in
+///
+/// Output to stdout uses `stdout_format`, and a file the format its
+/// extension names, so `shot.jpg` is a JPEG and `demo.webp` an animated WebP.
+/// Otherwise screenshots are PNGs and recordings `recording_format`, which
+/// also wins over a `.gif` extension when it is WebP.
+pub fn output_format(options: &CaptureOptions) -> StdoutFormat {
+    if options.output_path.to_str() == Some("-") {
+        return match options.stdout_format {
+            Some(format) => format,
+            None if options.is_recording => options.recording_format.into(),
+            None => StdoutFormat::Png,
+        };
+    }
+    match (format_from_extension(&options.output_path), options.is_recording) {
+        (Some(StdoutFormat::Webp), true) => StdoutFormat::Webp,
+        (_, true) => options.recording_format.into(),
+        (Some(StdoutFormat::Gif) | None, false) => StdoutFormat::Png,
+        (Some(format), false) => format,
+    }
+}
+
+/// Warning for an output file whose extension doesn't match what is written to it
+fn output_format_warning(options: &CaptureOptions) -> Option<String> {
+    let path = &options.output_path;
+    if path.to_str() == Some("-") || options.pdf.is_some() {
+        return None;
+    }
+    let format = output_format(options);
+    match path.extension() {
+        None => Some(format!("{} has no extension, so it is written as a {}", path.display(), format.label())),
+        Some(_) if format_from_extension(path) != Some(format) => Some(format!(
+            "{} does not name a format {} can be written in, so it is written as a {}",
+            path.display(), if options.is_recording { "recordings" } else { "screenshots" }, format.label()
+        )),
+        Some(_) => None,
+    }
+}
+
+impl std::str::FromStr for StdoutFormat {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<&str> = s.split('x').collect();
-        if parts.len() != 2 {
-            return Err(anyhow::anyhow!("Invalid viewport size format. Expected WIDTHxHEIGHT"));
+        match s.to_lowercase().as_str() {
+            "png" => Ok(StdoutFormat::Png),
+            "jpeg" | "jpg" => Ok(StdoutFormat::Jpeg),
+            "webp" => Ok(StdoutFormat::Webp),
+            "gif" => Ok(StdoutFormat::Gif),
+            _ => Err(anyhow::anyhow!("Invalid stdout format. Expected png, jpeg, webp or gif")),
         }
+    }
+}
 
-        let width = parts[0].parse::<u32>()
-            .context("Failed to parse viewport width")?;
-        let height = parts[1].parse::<u32>()
-            .context("Failed to parse viewport height")?;
+/// Curve the per-frame delay of an animation follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DelayRamp {
+    /// Every frame is shown for the same time
+    #[default]
+    Linear,
+    /// Frames near the start and end are shown up to twice as long, so loops
+    /// slow down into and out of the restart
+    Ease,
+}
 
-        Ok(ViewportSize { width, height })
+impl std::str::FromStr for DelayRamp {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "linear" => Ok(DelayRamp::Linear),
+            "ease" => Ok(DelayRamp::Ease),
+            _ => Err(anyhow::anyhow!("Invalid delay ramp. Expected linear or ease")),
+        }
     }
 }
 
-// User agent strings for rotation
-const USER_AGENTS: [&str; 2] = [
-    // Chrome on Windows
-    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/123.0.0.0 Safari/537.36",
-    // Chrome on Mac
-    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/123.0.0.0 Safari/537.36",
-];
+/// How the console log of each capture in a run is saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConsoleLogMode {
+    /// Each capture replaces the log file, so only one page can be captured
+    #[default]
+    Replace,
+    /// Each capture adds its messages to the end of the log file, under a
+    /// `==> URL <==` header
+    Append,
+    /// Each capture writes its own log next to its output (see [`console_log_path`])
+    PerPage,
+}
 
-// ChromeDriver management
-pub struct ChromeDriverManager {
-    process: Option<Child>,
-    port: u16,
-    debug: bool,
+impl std::str::FromStr for ConsoleLogMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "replace" => Ok(ConsoleLogMode::Replace),
+            "append" => Ok(ConsoleLogMode::Append),
+            "per-page" => Ok(ConsoleLogMode::PerPage),
+            _ => Err(anyhow::anyhow!("Invalid console log mode. Expected replace, append or per-page")),
+        }
+    }
 }
 
-impl ChromeDriverManager {
-    pub fn new(port: u16, debug: bool) -> Self {
-        ChromeDriverManager {
-            process: None,
-            port,
-            debug,
+/// Where a recording keeps its frames until they are encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameStorage {
+    /// PNG files in a temporary directory
+    #[default]
+    Disk,
+    /// The same PNGs in memory, for machines with a small or read-only temp directory
+    Memory,
+}
+
+impl std::str::FromStr for FrameStorage {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "disk" => Ok(FrameStorage::Disk),
+            "memory" => Ok(FrameStorage::Memory),
+            _ => Err(anyhow::anyhow!("Invalid frame storage. Expected memory or disk")),
         }
     }
+}
 
-    pub fn is_running(&self) -> bool {
-        TcpStream::connect(format!("127.0.0.1:{}", self.port)).is_ok()
+/// Resampling filter used wherever weblook resizes an image
+///
+/// `Nearest` keeps hard pixel edges, which suits pixel art; `Lanczos3` is the
+/// slowest but gives the smoothest result for photos and text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResizeFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    #[default]
+    Lanczos3,
+}
+
+impl ResizeFilter {
+    fn filter_type(self) -> image::imageops::FilterType {
+        match self {
+            ResizeFilter::Nearest => image::imageops::FilterType::Nearest,
+            ResizeFilter::Triangle => image::imageops::FilterType::Triangle,
+            ResizeFilter::CatmullRom => image::imageops::FilterType::CatmullRom,
+            ResizeFilter::Lanczos3 => image::imageops::FilterType::Lanczos3,
+        }
     }
+}
 
-    pub fn start(&mut self) -> Result<()> {
-        if self.is_running() {
-            if self.debug {
-                println!("ChromeDriver is already running on port {}", self.port);
-            }
-            return Ok(());
+impl std::str::FromStr for ResizeFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "nearest" => Ok(ResizeFilter::Nearest),
+            "triangle" => Ok(ResizeFilter::Triangle),
+            "catmull" | "catmullrom" | "catmull-rom" => Ok(ResizeFilter::CatmullRom),
+            "lanczos3" | "lanczos" => Ok(ResizeFilter::Lanczos3),
+            _ => Err(anyhow::anyhow!("Invalid resize filter. Expected nearest, triangle, catmull or lanczos3")),
         }
+    }
+}
 
-        if self.debug {
-            println!("Starting ChromeDriver on port {}...", self.port);
+/// A recorded frame, kept as the PNG the browser returned
+///
+/// Repeated frames share the same file or buffer.
+#[derive(Debug, Clone)]
+enum StoredFrame {
+    File(PathBuf),
+    Memory(Arc<Vec<u8>>),
+}
+
+impl StoredFrame {
+    fn load(&self) -> Result<image::DynamicImage> {
+        Ok(match self {
+            StoredFrame::File(path) => image::open(path)?,
+            StoredFrame::Memory(png) => image::load_from_memory_with_format(png, image::ImageFormat::Png)?,
+        })
+    }
+    
+    /// Copy the PNG to `writer`
+    fn copy_to(&self, writer: &mut impl Write) -> Result<()> {
+        match self {
+            StoredFrame::File(path) => {
+                io::copy(&mut fs::File::open(path)?, writer)?;
+            }
+            StoredFrame::Memory(png) => writer.write_all(png)?,
         }
-        
-        let process = if self.debug {
-            Command::new("chromedriver")
-                .arg(format!("--port={}", self.port))
-                .spawn()
-                .context("Failed to start ChromeDriver. Make sure it's installed.")?
-        } else {
-            Command::new("chromedriver")
-                .arg(format!("--port={}", self.port))
-                .stdout(Stdio::null())
-                .stderr(Stdio::null())
-                .spawn()
-                .context("Failed to start ChromeDriver. Make sure it's installed.")?
-        };
+        Ok(())
+    }
+}
 
-        self.process = Some(process);
+/// Keeps the frames of one recording in the chosen [`FrameStorage`]
+struct FrameStore {
+    /// Temporary directory of the frame files, removed on drop; `None` in memory
+    dir: Option<tempfile::TempDir>,
+}
 
-        // Wait for ChromeDriver to start
-        let start_time = std::time::Instant::now();
-        while !self.is_running() {
-            if start_time.elapsed() > Duration::from_secs(5) {
-                return Err(anyhow::anyhow!("Timed out waiting for ChromeDriver to start"));
+impl FrameStore {
+    fn new(storage: FrameStorage) -> Result<Self> {
+        let dir = match storage {
+            FrameStorage::Disk => Some(tempfile::tempdir().context("Failed to create a temporary directory for frames")?),
+            FrameStorage::Memory => None,
+        };
+        Ok(FrameStore { dir })
+    }
+    
+    /// Keep `png` as frame `i`
+    fn store(&self, i: u64, png: Vec<u8>) -> Result<StoredFrame> {
+        match &self.dir {
+            Some(dir) => {
+                let frame_path = dir.path().join(format!("frame_{:04}.png", i));
+                fs::write(&frame_path, png)?;
+                Ok(StoredFrame::File(frame_path))
             }
-            std::thread::sleep(Duration::from_millis(100));
+            None => Ok(StoredFrame::Memory(Arc::new(png))),
         }
+    }
+}
 
-        if self.debug {
-            println!("ChromeDriver started successfully");
+/// CSS media type the page is rendered for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaType {
+    Screen,
+    /// Apply the page's print stylesheet, as when printing or exporting a PDF
+    Print,
+}
+
+impl MediaType {
+    /// Name used by the `Emulation.setEmulatedMedia` CDP command
+    fn cdp_name(self) -> &'static str {
+        match self {
+            MediaType::Screen => "screen",
+            MediaType::Print => "print",
         }
-        Ok(())
     }
 }
 
-impl Drop for ChromeDriverManager {
-    fn drop(&mut self) {
-        if let Some(mut process) = self.process.take() {
-            if self.debug {
-                println!("Stopping ChromeDriver...");
-            }
-            let _ = process.kill();
-            let _ = process.wait();
-            if self.debug {
-                println!("ChromeDriver stopped");
-            }
+impl std::str::FromStr for MediaType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "screen" => Ok(MediaType::Screen),
+            "print" => Ok(MediaType::Print),
+            _ => Err(anyhow::anyhow!("Invalid media type. Expected screen or print")),
         }
     }
 }
 
-/// Main capture function that handles both screenshots and recordings
-pub async fn perform_capture(options: CaptureOptions) -> Result<()> {
-    // Determine if we're outputting to stdout
-    let is_piped = options.output_path.to_str() == Some("-");
+/// Loading state of a document, as reported by `document.readyState`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ReadyState {
+    Loading,
+    /// The HTML has been parsed, though images and stylesheets may still be loading
+    Interactive,
+    /// The page and all its resources have loaded
+    Complete,
+}
+
+impl ReadyState {
+    fn name(self) -> &'static str {
+        match self {
+            ReadyState::Loading => "loading",
+            ReadyState::Interactive => "interactive",
+            ReadyState::Complete => "complete",
+        }
+    }
+}
+
+impl std::str::FromStr for ReadyState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "interactive" => Ok(ReadyState::Interactive),
+            "complete" => Ok(ReadyState::Complete),
+            _ => Err(anyhow::anyhow!("Invalid ready state. Expected interactive or complete")),
+        }
+    }
+}
+
+/// What to do with JavaScript dialogs (`alert`, `confirm`, `prompt`) the page opens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DialogAction {
+    /// Accept the dialog, as clicking OK would
+    Accept,
+    /// Dismiss the dialog, as clicking Cancel would
+    #[default]
+    Dismiss,
+}
+
+impl DialogAction {
+    /// Value of the WebDriver `unhandledPromptBehavior` capability
+    fn capability(self) -> &'static str {
+        match self {
+            DialogAction::Accept => "accept",
+            DialogAction::Dismiss => "dismiss",
+        }
+    }
     
-    // Start ChromeDriver if not already running
-    let chromedriver_port = 9515;
-    let mut chromedriver = ChromeDriverManager::new(chromedriver_port, options.debug);
-    chromedriver.start()?;
+    fn past_tense(self) -> &'static str {
+        match self {
+            DialogAction::Accept => "Accepted",
+            DialogAction::Dismiss => "Dismissed",
+        }
+    }
+}
 
-    // Parse URL
-    let url = Url::parse(&options.url).context("Failed to parse URL")?;
+impl std::str::FromStr for DialogAction {
+    type Err = anyhow::Error;
 
-    // Parse viewport size
-    let viewport = options.size.parse::<ViewportSize>()?;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "accept" => Ok(DialogAction::Accept),
+            "dismiss" => Ok(DialogAction::Dismiss),
+            _ => Err(anyhow::anyhow!("Invalid dialog action. Expected accept or dismiss")),
+        }
+    }
+}
 
-    // Determine recording length if recording
-    let recording_length = if options.is_recording {
-        options.recording_length.unwrap_or(10)
-    } else {
-        0
+/// Strategy for recorded frames that differ in size, e.g. after a layout shift
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameFit {
+    /// Pad every frame with white to the size of the largest frame
+    #[default]
+    Pad,
+    /// Crop every frame to the size of the smallest frame
+    Crop,
+    /// Fail the recording
+    Error,
+}
+
+impl std::str::FromStr for FrameFit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pad" => Ok(FrameFit::Pad),
+            "crop" => Ok(FrameFit::Crop),
+            "error" => Ok(FrameFit::Error),
+            _ => Err(anyhow::anyhow!("Invalid frame fit. Expected pad, crop or error")),
+        }
+    }
+}
+
+/// Make all frames the same size according to `fit`
+///
+/// Returns the frames and whether any of them had to be changed.
+pub fn fit_frames(frames: Vec<image::RgbaImage>, fit: FrameFit) -> Result<(Vec<image::RgbaImage>, bool)> {
+    let Some(first) = frames.first() else {
+        return Ok((frames, false));
     };
+    let first_dims = first.dimensions();
+    if frames.iter().all(|f| f.dimensions() == first_dims) {
+        return Ok((frames, false));
+    }
 
-    if !is_piped && !options.debug {
-        eprintln!("{}", "Starting WebLook...".bright_cyan());
-        if options.is_recording {
-            eprintln!("{} {}", "•".yellow(), format!("Recording {} for {} seconds", url, recording_length).yellow());
-        } else {
-            eprintln!("{} {}", "•".yellow(), format!("Taking screenshot of {}", url).yellow());
+    let (width, height) = match fit {
+        FrameFit::Error => {
+            let (index, frame) = frames.iter().enumerate()
+                .find(|(_, f)| f.dimensions() != first_dims)
+                .expect("a mismatching frame exists");
+            return Err(anyhow::anyhow!(
+                "Recorded frame {} is {}x{} but the first frame is {}x{} (the page layout changed during recording)",
+                index, frame.width(), frame.height(), first_dims.0, first_dims.1
+            ));
         }
-        std::io::stderr().flush().ok();
+        FrameFit::Pad => (
+            frames.iter().map(|f| f.width()).max().unwrap_or(first_dims.0),
+            frames.iter().map(|f| f.height()).max().unwrap_or(first_dims.1),
+        ),
+        FrameFit::Crop => (
+            frames.iter().map(|f| f.width()).min().unwrap_or(first_dims.0),
+            frames.iter().map(|f| f.height()).min().unwrap_or(first_dims.1),
+        ),
+    };
+
+    let fitted = frames.into_iter()
+        .map(|frame| {
+            if frame.dimensions() == (width, height) {
+                return frame;
+            }
+            let mut canvas = image::RgbaImage::from_pixel(width, height, image::Rgba([255, 255, 255, 255]));
+            image::imageops::replace(&mut canvas, &frame, 0, 0);
+            canvas
+        })
+        .collect();
+
+    Ok((fitted, true))
+}
+
+impl RecordingSettings {
+    /// Number of frames to capture, after applying `max_frames`
+    pub fn total_frames(&self) -> u64 {
+        let total_frames = self.duration_secs * self.capture_fps as u64;
+        self.max_frames.map_or(total_frames, |max| total_frames.min(max))
     }
     
-    // Set up WebDriver
-    let driver = setup_webdriver(viewport, chromedriver_port).await?;
-    
-    // Navigate to URL and wait
-    navigate_and_wait(&driver, url, Duration::from_secs(options.wait), is_piped, options.debug).await?;
+    /// Index of the captured frame closest to `offset` into the recording
+    pub fn keyframe_index(&self, offset: Duration) -> u64 {
+        let total_frames = self.total_frames();
+        let index = (offset.as_secs_f64() * self.capture_fps as f64).round() as u64;
+        index.min(total_frames.saturating_sub(1))
+    }
     
-    // Execute JavaScript if provided
-    if let Some(js_code) = &options.js {
-        execute_javascript(&driver, js_code).await?;
+    /// Delay between GIF frames in hundredths of a second
+    pub fn gif_frame_delay(&self) -> u16 {
+        ((100 + self.playback_fps / 2) / self.playback_fps).max(1) as u16
     }
     
-    // Capture console logs if requested
-    if let Some(log_path) = &options.console_log {
-        capture_console_logs(&driver, log_path, is_piped, options.debug).await?;
+    /// Delay of each of `frames` animation frames in hundredths of a second,
+    /// following `delay_ramp` around `gif_frame_delay`
+    pub fn frame_delays(&self, frames: usize) -> Vec<u16> {
+        let delay = self.gif_frame_delay();
+        (0..frames)
+            .map(|i| match self.delay_ramp {
+                DelayRamp::Linear => delay,
+                DelayRamp::Ease => {
+                    // 2x the delay at either end, easing to 1x in the middle
+                    let t = if frames > 1 { i as f64 / (frames - 1) as f64 } else { 0.0 };
+                    let factor = 1.0 + (std::f64::consts::PI * t).cos().powi(2);
+                    (delay as f64 * factor).round().min(u16::MAX as f64) as u16
+                }
+            })
+            .collect()
     }
     
-    // Capture screenshot or recording
-    if options.is_recording {
-        create_recording(&driver, recording_length, &options.output_path, is_piped, options.debug).await?;
-    } else {
-        take_screenshot(&driver, &options.output_path, is_piped, options.debug).await?;
+    /// Delay of each frame of an on-change recording in hundredths of a second
+    ///
+    /// `times` holds when each frame was captured and `end` when the recording
+    /// ended, both from its start. Each frame lasts until the next one, scaled
+    /// by the playback rate as for fixed-rate recordings, and at least 2
+    /// hundredths since most viewers slow shorter delays right down.
+    pub fn elapsed_delays(&self, times: &[Duration], end: Duration) -> Vec<u16> {
+        let speed = self.capture_fps as f64 / self.playback_fps as f64;
+        times.iter().enumerate()
+            .map(|(i, &time)| {
+                let next = times.get(i + 1).copied().unwrap_or(end).max(time);
+                ((next - time).as_secs_f64() * speed * 100.0).round().clamp(2.0, u16::MAX as f64) as u16
+            })
+            .collect()
+    }
+}
+
+/// Parse a duration such as `500ms`, `1.5s`, `2m` or `1m30s`
+///
+/// A bare number is taken as seconds, so `--wait 10` keeps its old meaning.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    if let Ok(secs) = s.parse::<f64>() {
+        return Duration::try_from_secs_f64(secs)
+            .map_err(|_| anyhow::anyhow!("Invalid duration: {}", s));
     }
     
-    // Clean up
-    driver.quit().await?;
+    if s.is_empty() {
+        return Err(anyhow::anyhow!("Duration must not be empty"));
+    }
     
-    // ChromeDriver will be automatically stopped by the Drop implementation
+    let mut total = Duration::ZERO;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(rest.len());
+        let unit_len = rest[number_len..].find(|c: char| c.is_ascii_digit() || c == '.').unwrap_or(rest.len() - number_len);
+        let (number, unit) = (&rest[..number_len], rest[number_len..number_len + unit_len].trim());
+        let value = number.parse::<f64>()
+            .map_err(|_| anyhow::anyhow!("Invalid duration: {}. Expected e.g. 500ms, 1.5s or 2m", s))?;
+        let secs = match unit {
+            "ms" => value / 1000.0,
+            "s" => value,
+            "m" => value * 60.0,
+            "h" => value * 3600.0,
+            _ => return Err(anyhow::anyhow!("Invalid duration unit in {}. Expected ms, s, m or h", s)),
+        };
+        total += Duration::try_from_secs_f64(secs)
+            .map_err(|_| anyhow::anyhow!("Invalid duration: {}", s))?;
+        rest = &rest[number_len + unit_len..];
+    }
     
-    Ok(())
+    Ok(total)
 }
 
-async fn setup_webdriver(viewport: ViewportSize, port: u16) -> Result<WebDriver> {
-    let mut caps = ChromeCapabilities::new();
-    
-    // Select a random user agent
-    let user_agent_idx = 0; // Just use the first one for testing
-    let user_agent = USER_AGENTS[user_agent_idx];
-    
-    // Configure headless mode and user agent
-    caps.add_arg("--headless=new")?;
-    caps.add_arg("--disable-gpu")?;
+/// Part of a recording to keep, written `START-END`, e.g. `2s-5.5s`
+///
+/// Both ends are durations as for [`parse_duration`], so `2-5` is the third
+/// to fifth seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClipTime {
+    pub start: Duration,
+    pub end: Duration,
+}
+
+impl std::str::FromStr for ClipTime {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.split_once('-')
+            .ok_or_else(|| anyhow::anyhow!("Invalid clip {}. Expected START-END, e.g. 2s-5s", s))?;
+        let (start, end) = (parse_duration(start)?, parse_duration(end)?);
+        if start >= end {
+            return Err(anyhow::anyhow!("Invalid clip {}. The start must come before the end", s));
+        }
+        Ok(ClipTime { start, end })
+    }
+}
+
+/// Indices of the frames captured at `times` that show during `clip`
+///
+/// The frame on screen when the clip starts is included even if it was
+/// captured earlier, as happens with `on_change` recordings.
+pub fn clip_range(times: &[Duration], clip: ClipTime) -> std::ops::Range<usize> {
+    let first = times.partition_point(|&time| time <= clip.start).saturating_sub(1);
+    let last = times.partition_point(|&time| time < clip.end);
+    first..last.max(first)
+}
+
+/// Range of random extra delay added to the wait
+///
+/// Parsed from `MIN-MAX` (e.g. `500ms-2s`) or a single duration, which is the
+/// maximum of a range starting at zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Jitter {
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl Jitter {
+    /// Pick a random delay from the range
+    pub fn sample(&self) -> Duration {
+        rand::rng().random_range(self.min..=self.max)
+    }
+}
+
+impl std::str::FromStr for Jitter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (min, max) = match s.split_once('-') {
+            Some((min, max)) => (parse_duration(min)?, parse_duration(max)?),
+            None => (Duration::ZERO, parse_duration(s)?),
+        };
+        if min > max {
+            return Err(anyhow::anyhow!("Invalid jitter range {}. The minimum is larger than the maximum", s));
+        }
+        Ok(Jitter { min, max })
+    }
+}
+
+/// Viewport size representation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ViewportSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl std::str::FromStr for ViewportSize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split('x').collect();
+        if parts.len() != 2 {
+            return Err(anyhow::anyhow!("Invalid viewport size format. Expected WIDTHxHEIGHT"));
+        }
+
+        let width = parts[0].parse::<u32>()
+            .context("Failed to parse viewport width")?;
+        let height = parts[1].parse::<u32>()
+            .context("Failed to parse viewport height")?;
+
+        Ok(ViewportSize { width, height })
+    }
+}
+
+/// Screen position of the browser window's top-left corner, e.g. `1920,0`
+///
+/// Only a headful browser (see `display`) has a window to place; coordinates
+/// may be negative for monitors left of or above the primary one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WindowPosition {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl std::str::FromStr for WindowPosition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (x, y) = s.split_once(',')
+            .context("Invalid window position format. Expected X,Y")?;
+        let x = x.trim().parse::<i32>()
+            .context("Failed to parse window x coordinate")?;
+        let y = y.trim().parse::<i32>()
+            .context("Failed to parse window y coordinate")?;
+
+        Ok(WindowPosition { x, y })
+    }
+}
+
+/// Paper size for PDF export, in centimetres
+pub struct PaperSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl std::str::FromStr for PaperSize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = match s.to_lowercase().as_str() {
+            "letter" => (21.59, 27.94),
+            "legal" => (21.59, 35.56),
+            "tabloid" => (27.94, 43.18),
+            "a3" => (29.7, 42.0),
+            "a4" => (21.0, 29.7),
+            "a5" => (14.8, 21.0),
+            other => {
+                let parts: Vec<&str> = other.split('x').collect();
+                if parts.len() != 2 {
+                    return Err(anyhow::anyhow!(
+                        "Invalid paper size. Expected letter, legal, tabloid, a3, a4, a5 or WIDTHxHEIGHT in cm"
+                    ));
+                }
+
+                let width = parts[0].parse::<f64>()
+                    .context("Failed to parse paper width")?;
+                let height = parts[1].parse::<f64>()
+                    .context("Failed to parse paper height")?;
+                (width, height)
+            }
+        };
+
+        if width <= 0.0 || height <= 0.0 {
+            return Err(anyhow::anyhow!("Paper dimensions must be positive"));
+        }
+
+        Ok(PaperSize { width, height })
+    }
+}
+
+/// Path to a (possibly nested) iframe
+///
+/// A value made only of digits and dots (e.g. `0.1`) is a path of frame indices,
+/// outermost first. Anything else is a CSS selector for a single iframe.
+pub enum FramePath {
+    Indices(Vec<u16>),
+    Selector(String),
+}
+
+impl std::str::FromStr for FramePath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(anyhow::anyhow!("Frame path must not be empty"));
+        }
+
+        if s.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            let indices = s.split('.')
+                .map(|part| part.parse::<u16>())
+                .collect::<Result<Vec<_>, _>>()
+                .context("Invalid frame index path. Expected e.g. 0 or 0.1")?;
+            Ok(FramePath::Indices(indices))
+        } else {
+            Ok(FramePath::Selector(s.to_string()))
+        }
+    }
+}
+
+/// Part of the viewport a screenshot is cropped to
+enum ScreenshotRegion {
+    Frame(FramePath),
+    /// The element matching a CSS selector
+    Element(String),
+    /// From the top of the first selector's element to the bottom of the second's
+    Between(String, String),
+}
+
+/// Parse the region to capture, if the screenshot is limited to one
+fn screenshot_region(options: &CaptureOptions) -> Result<Option<ScreenshotRegion>> {
+    match (&options.frame, &options.selector, &options.clip_between) {
+        (Some(frame), None, None) => Ok(Some(ScreenshotRegion::Frame(frame.parse()?))),
+        (None, Some(selector), None) => Ok(Some(ScreenshotRegion::Element(selector.clone()))),
+        (None, None, Some((start, end))) => Ok(Some(ScreenshotRegion::Between(start.clone(), end.clone()))),
+        (None, None, None) => Ok(None),
+        _ => Err(anyhow::anyhow!("A capture can be limited to a frame, an element or the region between two selectors, but only one of them")),
+    }
+}
+
+/// Check that every match of a selector can be captured into its own file
+fn check_each(options: &CaptureOptions, region: Option<&ScreenshotRegion>) -> Result<()> {
+    if !options.each {
+        return Ok(());
+    }
+    if !matches!(region, Some(ScreenshotRegion::Element(_))) {
+        return Err(anyhow::anyhow!("Capturing each match needs a CSS selector"));
+    }
+    if options.is_recording || options.pdf.is_some() {
+        return Err(anyhow::anyhow!("Only screenshots can capture each match of a selector"));
+    }
+    if options.output_path.to_str() == Some("-") {
+        return Err(anyhow::anyhow!("Capturing each match writes numbered files, so it cannot write to stdout"));
+    }
+    Ok(())
+}
+
+/// What [`expand_env`] does with a variable that isn't set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingVar {
+    /// Fail, naming the variable
+    #[default]
+    Error,
+    /// Replace it with nothing
+    Empty,
+}
+
+impl std::str::FromStr for MissingVar {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(MissingVar::Error),
+            "empty" => Ok(MissingVar::Empty),
+            _ => Err(anyhow::anyhow!("Invalid handling of unset variables. Expected error or empty")),
+        }
+    }
+}
+
+/// Replace every `${NAME}` in `text` with the value `lookup` gives for `NAME`
+///
+/// Only names made of letters, digits and underscores (not starting with a
+/// digit) are expanded, so other `${...}`, such as JavaScript template
+/// expressions, are left alone; `$${` is written as a literal `${`.
+pub fn expand_env(text: &str, missing: MissingVar, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+    let mut expanded = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        // `$${` escapes an expansion
+        if rest[..start].ends_with('$') {
+            expanded.push_str(&rest[..start - 1]);
+            expanded.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let name = after.find('}').map(|end| &after[..end]).filter(|name| {
+            name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+        let Some(name) = name else {
+            expanded.push_str("${");
+            rest = after;
+            continue;
+        };
+        match (lookup(name), missing) {
+            (Some(value), _) => expanded.push_str(&value),
+            (None, MissingVar::Empty) => {}
+            (None, MissingVar::Error) => return Err(anyhow::anyhow!("Environment variable {} is not set", name)),
+        }
+        rest = &after[name.len() + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// The JavaScript to run before capture, with environment variables expanded if requested
+fn capture_js(options: &CaptureOptions) -> Result<Option<String>> {
+    match (&options.js, options.expand_env) {
+        (Some(js), Some(missing)) => Ok(Some(expand_env(js, missing, |name| std::env::var(name).ok())?)),
+        (js, None) => Ok(js.clone()),
+        (None, _) => Ok(None),
+    }
+}
+
+/// Where to scroll the page before capturing
+///
+/// A non-negative number of pixels (optionally suffixed with `px`) scrolls the
+/// window to that vertical offset. Anything else is a CSS selector for an
+/// element to scroll into view.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScrollTarget {
+    Pixels(u64),
+    Selector(String),
+}
+
+impl std::str::FromStr for ScrollTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(anyhow::anyhow!("Scroll target must not be empty"));
+        }
+
+        let number = s.strip_suffix("px").unwrap_or(s);
+        if let Ok(offset) = number.parse::<i64>() {
+            if offset < 0 {
+                return Err(anyhow::anyhow!("Scroll offset must not be negative"));
+            }
+            Ok(ScrollTarget::Pixels(offset as u64))
+        } else {
+            Ok(ScrollTarget::Selector(s.to_string()))
+        }
+    }
+}
+
+/// Computed styles to read from the first element matching a CSS selector
+///
+/// Written as `SELECTOR=PROP,PROP,...`, e.g. `h1=color,font-size`, or
+/// `SELECTOR=*` for every computed property. The selector ends at the last
+/// `=`, so attribute selectors such as `input[type=text]` work.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleQuery {
+    pub selector: String,
+    /// Properties to read; empty for all of them
+    pub properties: Vec<String>,
+}
+
+impl std::str::FromStr for StyleQuery {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow::anyhow!("Invalid styles {}. Expected SELECTOR=PROP,PROP,... or SELECTOR=*", s);
+        let (selector, properties) = s.rsplit_once('=').ok_or_else(invalid)?;
+        let selector = selector.trim();
+        if selector.is_empty() {
+            return Err(invalid());
+        }
+        
+        let properties = properties.trim();
+        let properties = if properties == "*" {
+            Vec::new()
+        } else {
+            let properties: Vec<String> = properties.split(',').map(|p| p.trim().to_lowercase()).collect();
+            let valid = |p: &String| !p.is_empty() && p.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+            if !properties.iter().all(valid) {
+                return Err(invalid());
+            }
+            properties
+        };
+        Ok(StyleQuery { selector: selector.to_string(), properties })
+    }
+}
+
+/// A Chrome preference, from `--pref`
+///
+/// Written as `KEY=VALUE`, where dots in the key separate the levels of
+/// Chrome's nested preferences, e.g. `profile.password_manager_enabled=false`.
+/// Values that are valid JSON (`true`, `2`, `"text"`, `[...]`) are used as
+/// such, and anything else as a string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChromePref {
+    pub key: String,
+    pub value: serde_json::Value,
+}
+
+impl std::str::FromStr for ChromePref {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = s.split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Invalid pref {}. Expected KEY=VALUE", s))?;
+        let key = key.trim();
+        if key.split('.').any(|part| part.is_empty()) {
+            return Err(anyhow::anyhow!("Invalid pref {}. Expected a key such as profile.password_manager_enabled", s));
+        }
+        let value = serde_json::from_str(value.trim())
+            .unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+        Ok(ChromePref { key: key.to_string(), value })
+    }
+}
+
+/// A font family rendered with another font, written `FAMILY=REPLACEMENT`
+///
+/// The replacement is looked up by name among the fonts installed on the
+/// machine, e.g. `Brand Sans=DejaVu Sans`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontSubstitute {
+    pub family: String,
+    pub replacement: String,
+}
+
+impl std::str::FromStr for FontSubstitute {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (family, replacement) = s.split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Invalid font substitute {}. Expected FAMILY=REPLACEMENT", s))?;
+        let unquote = |name: &str| name.trim().trim_matches(|c| c == '"' || c == '\'').trim().to_string();
+        let (family, replacement) = (unquote(family), unquote(replacement));
+        if family.is_empty() || replacement.is_empty() {
+            return Err(anyhow::anyhow!("Invalid font substitute {}. Expected FAMILY=REPLACEMENT", s));
+        }
+        Ok(FontSubstitute { family, replacement })
+    }
+}
+
+/// Nest `prefs` into the preferences object ChromeDriver expects
+///
+/// A key given twice takes its last value. Setting both a preference and
+/// one inside it, such as `a=1` and `a.b=2`, is an error.
+pub fn chrome_prefs(prefs: &[ChromePref]) -> Result<serde_json::Map<String, serde_json::Value>> {
+    let mut root = serde_json::Map::new();
+    for pref in prefs {
+        let mut parts: Vec<&str> = pref.key.split('.').collect();
+        let leaf = parts.pop().unwrap_or_default();
+        let mut level = &mut root;
+        for part in parts {
+            let entry = level.entry(part.to_string())
+                .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+            level = entry.as_object_mut()
+                .ok_or_else(|| anyhow::anyhow!("Pref {} is inside {}, which is set to a value", pref.key, part))?;
+        }
+        if level.get(leaf).is_some_and(|value| value.is_object()) {
+            return Err(anyhow::anyhow!("Pref {} cannot be set to a value; other prefs are inside it", pref.key));
+        }
+        level.insert(leaf.to_string(), pref.value.clone());
+    }
+    Ok(root)
+}
+
+/// A host name to allow or block, optionally covering its subdomains
+///
+/// `example.com` matches only that host, while `*.example.com` matches any
+/// subdomain of it (but not `example.com` itself).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainPattern {
+    host: String,
+    subdomains: bool,
+}
+
+impl DomainPattern {
+    /// Whether `host` is covered by this pattern
+    pub fn matches(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_lowercase();
+        if self.subdomains {
+            host.strip_suffix(&self.host).is_some_and(|prefix| prefix.ends_with('.') && prefix.len() > 1)
+        } else {
+            host == self.host
+        }
+    }
+}
+
+impl std::fmt::Display for DomainPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.subdomains {
+            write!(f, "*.{}", self.host)
+        } else {
+            write!(f, "{}", self.host)
+        }
+    }
+}
+
+impl std::str::FromStr for DomainPattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pattern = s.trim().trim_end_matches('.').to_lowercase();
+        let (host, subdomains) = match pattern.strip_prefix("*.") {
+            Some(host) => (host.to_string(), true),
+            None => (pattern, false),
+        };
+
+        let valid_label = |label: &str| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        };
+        if !host.split('.').all(valid_label) {
+            return Err(anyhow::anyhow!("Invalid domain {}. Expected e.g. example.com or *.example.com", s));
+        }
+        Ok(DomainPattern { host, subdomains })
+    }
+}
+
+/// Hosts the browser may contact during a capture
+///
+/// Enforced with Chrome's host resolver rules: hosts that are not permitted
+/// fail to resolve straight away, so the page never waits on them. Pages and
+/// resources addressed by IP address are not resolved and so are never blocked.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DomainFilter {
+    /// Only these hosts may be contacted
+    Allow(Vec<DomainPattern>),
+    /// These hosts may not be contacted
+    Deny(Vec<DomainPattern>),
+}
+
+impl DomainFilter {
+    /// Whether requests to `host` are permitted
+    pub fn permits(&self, host: &str) -> bool {
+        match self {
+            DomainFilter::Allow(patterns) => patterns.iter().any(|p| p.matches(host)),
+            DomainFilter::Deny(patterns) => !patterns.iter().any(|p| p.matches(host)),
+        }
+    }
+
+    /// Value of Chrome's `--host-resolver-rules` switch enforcing this filter
+    pub fn resolver_rules(&self) -> String {
+        match self {
+            DomainFilter::Allow(patterns) => std::iter::once("MAP * ~NOTFOUND".to_string())
+                .chain(patterns.iter().map(|p| format!("EXCLUDE {}", p)))
+                .collect::<Vec<_>>()
+                .join(", "),
+            DomainFilter::Deny(patterns) => patterns.iter()
+                .map(|p| format!("MAP {} ~NOTFOUND", p))
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+}
+
+/// A single step of a scripted flow run before the capture
+///
+/// Flow files are JSON arrays of steps, for example:
+///
+/// ```json
+/// [
+///   { "action": "goto", "url": "https://example.com/login" },
+///   { "action": "fill", "selector": "#user", "value": "me" },
+///   { "action": "click", "selector": "button[type=submit]" },
+///   { "action": "wait_for", "selector": "#dashboard", "timeout": 10 }
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum FlowStep {
+    /// Navigate to a URL
+    Goto { url: String },
+    /// Type a value into the element matching a CSS selector
+    Fill { selector: String, value: String },
+    /// Click the element matching a CSS selector
+    Click { selector: String },
+    /// Sleep for a number of seconds
+    Wait { seconds: f64 },
+    /// Wait until an element matching a CSS selector exists (timeout in seconds)
+    WaitFor {
+        selector: String,
+        #[serde(default = "default_wait_for_timeout")]
+        timeout: u64,
+    },
+}
+
+/// How long `--wait-gone` waits for its element to disappear
+const WAIT_GONE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long `--wait-min-chars` waits for the page's text to appear
+const WAIT_MIN_CHARS_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long `--wait-responses` waits for the page's responses to complete
+const WAIT_RESPONSES_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long `--ready-state` waits for the document to reach its state
+const READY_STATE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Screenshots tried for a recording frame before a broken one fails the recording
+const FRAME_ATTEMPTS: u32 = 3;
+
+/// How long `--stable` waits for the page to stop changing
+const STABLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn default_wait_for_timeout() -> u64 {
+    10
+}
+
+/// Load a flow file containing a JSON array of [`FlowStep`]s
+pub fn load_flow(path: &str) -> Result<Vec<FlowStep>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read flow file {}", path))?;
+    let steps = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse flow file {}", path))?;
+    Ok(steps)
+}
+
+/// One capture in a manifest file
+///
+/// Manifest files are JSON arrays of entries, each overriding the command line
+/// options for its own capture:
+///
+/// ```json
+/// [
+///   { "url": "https://example.com", "output": "home.png" },
+///   { "url": "https://example.com/pricing", "size": "1920x1080", "wait": "2s", "selector": "#plans" }
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestEntry {
+    pub url: String,
+    /// Output file (default: named after the URL, see [`file_name_for_url`])
+    pub output: Option<String>,
+    /// Viewport size as WIDTHxHEIGHT
+    #[serde(alias = "viewport")]
+    pub size: Option<String>,
+    /// Wait before capture, as seconds or a duration string such as `500ms`
+    #[serde(default, deserialize_with = "deserialize_wait")]
+    pub wait: Option<Duration>,
+    /// Capture only the element matching this CSS selector
+    pub selector: Option<String>,
+    /// JavaScript to execute before capture
+    pub js: Option<String>,
+}
+
+impl ManifestEntry {
+    /// Capture options for this entry, taking anything it doesn't set from `base`
+    ///
+    /// Entries without an `output` are named after their URL, in the directory
+    /// and with the extension of `base`'s output path.
+    pub fn to_options(&self, base: &CaptureOptions) -> Result<CaptureOptions> {
+        self.build_options(base, None)
+    }
+    
+    /// Like [`ManifestEntry::to_options`], but naming an entry without an
+    /// `output` by `template`, as the `index`th entry of the manifest
+    pub fn to_templated_options(&self, base: &CaptureOptions, template: &OutputTemplate, index: usize) -> Result<CaptureOptions> {
+        self.build_options(base, Some((template, index)))
+    }
+    
+    fn build_options(&self, base: &CaptureOptions, template: Option<(&OutputTemplate, usize)>) -> Result<CaptureOptions> {
+        let url = Url::parse(&self.url)
+            .with_context(|| format!("Failed to parse manifest URL {}", self.url))?;
+        
+        let mut options = base.clone();
+        if let Some(size) = &self.size {
+            options.size = size.clone();
+        }
+        options.output_path = match (&self.output, template) {
+            (Some(output), _) => PathBuf::from(output),
+            (None, Some((template, index))) => template.expand(&url, &options.size, index, chrono::Local::now().date_naive()),
+            (None, None) => {
+                let extension = base.output_path.extension().and_then(|e| e.to_str()).unwrap_or("png");
+                base.output_path.with_file_name(file_name_for_url(&url, extension))
+            }
+        };
+        options.url = self.url.clone();
+        if base.text_path.is_some() {
+            // Each entry saves its text next to its own output
+            options.text_path = Some(options.output_path.with_extension("txt"));
+        }
+        if base.perf_path.is_some() {
+            options.perf_path = Some(options.output_path.with_extension("perf.json"));
+        }
+        if let Some(wait) = self.wait {
+            options.wait = wait;
+        }
+        if let Some(selector) = &self.selector {
+            // The entry's own selector replaces any region set for the whole run
+            options.selector = Some(selector.clone());
+            options.frame = None;
+            options.clip_between = None;
+        }
+        if let Some(js) = &self.js {
+            options.js = Some(js.clone());
+        }
+        Ok(options)
+    }
+}
+
+fn deserialize_wait<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum Wait {
+        Seconds(f64),
+        Text(String),
+    }
+    
+    let wait = match <Option<Wait> as serde::Deserialize>::deserialize(deserializer)? {
+        Some(Wait::Seconds(secs)) => Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom)?,
+        Some(Wait::Text(text)) => parse_duration(&text).map_err(serde::de::Error::custom)?,
+        None => return Ok(None),
+    };
+    Ok(Some(wait))
+}
+
+/// Load a manifest file containing a JSON array of [`ManifestEntry`]s
+pub fn load_manifest(path: &str) -> Result<Vec<ManifestEntry>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read manifest file {}", path))?;
+    let entries = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse manifest file {}", path))?;
+    Ok(entries)
+}
+
+// User agent strings for rotation
+const USER_AGENTS: [&str; 2] = [
+    // Chrome on Windows
+    "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/123.0.0.0 Safari/537.36",
+    // Chrome on Mac
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/123.0.0.0 Safari/537.36",
+];
+
+/// User agents listed in a `--user-agents-file`, one per line, with blank lines skipped
+pub fn parse_user_agents(content: &str) -> Vec<String> {
+    content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// User agents to pick from for `options`; empty means the built-in ones
+///
+/// A file that can't be read or lists no user agents falls back to the
+/// built-in ones with a warning rather than failing the capture.
+fn user_agent_pool(options: &CaptureOptions) -> Vec<String> {
+    let Some(path) = &options.user_agents_file else {
+        return Vec::new();
+    };
+    let is_piped = options.output_path.to_str() == Some("-");
+    match fs::read_to_string(path) {
+        Ok(content) => {
+            let user_agents = parse_user_agents(&content);
+            if user_agents.is_empty() && !is_piped {
+                eprintln!("{} {}", "!".yellow(), format!("{} lists no user agents; using the built-in ones", path.display()).yellow());
+            }
+            user_agents
+        }
+        Err(e) => {
+            if !is_piped {
+                eprintln!("{} {}", "!".yellow(), format!("Could not read {}: {}; using the built-in user agents", path.display(), e).yellow());
+            }
+            Vec::new()
+        }
+    }
+}
+
+/// Pick a user agent at random from `pool`, or from the built-in ones if it is empty
+fn pick_user_agent(pool: &[String]) -> &str {
+    let mut rng = rand::rng();
+    if pool.is_empty() {
+        USER_AGENTS[rng.random_range(0..USER_AGENTS.len())]
+    } else {
+        &pool[rng.random_range(0..pool.len())]
+    }
+}
+
+// ChromeDriver management
+pub struct ChromeDriverManager {
+    process: Option<Child>,
+    port: u16,
+    debug: bool,
+    display: Option<String>,
+}
+
+impl ChromeDriverManager {
+    pub fn new(port: u16, debug: bool) -> Self {
+        ChromeDriverManager {
+            process: None,
+            port,
+            debug,
+            display: None,
+        }
+    }
+
+    /// Run ChromeDriver, and the Chrome it launches, on the given X display
+    pub fn with_display(mut self, display: Option<&str>) -> Self {
+        self.display = display.map(str::to_string);
+        self
+    }
+
+    /// Whether a ChromeDriver on the port is ready to create sessions
+    ///
+    /// An open port isn't enough: ChromeDriver accepts connections a moment
+    /// before it can start sessions, and the port may belong to something
+    /// else entirely. So this asks `/status` and goes by its `ready` field.
+    pub fn is_running(&self) -> bool {
+        driver_status(self.port).unwrap_or(false)
+    }
+
+    pub fn start(&mut self) -> Result<()> {
+        if TcpStream::connect(format!("127.0.0.1:{}", self.port)).is_ok() {
+            // Another ChromeDriver may still be starting up
+            self.wait_until_ready().with_context(|| format!(
+                "Port {} is in use, but not by a ChromeDriver ready to start sessions", self.port
+            ))?;
+            if self.debug {
+                println!("ChromeDriver is already running on port {}", self.port);
+            }
+            if let Some(display) = &self.display {
+                eprintln!("{} {}", "!".yellow(), format!("ChromeDriver is already running, so it will not use display {}", display).yellow());
+            }
+            return Ok(());
+        }
+
+        if self.debug {
+            println!("Starting ChromeDriver on port {}...", self.port);
+        }
+        
+        let mut command = Command::new("chromedriver");
+        command.arg(format!("--port={}", self.port));
+        if let Some(display) = &self.display {
+            command.env("DISPLAY", display);
+        }
+        
+        let process = if self.debug {
+            command
+                .spawn()
+                .context("Failed to start ChromeDriver. Make sure it's installed.")?
+        } else {
+            command
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .context("Failed to start ChromeDriver. Make sure it's installed.")?
+        };
+
+        self.process = Some(process);
+
+        self.wait_until_ready()
+            .context("Timed out waiting for ChromeDriver to start")?;
+
+        if self.debug {
+            println!("ChromeDriver started successfully");
+        }
+        Ok(())
+    }
+
+    /// Poll `/status` until ChromeDriver reports itself ready, for up to 5 seconds
+    fn wait_until_ready(&self) -> Result<()> {
+        let start_time = std::time::Instant::now();
+        while !self.is_running() {
+            if start_time.elapsed() > Duration::from_secs(5) {
+                return Err(anyhow::anyhow!("ChromeDriver on port {} did not become ready within 5 seconds", self.port));
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        Ok(())
+    }
+}
+
+/// Ask the WebDriver server on `port` whether it can create sessions
+///
+/// Each step is bounded by a short timeout, so a process that accepts the
+/// connection but never answers counts as not ready rather than hanging.
+fn driver_status(port: u16) -> Result<bool> {
+    const STATUS_TIMEOUT: Duration = Duration::from_secs(1);
+    let address = std::net::SocketAddr::from(([127, 0, 0, 1], port));
+    let mut stream = TcpStream::connect_timeout(&address, STATUS_TIMEOUT)?;
+    stream.set_read_timeout(Some(STATUS_TIMEOUT))?;
+    stream.set_write_timeout(Some(STATUS_TIMEOUT))?;
+    // HTTP/1.0, so the body comes whole rather than chunked
+    write!(stream, "GET /status HTTP/1.0\r\nHost: 127.0.0.1:{}\r\n\r\n", port)?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    parse_driver_status(&response)
+}
+
+/// The `ready` field of a raw HTTP response to a WebDriver `/status` request
+///
+/// Anything but a `200` with a JSON body reporting `"ready": true` counts as
+/// not ready.
+pub fn parse_driver_status(response: &[u8]) -> Result<bool> {
+    let split = response.windows(4).position(|w| w == b"\r\n\r\n")
+        .context("Incomplete HTTP response")?;
+    let (head, body) = (&response[..split], &response[split + 4..]);
+    let status_line = head.split(|&b| b == b'\r').next().unwrap_or_default();
+    if String::from_utf8_lossy(status_line).split_whitespace().nth(1) != Some("200") {
+        return Ok(false);
+    }
+    let status: serde_json::Value = serde_json::from_slice(body)
+        .context("The status response is not JSON")?;
+    Ok(status["value"]["ready"].as_bool().unwrap_or(false))
+}
+
+impl Drop for ChromeDriverManager {
+    fn drop(&mut self) {
+        if let Some(mut process) = self.process.take() {
+            if self.debug {
+                println!("Stopping ChromeDriver...");
+            }
+            let _ = process.kill();
+            let _ = process.wait();
+            if self.debug {
+                println!("ChromeDriver stopped");
+            }
+        }
+    }
+}
+
+/// RAII guard around a WebDriver session and the ChromeDriver serving it
+///
+/// The session is quit before ChromeDriver is stopped, including on error and
+/// panic paths, so the headless Chrome it spawned isn't left running.
+pub struct BrowserSession {
+    driver: Option<WebDriver>,
+    _chromedriver: ChromeDriverManager,
+    /// Borrowed from `weblook serve`, so left open rather than quit
+    warm: bool,
+    /// The session's BiDi events, when it was launched with `bidi` set
+    events: Option<BidiEvents>,
+}
+
+impl BrowserSession {
+    pub fn new(driver: WebDriver, chromedriver: ChromeDriverManager) -> Self {
+        BrowserSession {
+            driver: Some(driver),
+            _chromedriver: chromedriver,
+            warm: false,
+            events: None,
+        }
+    }
+
+    /// Wrap a warm session (see [`crate::warm`]), which is left open when done
+    pub fn warm(driver: WebDriver, chromedriver: ChromeDriverManager) -> Self {
+        BrowserSession {
+            driver: Some(driver),
+            _chromedriver: chromedriver,
+            warm: true,
+            events: None,
+        }
+    }
+
+    /// Follow the session's BiDi events while it is open
+    pub fn with_events(mut self, events: BidiEvents) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// The session's BiDi events, if it is following them
+    pub fn events(&self) -> Option<&BidiEvents> {
+        self.events.as_ref()
+    }
+
+    /// Whether this is a warm session borrowed from `weblook serve`
+    pub fn is_warm(&self) -> bool {
+        self.warm
+    }
+
+    /// Quit the session, reporting any error
+    pub async fn quit(mut self) -> Result<()> {
+        if let Some(driver) = self.driver.take() {
+            if self.warm {
+                let _ = driver.leak();
+            } else {
+                driver.quit().await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::ops::Deref for BrowserSession {
+    type Target = WebDriver;
+
+    fn deref(&self) -> &WebDriver {
+        self.driver.as_ref().expect("browser session already quit")
+    }
+}
+
+impl Drop for BrowserSession {
+    fn drop(&mut self) {
+        let Some(driver) = self.driver.take() else {
+            return;
+        };
+        if self.warm {
+            let _ = driver.leak();
+            return;
+        }
+
+        // Best-effort quit; wait for it when the runtime allows blocking so that
+        // ChromeDriver is only stopped once the browser is gone
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(|| {
+                    let _ = handle.block_on(driver.quit());
+                });
+            }
+            // thirtyfour quits sessions dropped without quit() on its own
+            _ => drop(driver),
+        }
+    }
+}
+
+/// Errors returned by the public capture API
+///
+/// Every variant but `Timeout` wraps the underlying error, so the full cause
+/// chain is still available through [`std::error::Error::source`].
+#[derive(Debug)]
+pub enum CaptureError {
+    /// ChromeDriver could not be started, or no browser session could be created
+    DriverNotFound(anyhow::Error),
+    /// Loading the page, or running the flow, JavaScript or scrolling on it, failed
+    Navigation(anyhow::Error),
+    /// The capture did not finish within the maximum wait
+    Timeout(Duration),
+    /// The captured image, animation or PDF could not be encoded
+    Encode(anyhow::Error),
+    /// Reading or writing a file failed
+    Io(anyhow::Error),
+    /// An option could not be parsed or is out of range
+    InvalidOptions(anyhow::Error),
+    /// The browser failed while taking the capture
+    Browser(anyhow::Error),
+}
+
+impl CaptureError {
+    /// Classify an error raised while taking or writing the capture
+    fn from_output(e: anyhow::Error) -> Self {
+        if e.chain().any(|cause| cause.is::<io::Error>()) {
+            CaptureError::Io(e)
+        } else if e.chain().any(|cause| cause.is::<thirtyfour::error::WebDriverError>()) {
+            CaptureError::Browser(e)
+        } else {
+            CaptureError::Encode(e)
+        }
+    }
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureError::Timeout(max_wait) => write!(f, "Capture did not finish within the maximum wait of {:?}", max_wait),
+            CaptureError::DriverNotFound(e)
+            | CaptureError::Navigation(e)
+            | CaptureError::Encode(e)
+            | CaptureError::Io(e)
+            | CaptureError::InvalidOptions(e)
+            | CaptureError::Browser(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CaptureError::Timeout(_) => None,
+            CaptureError::DriverNotFound(e)
+            | CaptureError::Navigation(e)
+            | CaptureError::Encode(e)
+            | CaptureError::Io(e)
+            | CaptureError::InvalidOptions(e)
+            | CaptureError::Browser(e) => e.chain().nth(1),
+        }
+    }
+}
+
+/// Point at which a capture's maximum wait runs out, if it has one
+#[derive(Debug, Clone, Copy)]
+struct Deadline(Option<(Instant, Duration)>);
+
+impl Deadline {
+    fn after(max_wait: Option<Duration>) -> Self {
+        Deadline(max_wait.map(|max_wait| (Instant::now() + max_wait, max_wait)))
+    }
+    
+    /// Run `step`, failing with [`CaptureError::Timeout`] if it is still going at the deadline
+    async fn run<T>(self, step: impl std::future::Future<Output = Result<T, CaptureError>>) -> Result<T, CaptureError> {
+        match self.0 {
+            // Dropping the timed-out step leaves the browser to its session guard
+            Some((at, max_wait)) => tokio::time::timeout_at(at, step).await
+                .map_err(|_| CaptureError::Timeout(max_wait))?,
+            None => step.await,
+        }
+    }
+}
+
+/// Main capture function that handles both screenshots and recordings
+pub async fn perform_capture(options: CaptureOptions) -> Result<CaptureInfo, CaptureError> {
+    let deadline = Deadline::after(options.max_wait);
+    
+    // Determine if we're outputting to stdout
+    let is_piped = options.output_path.to_str() == Some("-");
+    
+    let plan = plan_capture(&options)?;
+    let driver = deadline.run(start_browser(&options, &plan, is_piped)).await?;
+    
+    let reused = driver.is_warm();
+    let info = capture_before(deadline, &driver, driver.events(), &options, &plan, reused, is_piped).await?;
+    
+    // Clean up
+    driver.quit().await
+        .map_err(CaptureError::Browser)?;
+    
+    // ChromeDriver is stopped when the session guard is dropped
+    Ok(info)
+}
+
+/// Start ChromeDriver and a browser for `options`, or attach to the warm session
+async fn start_browser(options: &CaptureOptions, plan: &CapturePlan, is_piped: bool) -> Result<BrowserSession, CaptureError> {
+    // Start ChromeDriver if not already running
+    // Reuse a warm session when it was launched with everything this capture needs
+    let launch_defaults = LaunchSettings::from_options(options) == LaunchSettings::default();
+    let warm = if options.reuse_session && launch_defaults {
+        attach_warm_session(options.debug).await
+    } else {
+        None
+    };
+    
+    let chromedriver_port = 9515;
+    let mut chromedriver = ChromeDriverManager::new(chromedriver_port, options.debug)
+        .with_display(options.display.as_deref());
+    if warm.is_none() {
+        chromedriver.start()
+            .map_err(CaptureError::DriverNotFound)?;
+    }
+
+    if !is_piped && !options.debug {
+        eprintln!("{}", "Starting WebLook...".bright_cyan());
+    }
+    announce_capture(options, plan, is_piped);
+    
+    // Set up WebDriver; the session guard quits it on every exit path
+    let driver = match warm {
+        Some(driver) => {
+            if !is_piped {
+                eprintln!("{} {}", "•".yellow(), "Reusing the warm browser session from weblook serve".yellow());
+            }
+            let driver = BrowserSession::warm(driver, chromedriver);
+            resize_window(&driver, options, plan.viewport).await?;
+            driver
+        }
+        None => {
+            let flags = LaunchFlags::from_options(options);
+            if flags.no_sandbox && !options.no_sandbox && !is_piped {
+                eprintln!("{} {}", "!".yellow(), "Running as root, so Chrome is started without its sandbox".yellow());
+            }
+            if flags.ignore_cert_errors && !is_piped {
+                eprintln!("{} {}", "!".yellow(), "Ignoring TLS certificate errors; the page's identity is not verified".yellow());
+            }
+            let driver = setup_webdriver(plan.viewport, options.window_position, chromedriver_port, flags, plan.domain_filter.as_ref(), &user_agent_pool(options), &plan.prefs).await
+                .map_err(CaptureError::DriverNotFound)?;
+            let driver = BrowserSession::new(driver, chromedriver);
+            if options.bidi {
+                let events = subscribe_events(&driver, chromedriver_port).await
+                    .map_err(CaptureError::DriverNotFound)?;
+                driver.with_events(events)
+            } else {
+                driver
+            }
+        }
+    };
+    Ok(driver)
+}
+
+/// Run [`capture_page`] until `deadline`, dumping diagnostics if it runs out
+async fn capture_before(deadline: Deadline, driver: &WebDriver, events: Option<&BidiEvents>, options: &CaptureOptions, plan: &CapturePlan, reused: bool, is_piped: bool) -> Result<CaptureInfo, CaptureError> {
+    let result = deadline.run(capture_page(driver, events, options, plan, reused, is_piped)).await;
+    if let Err(CaptureError::Timeout(_)) = &result
+        && let Some(dir) = &options.on_timeout_dump
+    {
+        dump_diagnostics(driver, events, dir, is_piped).await;
+    }
+    result
+}
+
+/// Longest each diagnostics step may take, as a page that timed out may not respond
+const DUMP_STEP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Save a screenshot, the console log and the current URL to `dir`
+///
+/// This is best-effort: each file is fetched and written on its own, and any
+/// that cannot be is reported as a warning rather than an error.
+async fn dump_diagnostics(driver: &WebDriver, events: Option<&BidiEvents>, dir: &Path, is_piped: bool) {
+    if !is_piped {
+        eprintln!("{} {}", "!".yellow(), format!("Capture timed out; saving diagnostics to {}", dir.display()).yellow());
+    }
+    if let Err(e) = fs::create_dir_all(dir) {
+        if !is_piped {
+            eprintln!("{} {}", "!".yellow(), format!("Could not create {}: {}", dir.display(), e).yellow());
+        }
+        return;
+    }
+    
+    let results = [
+        dump_step(dir, "screenshot.png", async { Ok(driver.screenshot_as_png().await?) }).await,
+        dump_step(dir, "console.log", async { Ok(console_logs(driver, events).await?.into_bytes()) }).await,
+        dump_step(dir, "url.txt", async { Ok(format!("{}\n", driver.current_url().await?).into_bytes()) }).await,
+    ];
+    if is_piped {
+        return;
+    }
+    for result in results {
+        match result {
+            Ok(path) => eprintln!("{} {}", "✓".green(), format!("Saved {}", path.display()).bright_green()),
+            Err(e) => eprintln!("{} {}", "!".yellow(), format!("{:#}", e).yellow()),
+        }
+    }
+}
+
+/// Write the data `fetch` returns to `dir/name`, giving up after [`DUMP_STEP_TIMEOUT`]
+async fn dump_step(dir: &Path, name: &str, fetch: impl std::future::Future<Output = Result<Vec<u8>>>) -> Result<PathBuf> {
+    let data = tokio::time::timeout(DUMP_STEP_TIMEOUT, fetch).await
+        .map_err(|_| anyhow::anyhow!("The browser did not respond within {:?}", DUMP_STEP_TIMEOUT))
+        .and_then(|data| data)
+        .with_context(|| format!("Could not save {}", name))?;
+    let path = dir.join(name);
+    fs::write(&path, data)
+        .with_context(|| format!("Could not write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Everything parsed and checked from [`CaptureOptions`] before a browser is involved
+struct CapturePlan {
+    url: Url,
+    viewport: ViewportSize,
+    paper_size: Option<PaperSize>,
+    region: Option<ScreenshotRegion>,
+    scroll_to: Option<ScrollTarget>,
+    domain_filter: Option<DomainFilter>,
+    label_background: image::Rgba<u8>,
+    mask_color: image::Rgba<u8>,
+    recording: RecordingSettings,
+    js: Option<String>,
+    referer: Option<Url>,
+    prefs: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Parse and check `options`, so that mistakes are reported before a browser is started
+fn plan_capture(options: &CaptureOptions) -> Result<CapturePlan, CaptureError> {
+    // Parse URL
+    let url = Url::parse(&options.url).context("Failed to parse URL")
+        .map_err(CaptureError::InvalidOptions)?;
+
+    // Parse viewport size
+    let viewport = options.size.parse::<ViewportSize>()
+        .map_err(CaptureError::InvalidOptions)?;
+
+    // Parse paper size if exporting a PDF
+    let paper_size = options.pdf.as_deref().map(str::parse::<PaperSize>).transpose()
+        .map_err(CaptureError::InvalidOptions)?;
+
+    // Parse the iframe or selectors bounding the screenshot
+    let region = screenshot_region(options)
+        .map_err(CaptureError::InvalidOptions)?;
+    check_each(options, region.as_ref())
+        .map_err(CaptureError::InvalidOptions)?;
+    check_states(options)
+        .map_err(CaptureError::InvalidOptions)?;
+    check_resize_sequence(options)
+        .map_err(CaptureError::InvalidOptions)?;
+
+    // Check the X display looks like one
+    validate_display(options.display.as_deref())
+        .map_err(CaptureError::InvalidOptions)?;
+
+    // Check the Chrome binary exists before ChromeDriver tries to launch it
+    validate_chrome_binary(options.chrome_binary.as_deref())
+        .map_err(CaptureError::InvalidOptions)?;
+
+    // Parse scroll target if scrolling before capture
+    let scroll_to = options.scroll_to.as_deref().map(str::parse::<ScrollTarget>).transpose()
+        .map_err(CaptureError::InvalidOptions)?;
+
+    // Parse the domains the browser may contact
+    let domain_filter = domain_filter(options, &url)
+        .map_err(CaptureError::InvalidOptions)?;
+
+    // Parse label background colour
+    let label_background = label_background(options)
+        .map_err(CaptureError::InvalidOptions)?;
+
+    // Parse the colour to redact masked elements with
+    let mask_color = mask_color(options)
+        .map_err(CaptureError::InvalidOptions)?;
+
+    // Make sure the output format suits the capture
+    check_stdout_format(options)
+        .map_err(CaptureError::InvalidOptions)?;
+    check_transparency(options)
+        .map_err(CaptureError::InvalidOptions)?;
+    check_effects(options)
+        .map_err(CaptureError::InvalidOptions)?;
+    check_device_scale(options, viewport)
+        .map_err(CaptureError::InvalidOptions)?;
+
+    // Determine recording length and frame rates if recording
+    let recording = recording_settings(options)
+        .map_err(CaptureError::InvalidOptions)?;
+    
+    // Expand environment variables in the JavaScript
+    let js = capture_js(options)
+        .map_err(CaptureError::InvalidOptions)?;
+    
+    // Parse the referer to navigate with
+    let referer = parse_referer(options)
+        .map_err(CaptureError::InvalidOptions)?;
+    
+    // Nest the Chrome preferences to launch with
+    let prefs = chrome_prefs(&options.prefs)
+        .map_err(CaptureError::InvalidOptions)?;
+    
+    Ok(CapturePlan { url, viewport, paper_size, region, scroll_to, domain_filter, label_background, mask_color, recording, js, referer, prefs })
+}
+
+/// Parse `--referer`, which must be an absolute http or https URL
+fn parse_referer(options: &CaptureOptions) -> Result<Option<Url>> {
+    let Some(referer) = &options.referer else {
+        return Ok(None);
+    };
+    let url = Url::parse(referer).with_context(|| format!("Invalid referer {}", referer))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        anyhow::bail!("Invalid referer {}. Expected an http or https URL", referer);
+    }
+    Ok(Some(url))
+}
+
+/// Say what is about to be captured
+fn announce_capture(options: &CaptureOptions, plan: &CapturePlan, is_piped: bool) {
+    if is_piped {
+        return;
+    }
+    if let Some(warning) = output_format_warning(options) {
+        eprintln!("{} {}", "!".yellow(), warning.yellow());
+    }
+    if options.debug {
+        return;
+    }
+    if options.is_recording {
+        eprintln!("{} {}", "•".yellow(), format!("Recording {} for {} seconds", plan.url, plan.recording.duration_secs).yellow());
+    } else if plan.paper_size.is_some() {
+        eprintln!("{} {}", "•".yellow(), format!("Exporting PDF of {}", plan.url).yellow());
+    } else {
+        eprintln!("{} {}", "•".yellow(), format!("Taking screenshot of {}", plan.url).yellow());
+    }
+    std::io::stderr().flush().ok();
+}
+
+/// Move and resize the window of a session launched for an earlier capture
+async fn resize_window(driver: &WebDriver, options: &CaptureOptions, viewport: ViewportSize) -> Result<(), CaptureError> {
+    let position = options.window_position;
+    driver.set_window_rect(position.x as i64, position.y as i64, viewport.width, viewport.height).await
+        .map_err(|e| CaptureError::DriverNotFound(e.into()))
+}
+
+/// Resize the window so that the page's viewport is exactly `viewport`
+///
+/// `--window-size` and WebDriver size the whole window, which in a headful
+/// browser includes its tabs and toolbars, so pages get less room than asked
+/// for. The shortfall is measured and added to the window, a second time if
+/// the resize itself changed the browser's UI.
+async fn fit_viewport(driver: &WebDriver, position: WindowPosition, viewport: ViewportSize, is_piped: bool) -> Result<()> {
+    let mut inner = inner_size(driver).await?;
+    for _ in 0..2 {
+        if inner == viewport {
+            return Ok(());
+        }
+        let window = driver.get_window_rect().await?;
+        let width = (window.width + viewport.width as i64 - inner.width as i64).max(1) as u32;
+        let height = (window.height + viewport.height as i64 - inner.height as i64).max(1) as u32;
+        driver.set_window_rect(position.x as i64, position.y as i64, width, height).await?;
+        inner = inner_size(driver).await?;
+    }
+    if inner != viewport && !is_piped {
+        eprintln!("{} {}", "!".yellow(), format!(
+            "The viewport is {}x{} instead of {}x{}; the window could not be sized to fit",
+            inner.width, inner.height, viewport.width, viewport.height
+        ).yellow());
+    }
+    Ok(())
+}
+
+/// The size of the page's viewport, including any scrollbars
+async fn inner_size(driver: &WebDriver) -> Result<ViewportSize> {
+    let size = driver.execute("return [window.innerWidth, window.innerHeight];", Vec::new()).await?;
+    let (width, height): (u32, u32) = serde_json::from_value(size.json().clone())
+        .context("Unexpected viewport size from the page")?;
+    Ok(ViewportSize { width, height })
+}
+
+/// Load the page and take the capture described by `options` and `plan`
+///
+/// A `reused` session has its emulation state reset, in case an earlier
+/// capture changed it.
+async fn capture_page(driver: &WebDriver, events: Option<&BidiEvents>, options: &CaptureOptions, plan: &CapturePlan, reused: bool, is_piped: bool) -> Result<CaptureInfo, CaptureError> {
+    if options.exact_viewport {
+        fit_viewport(driver, options.window_position, plan.viewport, is_piped).await
+            .map_err(|e| CaptureError::Browser(e.context("Failed to fit the window to the viewport")))?;
+    }
+    
+    if options.dpr.is_some() || reused {
+        set_device_scale(driver, options.dpr).await
+            .map_err(|e| CaptureError::Browser(e.context("Failed to set the device pixel ratio")))?;
+    }
+    
+    // Emulate the requested media type
+    if options.emulate_media.is_some() || options.reduced_motion || reused {
+        emulate_media(driver, options.emulate_media, options.reduced_motion).await
+            .map_err(|e| CaptureError::Browser(e.context("Failed to emulate the media type")))?;
+    }
+    if options.locale.is_some() || reused {
+        emulate_locale(driver, options.locale.as_deref()).await
+            .map_err(|e| CaptureError::Browser(e.context("Failed to emulate the locale")))?;
+    }
+    if options.transparent || reused {
+        set_transparent_background(driver, options.transparent).await
+            .map_err(|e| CaptureError::Browser(e.context("Failed to set a transparent background")))?;
+    }
+    
+    // Freeze the clocks before the page's scripts first read them
+    let freeze_script = match options.freeze_time {
+        Some(time) => Some(install_time_freeze(driver, time).await
+            .map_err(|e| CaptureError::Browser(e.context("Failed to freeze time")))?),
+        None => None,
+    };
+    
+    // Headless tabs can count as hidden, and pages pause their animations then
+    let visible_script = if options.force_visible {
+        Some(install_visibility_override(driver).await
+            .map_err(|e| CaptureError::Browser(e.context("Failed to force the page visible")))?)
+    } else {
+        None
+    };
+    
+    let font_script = if options.font_substitutes.is_empty() {
+        None
+    } else {
+        Some(install_font_substitutes(driver, &options.font_substitutes).await
+            .map_err(|e| CaptureError::Browser(e.context("Failed to substitute fonts")))?)
+    };
+    
+    // Note the dialogs the page opens, which WebDriver answers silently
+    let dialog_script = install_dialog_log(driver).await
+        .map_err(|e| CaptureError::Browser(e.context("Failed to watch for dialogs")))?;
+    
+    let info = load_and_capture(driver, events, options, plan, is_piped).await;
+    if font_script.is_some() {
+        let _ = report_missing_fonts(driver, is_piped).await;
+    }
+    
+    // Don't leave the shims behind for later captures in a reused session
+    if let Some(identifier) = freeze_script {
+        let _ = remove_time_freeze(driver, identifier).await;
+    }
+    if let Some(identifier) = visible_script {
+        let _ = remove_visibility_override(driver, identifier).await;
+    }
+    if let Some(identifier) = font_script {
+        let _ = remove_script(driver, identifier).await;
+    }
+    let _ = remove_script(driver, dialog_script).await;
+    info
+}
+
+/// Navigate to the page, wait for it and take the capture
+async fn load_and_capture(driver: &WebDriver, events: Option<&BidiEvents>, options: &CaptureOptions, plan: &CapturePlan, is_piped: bool) -> Result<CaptureInfo, CaptureError> {
+    let url = &plan.url;
+    
+    // Note the user agent, so captures that render differently can be reproduced
+    let user_agent = browser_user_agent(driver).await.ok();
+    if let Some(user_agent) = &user_agent
+        && options.debug
+    {
+        eprintln!("User agent: {}", user_agent);
+    }
+    
+    // Run the scripted flow, if any, before the actual capture
+    if let Some(steps) = &options.flow {
+        run_flow(driver, steps, options.debug).await
+            .map_err(CaptureError::Navigation)?;
+    }
+    
+    // Navigate to URL and wait
+    let jitter = options.jitter.map(|jitter| jitter.sample()).unwrap_or_default();
+    if options.debug && options.jitter.is_some() {
+        eprintln!("Adding {:?} of jitter to the wait", jitter);
+    }
+    emit(options, CaptureEvent::Navigating { url: url.to_string() });
+    if options.capture_redirects {
+        // Only this navigation's requests make up the chain
+        read_performance_log(driver).await
+            .map_err(|e| CaptureError::Navigation(e.context("Failed to read the browser's network log")))?;
+    }
+    if let Some(events) = events {
+        // Only this navigation's events describe the page
+        events.clear();
+    }
+    navigate(driver, url, plan.referer.as_ref()).await
+        .map_err(CaptureError::Navigation)?;
+    
+    // Make sure the document has got far enough before the fixed wait starts
+    if let Some(state) = options.ready_state {
+        wait_for_ready_state(driver, events, state, is_piped).await
+            .map_err(CaptureError::Navigation)?;
+    }
+    wait_for_page(options.wait + jitter, is_piped, options.debug).await;
+    
+    // Wait for loading indicators to go away
+    if let Some(selector) = &options.wait_gone {
+        wait_until_gone(driver, selector, is_piped, options.debug).await
+            .map_err(CaptureError::Navigation)?;
+    }
+    
+    // Wait for pages that render an empty shell to fill it in
+    if let Some(min_chars) = options.wait_min_chars {
+        wait_for_text(driver, min_chars, is_piped, options.debug).await
+            .map_err(CaptureError::Navigation)?;
+    }
+    
+    // Wait for pages that load their content in waves
+    if let Some(min_responses) = options.wait_responses {
+        wait_for_responses(driver, events, min_responses, is_piped, options.debug).await
+            .map_err(CaptureError::Navigation)?;
+    }
+    
+    // Note how fast the page got here, before scripts or scrolling change it
+    if let Some(perf_path) = &options.perf_path {
+        save_perf(driver, perf_path, options.no_clobber, is_piped).await
+            .map_err(CaptureError::Navigation)?;
+    }
+    
+    // Note where the page ended up after any redirects
+    let final_url = driver.current_url().await
+        .map_err(|e| CaptureError::Navigation(e.into()))?;
+    let redirects = if options.capture_redirects {
+        let events = read_performance_log(driver).await
+            .map_err(|e| CaptureError::Navigation(e.context("Failed to read the browser's network log")))?;
+        let chain = redirect_chain(&events);
+        if options.debug && !is_piped {
+            report_redirects(&chain);
+        }
+        Some(chain)
+    } else {
+        None
+    };
+    if final_url.origin() != url.origin() && !is_piped {
+        eprintln!("{} {}", "!".yellow(), format!("{} redirected to a different origin: {}", url, final_url).yellow());
+    }
+    emit(options, CaptureEvent::Loaded { url: final_url.to_string() });
+    let mut output_path = options.output_path.clone();
+    if options.name_by_final_url && !is_piped {
+        let extension = output_path.extension().and_then(|e| e.to_str()).unwrap_or("png").to_string();
+        output_path.set_file_name(file_name_for_url(&final_url, &extension));
+    }
+    if options.name_by_title && !is_piped {
+        let extension = output_path.extension().and_then(|e| e.to_str()).unwrap_or("png").to_string();
+        let title = driver.title().await.unwrap_or_default();
+        output_path = unused_path(&output_path.with_file_name(file_name_for_title(&title, &final_url, &extension)));
+    }
+    
+    // Execute JavaScript if provided
+    if let Some(js_code) = &plan.js {
+        execute_javascript(driver, js_code).await
+            .map_err(CaptureError::Navigation)?;
+    }
+    
+    // Scroll to the requested position
+    if let Some(target) = &plan.scroll_to {
+        scroll_to_target(driver, target).await
+            .map_err(CaptureError::Navigation)?;
+    }
+    
+    // Stop the page's animations at the frozen time
+    if let Some(time) = options.freeze_time {
+        pause_animations(driver, time).await
+            .map_err(CaptureError::Navigation)?;
+    }
+    
+    // Capture console logs if requested
+    if let Some(log_path) = &options.console_log {
+        let log_path = console_log_path(log_path, options.console_log_mode, &output_path);
+        capture_console_logs(driver, events, &log_path, options, is_piped).await
+            .map_err(CaptureError::from_output)?;
+    }
+    
+    // Save the page text as it is at capture time
+    if let Some(text_path) = &options.text_path {
+        save_text(driver, text_path, options.text_selector.as_deref(), options.no_clobber, is_piped).await
+            .map_err(CaptureError::Navigation)?;
+    }
+    
+    // Save computed styles as they are at capture time
+    let styles = if options.styles.is_empty() {
+        None
+    } else {
+        Some(save_styles(driver, &output_path, &options.styles, options.no_clobber, is_piped).await
+            .map_err(CaptureError::Navigation)?)
+    };
+    
+    // Say which dialogs were answered while the page loaded
+    let _ = report_dialogs(driver, options.dialog, is_piped).await;
+    
+    // Cover sensitive elements for as long as the capture takes
+    if !options.masks.is_empty() {
+        mask_elements(driver, &options.masks, plan.mask_color, is_piped).await
+            .map_err(CaptureError::Navigation)?;
+    }
+    let captured = capture_output(driver, options, plan, &output_path, is_piped).await;
+    // Don't leave the masks behind for later captures in a reused session
+    if !options.masks.is_empty() {
+        let _ = remove_masks(driver).await;
+    }
+    let (recording_stats, elements, sizes) = captured?;
+    let _ = report_dialogs(driver, options.dialog, is_piped).await;
+    
+    // Save the favicon alongside the output
+    let favicon = if options.favicon {
+        save_favicon(driver, &output_path, plan.domain_filter.as_ref(), options.no_clobber, is_piped).await
+    } else {
+        None
+    };
+    
+    // Describe what was written
+    let (dimensions, bytes) = if is_piped || elements.is_some() || sizes.is_some() {
+        (None, None)
+    } else {
+        let dimensions = if plan.paper_size.is_some() {
+            None
+        } else {
+            Some(open_capture(&output_path).and_then(|reader| Ok(reader.into_dimensions()?))
+                .map_err(CaptureError::from_output)?)
+        };
+        let bytes = fs::metadata(&output_path).map_err(|e| CaptureError::Io(e.into()))?.len();
+        (dimensions, Some(bytes))
+    };
+    
+    let info = CaptureInfo {
+        output_path,
+        final_url: Some(final_url.to_string()),
+        user_agent,
+        dimensions,
+        bytes,
+        recording: recording_stats,
+        elements,
+        sizes,
+        favicon,
+        styles,
+        redirects,
+    };
+    for path in info.written_files() {
+        emit(options, CaptureEvent::Saved { path: path.to_path_buf() });
+    }
+    Ok(info)
+}
+
+/// Take the screenshot, recording or PDF `options` ask for
+///
+/// Returns the recording's statistics, the files of `--each` elements and
+/// the files of a resize sequence, where they apply.
+async fn capture_output(driver: &WebDriver, options: &CaptureOptions, plan: &CapturePlan, output_path: &Path, is_piped: bool) -> Result<(Option<RecordingStats>, Option<Vec<PathBuf>>, Option<Vec<PathBuf>>), CaptureError> {
+    let mut recording_stats = None;
+    let mut elements = None;
+    let mut sizes = None;
+    if options.is_recording {
+        recording_stats = Some(create_recording(driver, &plan.recording, output_path, is_piped, options).await
+            .map_err(CaptureError::from_output)?);
+    } else if let Some(paper_size) = &plan.paper_size {
+        export_pdf(driver, paper_size, output_path, options.no_clobber, is_piped, options.debug).await
+            .map_err(CaptureError::from_output)?;
+    } else {
+        if options.stable {
+            wait_until_stable(driver, is_piped, options.debug).await
+                .map_err(CaptureError::Navigation)?;
+        }
+        let label = screenshot_label(options, plan.label_background);
+        if options.each
+            && let Some(ScreenshotRegion::Element(selector)) = &plan.region
+        {
+            elements = Some(take_element_screenshots(driver, output_path, selector, label.as_ref(), options, is_piped).await
+                .map_err(CaptureError::from_output)?);
+        } else if !options.resize_sequence.is_empty() {
+            sizes = Some(take_resize_sequence(driver, output_path, plan.region.as_ref(), label.as_ref(), options, is_piped).await
+                .map_err(CaptureError::from_output)?);
+        } else if options.states.is_some() {
+            take_state_sheet(driver, output_path, plan.region.as_ref(), label.as_ref(), options, is_piped).await
+                .map_err(CaptureError::from_output)?;
+        } else {
+            take_screenshot(driver, output_path, plan.region.as_ref(), label.as_ref(), options, is_piped).await
+                .map_err(CaptureError::from_output)?;
+        }
+    }
+    Ok((recording_stats, elements, sizes))
+}
+
+/// Cover every element matching `selectors` with a box of `color`
+///
+/// The boxes live in a layer of their own at the top of the document and
+/// follow the elements on every animation frame, so they keep covering them
+/// through scrolling, resizing and recording.
+async fn mask_elements(driver: &WebDriver, selectors: &[String], color: image::Rgba<u8>, is_piped: bool) -> Result<()> {
+    let script = r#"
+        const [selectors, color] = arguments;
+        const layer = document.createElement('div');
+        layer.setAttribute('data-weblook-masks', '');
+        layer.style.cssText = 'position: absolute; top: 0; left: 0; width: 0; height: 0; overflow: visible; z-index: 2147483647; pointer-events: none';
+        document.documentElement.appendChild(layer);
+        const matches = selectors.map(selector => document.querySelectorAll(selector).length);
+        const place = () => {
+            if (!layer.isConnected) {
+                return;
+            }
+            const elements = selectors.flatMap(selector => Array.from(document.querySelectorAll(selector)));
+            while (layer.children.length < elements.length) {
+                layer.appendChild(document.createElement('div'));
+            }
+            while (layer.children.length > elements.length) {
+                layer.lastChild.remove();
+            }
+            elements.forEach((element, i) => {
+                const rect = element.getBoundingClientRect();
+                const style = `position: absolute; top: ${rect.top + window.scrollY}px; left: ${rect.left + window.scrollX}px; `
+                    + `width: ${rect.width}px; height: ${rect.height}px; background: ${color}`;
+                if (layer.children[i].style.cssText !== style) {
+                    layer.children[i].style.cssText = style;
+                }
+            });
+            requestAnimationFrame(place);
+        };
+        place();
+        return matches;
+    "#;
+    let [r, g, b, a] = color.0;
+    let css_color = format!("rgba({}, {}, {}, {:.3})", r, g, b, a as f32 / 255.0);
+    let matches = driver.execute(script, vec![serde_json::json!(selectors), serde_json::json!(css_color)]).await
+        .context("Failed to mask elements")?;
+    let matches: Vec<u64> = serde_json::from_value(matches.json().clone())
+        .context("The page did not report what it masked")?;
+    
+    for (selector, count) in selectors.iter().zip(matches) {
+        if count == 0 && !is_piped {
+            eprintln!("{} {}", "!".yellow(), format!("Mask {} matched no elements", selector).yellow());
+        }
+    }
+    Ok(())
+}
+
+/// Remove the boxes added by [`mask_elements`]
+async fn remove_masks(driver: &WebDriver) -> Result<()> {
+    driver.execute("document.querySelectorAll('[data-weblook-masks]').forEach(layer => layer.remove());", vec![]).await?;
+    Ok(())
+}
+
+/// A browser kept open across captures, for library users taking many of them
+///
+/// [`perform_capture`] starts ChromeDriver and Chrome for every call, while a
+/// `Session` starts them once and takes each capture in the same tab, so
+/// cookies and storage carry over from one capture to the next. Options that
+/// only take effect when the browser is launched (`enable_gpu`, `display`,
+/// `no_sandbox`, `ignore_cert_errors`, `chrome_binary`, `dialog`,
+/// `capture_redirects`, `bidi`, `user_agents_file`, `prefs` and the domain filters)
+/// are fixed by [`Session::start`], and captures asking for different ones
+/// are rejected.
+///
+/// ```no_run
+/// # async fn example(options: weblook::CaptureOptions) -> Result<(), weblook::CaptureError> {
+/// let mut session = weblook::Session::start(&options).await?;
+/// for (url, output) in [("https://example.com", "com.png"), ("https://example.org", "org.png")] {
+///     let options = weblook::CaptureOptions { url: url.to_string(), output_path: output.into(), ..options.clone() };
+///     session.screenshot(options).await?;
+/// }
+/// session.close().await
+/// # }
+/// ```
+pub struct Session {
+    driver: BrowserSession,
+    launch: LaunchSettings,
+    captures: usize,
+}
+
+/// Options that only take effect when the browser is launched
+///
+/// Keep the list in the [`Session`] docs and the error for a capture that
+/// changes them in step with these fields.
+#[derive(Default, PartialEq)]
+struct LaunchSettings {
+    enable_gpu: bool,
+    display: Option<String>,
+    no_sandbox: bool,
+    ignore_cert_errors: bool,
+    chrome_binary: Option<PathBuf>,
+    dialog: DialogAction,
+    capture_redirects: bool,
+    bidi: bool,
+    user_agents_file: Option<PathBuf>,
+    prefs: Vec<ChromePref>,
+    allow_domains: Vec<String>,
+    deny_domains: Vec<String>,
+}
+
+impl LaunchSettings {
+    fn from_options(options: &CaptureOptions) -> Self {
+        LaunchSettings {
+            enable_gpu: options.enable_gpu,
+            display: options.display.clone(),
+            no_sandbox: options.no_sandbox,
+            ignore_cert_errors: options.ignore_cert_errors,
+            chrome_binary: options.chrome_binary.clone(),
+            dialog: options.dialog,
+            capture_redirects: options.capture_redirects,
+            bidi: options.bidi,
+            user_agents_file: options.user_agents_file.clone(),
+            prefs: options.prefs.clone(),
+            allow_domains: options.allow_domains.clone(),
+            deny_domains: options.deny_domains.clone(),
+        }
+    }
+}
+
+impl Session {
+    /// Start ChromeDriver and a browser with the launch options in `options`
+    ///
+    /// Only the launch options, viewport size and window position are used;
+    /// nothing is captured until [`Session::screenshot`] or [`Session::record`].
+    pub async fn start(options: &CaptureOptions) -> Result<Self, CaptureError> {
+        let viewport = options.size.parse::<ViewportSize>()
+            .map_err(CaptureError::InvalidOptions)?;
+        validate_display(options.display.as_deref())
+            .map_err(CaptureError::InvalidOptions)?;
+        validate_chrome_binary(options.chrome_binary.as_deref())
+            .map_err(CaptureError::InvalidOptions)?;
+        let domain_filter = parse_domain_filter(options)
+            .map_err(CaptureError::InvalidOptions)?;
+        let prefs = chrome_prefs(&options.prefs)
+            .map_err(CaptureError::InvalidOptions)?;
+        
+        let chromedriver_port = 9515;
+        let mut chromedriver = ChromeDriverManager::new(chromedriver_port, options.debug)
+            .with_display(options.display.as_deref());
+        chromedriver.start()
+            .map_err(CaptureError::DriverNotFound)?;
+        
+        let flags = LaunchFlags::from_options(options);
+        let driver = setup_webdriver(viewport, options.window_position, chromedriver_port, flags, domain_filter.as_ref(), &user_agent_pool(options), &prefs).await
+            .map_err(CaptureError::DriverNotFound)?;
+        let mut driver = BrowserSession::new(driver, chromedriver);
+        if options.bidi {
+            let events = subscribe_events(&driver, chromedriver_port).await
+                .map_err(CaptureError::DriverNotFound)?;
+            driver = driver.with_events(events);
+        }
+        
+        Ok(Session {
+            driver,
+            launch: LaunchSettings::from_options(options),
+            captures: 0,
+        })
+    }
+    
+    /// Take a screenshot, or export a PDF, as [`perform_capture`] would
+    pub async fn screenshot(&mut self, options: CaptureOptions) -> Result<CaptureInfo, CaptureError> {
+        if options.is_recording {
+            return Err(CaptureError::InvalidOptions(anyhow::anyhow!("Recordings are taken with Session::record")));
+        }
+        self.capture(options).await
+    }
+    
+    /// Record the page as [`perform_capture`] would with `is_recording` set
+    pub async fn record(&mut self, mut options: CaptureOptions) -> Result<CaptureInfo, CaptureError> {
+        options.is_recording = true;
+        self.capture(options).await
+    }
+    
+    /// Quit the browser and stop ChromeDriver, reporting any error
+    ///
+    /// Dropping the session does the same, but cannot report errors.
+    pub async fn close(self) -> Result<(), CaptureError> {
+        self.driver.quit().await
+            .map_err(CaptureError::Browser)
+    }
+    
+    async fn capture(&mut self, options: CaptureOptions) -> Result<CaptureInfo, CaptureError> {
+        let deadline = Deadline::after(options.max_wait);
+        let is_piped = options.output_path.to_str() == Some("-");
+        let plan = plan_capture(&options)?;
+        if LaunchSettings::from_options(&options) != self.launch {
+            return Err(CaptureError::InvalidOptions(anyhow::anyhow!(
+                "GPU, display, sandbox, certificate, Chrome binary, dialog, redirect, BiDi, user agent, pref and domain options cannot change after the session has started"
+            )));
+        }
+        
+        announce_capture(&options, &plan, is_piped);
+        deadline.run(resize_window(&self.driver, &options, plan.viewport)).await?;
+        
+        // Count the capture up front, so one that fails midway still has its
+        // emulation state reset by the next
+        let reused = self.captures > 0;
+        self.captures += 1;
+        capture_before(deadline, &self.driver, self.driver.events(), &options, &plan, reused, is_piped).await
+    }
+}
+
+/// File name for a capture of `url`, e.g. `example.com-docs-intro.png`
+///
+/// The host and path are kept, with every run of other characters turned into
+/// a single `-`.
+pub fn file_name_for_url(url: &Url, extension: &str) -> String {
+    let name = slugify(&format!("{}{}", url.host_str().unwrap_or("page"), url.path()));
+    format!("{}.{}", if name.is_empty() { "page" } else { &name }, extension)
+}
+
+/// File name for a capture of a page titled `title`, e.g. `Pricing-Example-Inc.png`
+///
+/// Titles are slugged like URLs and cut to 100 characters. Pages without a
+/// usable title (empty, or with no ASCII letters or digits) are named after
+/// their URL instead (see [`file_name_for_url`]).
+pub fn file_name_for_title(title: &str, url: &Url, extension: &str) -> String {
+    let slug = slugify(title);
+    let name: String = slug.chars().take(100).collect();
+    let name = name.trim_end_matches(['-', '.']);
+    if !name.chars().any(|c| c.is_ascii_alphanumeric()) {
+        return file_name_for_url(url, extension);
+    }
+    format!("{}.{}", name, extension)
+}
+
+/// `path`, or the first of `name-2.png`, `name-3.png`, ... that doesn't exist yet
+pub fn unused_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    (2..).map(|index| numbered_path(path, index)).find(|candidate| !candidate.exists()).unwrap()
+}
+
+/// Keep letters, digits, `.` and `_`, turning every run of other characters
+/// into a single `-`, with none at either end
+///
+/// The result is safe as a single path component, except that it may be empty
+/// or all dots.
+fn slugify(raw: &str) -> String {
+    let mut slug = String::new();
+    for c in raw.chars() {
+        if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+/// Output path pattern for manifest captures, e.g. `shots/{host}/{path}-{size}.png`
+///
+/// Placeholders:
+/// - `{host}`: the URL's host
+/// - `{path}`: the URL's path, or `index` for the root
+/// - `{date}`: today's date as `YYYY-MM-DD`
+/// - `{index}`: the entry's position in the manifest, from 0
+/// - `{size}`: the viewport size, e.g. `1280x720`
+///
+/// Values are made safe as single path components (see [`file_name_for_url`]),
+/// so only the template's own `/` creates directories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart {
+    Text(String),
+    Host,
+    Path,
+    Date,
+    Index,
+    Size,
+}
+
+impl std::str::FromStr for OutputTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut rest = s;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(TemplatePart::Text(rest[..start].to_string()));
+            }
+            let end = rest[start..].find('}')
+                .with_context(|| format!("Unclosed placeholder in output template: {}", s))?;
+            parts.push(match &rest[start + 1..start + end] {
+                "host" => TemplatePart::Host,
+                "path" => TemplatePart::Path,
+                "date" => TemplatePart::Date,
+                "index" => TemplatePart::Index,
+                "size" => TemplatePart::Size,
+                other => return Err(anyhow::anyhow!(
+                    "Unknown placeholder {{{}}} in output template. Expected {{host}}, {{path}}, {{date}}, {{index}} or {{size}}",
+                    other
+                )),
+            });
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Text(rest.to_string()));
+        }
+        if parts.is_empty() {
+            return Err(anyhow::anyhow!("The output template is empty"));
+        }
+        Ok(OutputTemplate { parts })
+    }
+}
+
+impl OutputTemplate {
+    /// Output path for the `index`th capture, of `url` at viewport `size`, taken on `date`
+    pub fn expand(&self, url: &Url, size: &str, index: usize, date: chrono::NaiveDate) -> PathBuf {
+        // A value of only dots would climb out of the directory
+        let component = |raw: &str, fallback: &str| {
+            let slug = slugify(raw);
+            if slug.chars().all(|c| c == '.') { fallback.to_string() } else { slug }
+        };
+        let mut path = String::new();
+        for part in &self.parts {
+            match part {
+                TemplatePart::Text(text) => path.push_str(text),
+                TemplatePart::Host => path.push_str(&component(url.host_str().unwrap_or(""), "page")),
+                TemplatePart::Path => path.push_str(&component(url.path(), "index")),
+                TemplatePart::Date => path.push_str(&date.format("%Y-%m-%d").to_string()),
+                TemplatePart::Index => path.push_str(&index.to_string()),
+                TemplatePart::Size => path.push_str(&component(size, "size")),
+            }
+        }
+        PathBuf::from(path)
+    }
+}
+
+/// Frame count and playback length of a finished recording
+#[derive(Debug, Clone, Copy)]
+pub struct RecordingStats {
+    pub frames: usize,
+    pub duration: Duration,
+}
+
+/// Summary of a finished capture
+///
+/// Displays as a single machine-parseable line, e.g.
+/// `weblook.png 1280x720 84213 bytes`, with `<frames> frames <seconds>s`
+/// appended for recordings.
+#[derive(Debug, Clone)]
+pub struct CaptureInfo {
+    pub output_path: PathBuf,
+    /// URL of the page after redirects
+    pub final_url: Option<String>,
+    /// User agent the browser sent
+    pub user_agent: Option<String>,
+    /// Width and height of the image or animation; `None` for PDFs and stdout output
+    pub dimensions: Option<(u32, u32)>,
+    /// Size of the output file; `None` when written to stdout
+    pub bytes: Option<u64>,
+    pub recording: Option<RecordingStats>,
+    /// Files written for the matching elements when capturing `each` of them;
+    /// `output_path` is then only the pattern they are named after
+    pub elements: Option<Vec<PathBuf>>,
+    /// Files written at each size of a `resize_sequence`; `output_path` is
+    /// then only the pattern they are named after
+    pub sizes: Option<Vec<PathBuf>>,
+    /// Where the page's favicon was saved, if it was requested and found
+    pub favicon: Option<PathBuf>,
+    /// Where the requested computed styles were saved
+    pub styles: Option<PathBuf>,
+    /// Each URL the page passed through, from the one requested to the one
+    /// captured, when `capture_redirects` is set
+    pub redirects: Option<Vec<RedirectHop>>,
+}
+
+/// A page the browser loaded on its way to the captured one
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct RedirectHop {
+    pub url: String,
+    /// HTTP status of the redirect away from this page; `None` for the final
+    /// page, and for a page that moved on by itself with a script or a meta refresh
+    pub status: Option<u16>,
+}
+
+impl std::fmt::Display for CaptureInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.output_path.display())?;
+        if let Some((width, height)) = self.dimensions {
+            write!(f, " {}x{}", width, height)?;
+        }
+        if let Some(bytes) = self.bytes {
+            write!(f, " {} bytes", bytes)?;
+        }
+        if let Some(stats) = &self.recording {
+            write!(f, " {} frames {:.1}s", stats.frames, stats.duration.as_secs_f64())?;
+        }
+        if let Some(elements) = &self.elements {
+            write!(f, " {} elements", elements.len())?;
+        }
+        if let Some(sizes) = &self.sizes {
+            write!(f, " {} sizes", sizes.len())?;
+        }
+        if let Some(redirects) = &self.redirects {
+            write!(f, " {} redirects", redirects.len().saturating_sub(1))?;
+        }
+        Ok(())
+    }
+}
+
+impl CaptureInfo {
+    /// Files the capture wrote: one per element when capturing each match, or
+    /// per size of a resize sequence, otherwise the output
+    pub fn written_files(&self) -> Vec<&Path> {
+        match self.elements.as_ref().or(self.sizes.as_ref()) {
+            Some(paths) => paths.iter().map(PathBuf::as_path).collect(),
+            None => vec![self.output_path.as_path()],
+        }
+    }
+}
+
+/// Parse the label background colour, defaulting to translucent black
+fn label_background(options: &CaptureOptions) -> Result<image::Rgba<u8>> {
+    match &options.label_background {
+        Some(color) => label::parse_color(color),
+        None => Ok(image::Rgba([0, 0, 0, 180])),
+    }
+}
+
+fn mask_color(options: &CaptureOptions) -> Result<image::Rgba<u8>> {
+    match &options.mask_color {
+        Some(color) => label::parse_color(color),
+        None => Ok(image::Rgba([0, 0, 0, 255])),
+    }
+}
+
+/// The label to stamp onto a screenshot taken now, if any
+fn screenshot_label(options: &CaptureOptions, background: image::Rgba<u8>) -> Option<Label> {
+    let timestamp = options.label_timestamp
+        .then(|| chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+    let text = match (&options.label, timestamp) {
+        (Some(text), Some(timestamp)) => format!("{}  {}", text, timestamp),
+        (Some(text), None) => text.clone(),
+        (None, Some(timestamp)) => timestamp,
+        (None, None) => return None,
+    };
+    Some(Label { text, position: options.label_position, background })
+}
+
+/// Build the recording settings for `options`, applying defaults
+fn recording_settings(options: &CaptureOptions) -> Result<RecordingSettings> {
+    let recording_length = if options.is_recording {
+        options.recording_length.unwrap_or(10)
+    } else {
+        0
+    };
+    let capture_fps = options.capture_fps.unwrap_or(10);
+    let recording = RecordingSettings {
+        duration_secs: recording_length,
+        capture_fps,
+        playback_fps: options.playback_fps.unwrap_or(capture_fps),
+        gif_width: options.gif_width,
+        colors: options.colors.unwrap_or(256),
+        resize_filter: options.resize_filter,
+        frame_fit: options.frame_fit,
+        format: recording_format(options),
+        keyframes: options.keyframes.clone(),
+        clip: options.clip_time,
+        max_frames: options.max_frames,
+        delay_ramp: options.delay_ramp,
+        on_change: options.record_on_change,
+        effects: options.effects,
+    };
+    if recording.capture_fps == 0 || recording.playback_fps == 0 {
+        return Err(anyhow::anyhow!("Frame rates must be at least 1 frame per second"));
+    }
+    if options.is_recording && options.freeze_time.is_some() {
+        return Err(anyhow::anyhow!("Time cannot be frozen while recording"));
+    }
+    if recording.max_frames == Some(0) {
+        return Err(anyhow::anyhow!("The frame cap must be at least 1 frame"));
+    }
+    if !(2..=256).contains(&recording.colors) {
+        return Err(anyhow::anyhow!("A GIF palette must have between 2 and 256 colours, not {}", recording.colors));
+    }
+    if options.colors.is_some() && recording.format != RecordingFormat::Gif {
+        return Err(anyhow::anyhow!("--colors only applies to GIF recordings, not {}", recording.format.label()));
+    }
+    if let Some(clip) = recording.clip
+        && clip.end.as_secs_f64() > recording.duration_secs as f64
+    {
+        return Err(anyhow::anyhow!(
+            "Clip {:?}-{:?} ends past the end of the {} second recording",
+            clip.start, clip.end, recording.duration_secs
+        ));
+    }
+    if let Some(keyframe) = recording.keyframes.iter().find(|k| k.as_secs_f64() > recording.duration_secs as f64) {
+        return Err(anyhow::anyhow!(
+            "Keyframe at {:?} is past the end of the {} second recording",
+            keyframe, recording.duration_secs
+        ));
+    }
+    Ok(recording)
+}
+
+/// Animation format recordings are encoded in (see [`output_format`])
+fn recording_format(options: &CaptureOptions) -> RecordingFormat {
+    output_format(options).recording_format().unwrap_or(options.recording_format)
+}
+
+/// Check that a stdout format is only given for stdout output, and suits the capture
+fn check_stdout_format(options: &CaptureOptions) -> Result<()> {
+    let Some(format) = options.stdout_format else {
+        return Ok(());
+    };
+    if options.output_path.to_str() != Some("-") {
+        return Err(anyhow::anyhow!("A stdout format only applies when writing to stdout (output -)"));
+    }
+    if options.pdf.is_some() {
+        return Err(anyhow::anyhow!("PDFs are always written to stdout as PDF"));
+    }
+    if options.is_recording && format.recording_format().is_none() {
+        return Err(anyhow::anyhow!("Recordings can only be written to stdout as GIF or WebP"));
+    }
+    if !options.is_recording && format == StdoutFormat::Gif {
+        return Err(anyhow::anyhow!("Screenshots can only be written to stdout as PNG, JPEG or WebP"));
+    }
+    Ok(())
+}
+
+/// Check that a transparent background can be kept in the output format
+fn check_transparency(options: &CaptureOptions) -> Result<()> {
+    if !options.transparent {
+        return Ok(());
+    }
+    let format = if options.pdf.is_some() {
+        "PDF"
+    } else if options.is_recording && recording_format(options) == RecordingFormat::Gif {
+        "GIF"
+    } else if output_format(options) == StdoutFormat::Jpeg {
+        "JPEG"
+    } else {
+        return Ok(());
+    };
+    Err(anyhow::anyhow!("A transparent background needs PNG or WebP output, not {}", format))
+}
+
+/// Check that colour effects are only asked of images, and framing of screenshots
+fn check_effects(options: &CaptureOptions) -> Result<()> {
+    if options.pdf.is_some() && !options.effects.is_none() {
+        return Err(anyhow::anyhow!("Grayscale and invert apply to screenshots and recordings, not PDFs"));
+    }
+    if options.polish.is_some() && (options.is_recording || options.pdf.is_some()) {
+        return Err(anyhow::anyhow!("Polish frames screenshots, not recordings or PDFs"));
+    }
+    Ok(())
+}
+
+/// Size in device pixels of a `viewport` rendered at `dpr` device pixels per CSS pixel
+pub fn scaled_size(viewport: ViewportSize, dpr: f64) -> ViewportSize {
+    ViewportSize {
+        width: (viewport.width as f64 * dpr).round() as u32,
+        height: (viewport.height as f64 * dpr).round() as u32,
+    }
+}
+
+/// Check the device pixel ratio, and that GIF frames of the scaled viewport
+/// fit the format's 16-bit width and height
+fn check_device_scale(options: &CaptureOptions, viewport: ViewportSize) -> Result<()> {
+    let dpr = options.dpr.unwrap_or(1.0);
+    if !dpr.is_finite() || dpr <= 0.0 {
+        return Err(anyhow::anyhow!("The device pixel ratio must be a positive number, not {}", dpr));
+    }
+    if !options.is_recording || recording_format(options) != RecordingFormat::Gif {
+        return Ok(());
+    }
+    let scaled = scaled_size(viewport, dpr);
+    let frame = match options.gif_width {
+        Some(width) => ViewportSize { width, height: (scaled.height as f64 * width as f64 / scaled.width.max(1) as f64).round() as u32 },
+        None => scaled,
+    };
+    if frame.width > u16::MAX as u32 || frame.height > u16::MAX as u32 {
+        return Err(anyhow::anyhow!(
+            "GIF frames can be at most {} pixels wide and tall, but these would be {}x{}",
+            u16::MAX, frame.width, frame.height
+        ));
+    }
+    Ok(())
+}
+
+/// Build the domain filter from `allow_domains` or `deny_domains`, checking
+/// that it lets the browser reach `url`
+fn domain_filter(options: &CaptureOptions, url: &Url) -> Result<Option<DomainFilter>> {
+    let Some(filter) = parse_domain_filter(options)? else {
+        return Ok(None);
+    };
+    if let Some(url::Host::Domain(host)) = url.host()
+        && !filter.permits(host)
+    {
+        return Err(anyhow::anyhow!("{} would be blocked by the domain filter", host));
+    }
+    Ok(Some(filter))
+}
+
+/// Build the domain filter from `allow_domains` or `deny_domains`
+fn parse_domain_filter(options: &CaptureOptions) -> Result<Option<DomainFilter>> {
+    let parse = |domains: &[String]| domains.iter().map(|d| d.parse::<DomainPattern>()).collect::<Result<Vec<_>>>();
+    let filter = match (options.allow_domains.is_empty(), options.deny_domains.is_empty()) {
+        (true, true) => return Ok(None),
+        (false, true) => DomainFilter::Allow(parse(&options.allow_domains)?),
+        (true, false) => DomainFilter::Deny(parse(&options.deny_domains)?),
+        (false, false) => return Err(anyhow::anyhow!("Allowed and denied domains cannot be combined")),
+    };
+    Ok(Some(filter))
+}
+
+/// Check that `display` looks like an X display name such as `:99` or `host:0.0`
+fn validate_display(display: Option<&str>) -> Result<()> {
+    if let Some(display) = display {
+        let number = display.rsplit_once(':').map(|(_, number)| number).unwrap_or("");
+        if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == '.') {
+            return Err(anyhow::anyhow!("Invalid display {}. Expected e.g. :99", display));
+        }
+    }
+    Ok(())
+}
+
+/// Check that `--chrome-binary` names an executable file, so that a typo is
+/// reported instead of ChromeDriver's failure to start the browser
+fn validate_chrome_binary(binary: Option<&Path>) -> Result<()> {
+    let Some(binary) = binary else {
+        return Ok(());
+    };
+    let metadata = fs::metadata(binary)
+        .with_context(|| format!("Chrome binary {} does not exist", binary.display()))?;
+    if metadata.is_dir() {
+        return Err(anyhow::anyhow!(
+            "Chrome binary {} is a directory; give the path of the executable inside it",
+            binary.display()
+        ));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err(anyhow::anyhow!("Chrome binary {} is not executable", binary.display()));
+        }
+    }
+    Ok(())
+}
+
+/// Validate a capture without loading the page
+///
+/// Parses every option, checks that ChromeDriver can be started and that the
+/// output (and console log) paths are writable, then prints a summary of the
+/// capture that would have been performed.
+pub fn dry_run(options: &CaptureOptions) -> Result<(), CaptureError> {
+    // Parse and validate options exactly as a real capture would
+    let CapturePlan { url, viewport, paper_size, recording, referer, .. } = plan_capture(options)?;
+    
+    // Make sure ChromeDriver is running or can be started; it is stopped again on drop
+    let mut chromedriver = ChromeDriverManager::new(9515, options.debug)
+        .with_display(options.display.as_deref());
+    chromedriver.start()
+        .map_err(CaptureError::DriverNotFound)?;
+    drop(chromedriver);
+    
+    // Make sure the output files could be written
+    if options.no_clobber && options.output_path.exists() {
+        return Err(CaptureError::Io(anyhow::anyhow!("{} already exists and no-clobber is set", options.output_path.display())));
+    }
+    check_writable(&options.output_path)
+        .map_err(CaptureError::Io)?;
+    if let Some(log_path) = &options.console_log {
+        check_writable(&console_log_path(log_path, options.console_log_mode, &options.output_path))
+            .map_err(CaptureError::Io)?;
+    }
+    if let Some(text_path) = &options.text_path {
+        check_writable(text_path)
+            .map_err(CaptureError::Io)?;
+    }
+    if let Some(perf_path) = &options.perf_path {
+        check_writable(perf_path)
+            .map_err(CaptureError::Io)?;
+    }
+    
+    let action = if options.is_recording {
+        format!(
+            "Record {} for {} seconds at {}fps as {}",
+            url, recording.duration_secs, recording.capture_fps, recording.format.label()
+        )
+    } else if let Some(paper_size) = &paper_size {
+        format!("Export {} as a {}x{}cm PDF", url, paper_size.width, paper_size.height)
+    } else {
+        format!("Take a screenshot of {}", url)
+    };
+    
+    eprintln!("{}", "Dry run: all checks passed".bright_green());
+    eprintln!("{} {}", "•".yellow(), action.yellow());
+    eprintln!("{} {}", "•".yellow(), format!("Viewport {}x{}, waiting {:?}", viewport.width, viewport.height, options.wait).yellow());
+    if let Some(dpr) = options.dpr {
+        let scaled = scaled_size(viewport, dpr);
+        eprintln!("{} {}", "•".yellow(), format!("Rendered at {} device pixels per CSS pixel, {}x{} pixels", dpr, scaled.width, scaled.height).yellow());
+    }
+    if let Some(jitter) = &options.jitter {
+        eprintln!("{} {}", "•".yellow(), format!("Plus a random {:?} to {:?} of jitter", jitter.min, jitter.max).yellow());
+    }
+    if let Some(referer) = &referer {
+        eprintln!("{} {}", "•".yellow(), format!("Send {} as the referer", referer).yellow());
+    }
+    if let Some(state) = options.ready_state {
+        eprintln!("{} {}", "•".yellow(), format!("Wait up to {:?} for the document to be {} before the wait", READY_STATE_TIMEOUT, state.name()).yellow());
+    }
+    if options.bidi {
+        eprintln!("{} {}", "•".yellow(), "Follow console messages, responses and page loads through WebDriver BiDi events".yellow());
+    }
+    if let Some(steps) = &options.flow {
+        eprintln!("{} {}", "•".yellow(), format!("Run a flow of {} steps first", steps.len()).yellow());
+    }
+    if let Some(selector) = &options.wait_gone {
+        eprintln!("{} {}", "•".yellow(), format!("Then wait up to {:?} for {} to disappear", WAIT_GONE_TIMEOUT, selector).yellow());
+    }
+    if let Some(time) = options.freeze_time {
+        eprintln!("{} {}", "•".yellow(), format!("Freeze time and animations {:?} into the page", time).yellow());
+    }
+    if let Some(min_chars) = options.wait_min_chars {
+        eprintln!("{} {}", "•".yellow(), format!("Then wait up to {:?} for more than {} characters of text", WAIT_MIN_CHARS_TIMEOUT, min_chars).yellow());
+    }
+    if let Some(min_responses) = options.wait_responses {
+        eprintln!("{} {}", "•".yellow(), format!("Then wait up to {:?} for {} network responses to complete", WAIT_RESPONSES_TIMEOUT, min_responses).yellow());
+    }
+    if let Some(states) = options.states {
+        eprintln!("{} {}", "•".yellow(), format!("Capture {} states into a sprite sheet", states).yellow());
+    }
+    if !options.resize_sequence.is_empty() {
+        let sizes: Vec<String> = options.resize_sequence.iter().map(|size| format!("{}x{}", size.width, size.height)).collect();
+        eprintln!("{} {}", "•".yellow(), format!(
+            "Resize to {} in turn, letting the page settle {:?} before each screenshot",
+            sizes.join(", "), options.resize_settle.unwrap_or(DEFAULT_RESIZE_SETTLE)
+        ).yellow());
+    }
+    if options.stable && !options.is_recording && paper_size.is_none() {
+        eprintln!("{} {}", "•".yellow(), format!("Wait up to {:?} for the page to stop changing before the screenshot", STABLE_TIMEOUT).yellow());
+    }
+    let output = if options.output_path.to_str() == Some("-") {
+        "stdout".to_string()
+    } else {
+        options.output_path.display().to_string()
+    };
+    eprintln!("{} {}", "•".yellow(), format!("Write output to {}", output).yellow());
+    if let Some(warning) = output_format_warning(options) {
+        eprintln!("{} {}", "!".yellow(), warning.yellow());
+    }
+    
+    Ok(())
+}
+
+/// Check that `path` could be created or overwritten, without touching it
+fn check_writable(path: &Path) -> Result<()> {
+    if path.to_str() == Some("-") {
+        return Ok(());
+    }
+    
+    if path.is_dir() {
+        return Err(anyhow::anyhow!("Output path {} is a directory", path.display()));
+    }
+    if path.exists() {
+        fs::OpenOptions::new().append(true).open(path)
+            .with_context(|| format!("Output path {} is not writable", path.display()))?;
+        return Ok(());
+    }
+    
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    tempfile::NamedTempFile::new_in(parent)
+        .with_context(|| format!("Cannot create {} (is {} writable?)", path.display(), parent.display()))?;
+    
+    Ok(())
+}
+
+/// Visible text and title extracted from a page
+pub struct PageText {
+    pub title: String,
+    pub text: String,
+}
+
+/// Navigate to a page, wait, and return its visible text and title
+///
+/// When `selector` is given, only the text of the first matching element is returned.
+pub async fn extract_text(url: &str, wait: Duration, size: &str, selector: Option<&str>, debug: bool) -> Result<PageText, CaptureError> {
+    // Parse URL and viewport size
+    let url = Url::parse(url).context("Failed to parse URL")
+        .map_err(CaptureError::InvalidOptions)?;
+    let viewport = size.parse::<ViewportSize>()
+        .map_err(CaptureError::InvalidOptions)?;
+
+    // Start ChromeDriver if not already running
+    let chromedriver_port = 9515;
+    let mut chromedriver = ChromeDriverManager::new(chromedriver_port, debug);
+    chromedriver.start()
+        .map_err(CaptureError::DriverNotFound)?;
+
+    // Set up WebDriver and load the page without progress output
+    let driver = setup_webdriver(viewport, WindowPosition::default(), chromedriver_port, LaunchFlags::default(), None, &[], &serde_json::Map::new()).await
+        .map_err(CaptureError::DriverNotFound)?;
+    let driver = BrowserSession::new(driver, chromedriver);
+    navigate(&driver, &url, None).await
+        .map_err(CaptureError::Navigation)?;
+    wait_for_page(wait, true, debug).await;
+
+    let page = async {
+        let text = element_text(&driver, selector).await?;
+        let title = driver.title().await?;
+        Ok(PageText { title, text })
+    }.await.map_err(CaptureError::Browser)?;
+
+    // Clean up
+    driver.quit().await
+        .map_err(CaptureError::Browser)?;
+
+    Ok(page)
+}
+
+/// Visible text of the first element matching `selector`, or of the whole page
+async fn element_text(driver: &WebDriver, selector: Option<&str>) -> Result<String> {
+    let script = r#"
+        const el = arguments[0] ? document.querySelector(arguments[0]) : document.body;
+        return el ? el.innerText : null;
+    "#;
+    let selector_arg = selector.map(serde_json::Value::from).unwrap_or(serde_json::Value::Null);
+    let result = driver.execute(script, vec![selector_arg]).await?;
+
+    Ok(result.json().as_str()
+        .with_context(|| format!("No element matches selector: {}", selector.unwrap_or("body")))?
+        .to_string())
+}
+
+/// Tidy extracted page text for saving
+///
+/// Runs of spaces and tabs become a single space and lines are trimmed, while
+/// line breaks between blocks are kept, with at most one blank line in a row.
+pub fn normalize_text(text: &str) -> String {
+    let mut normalized = String::new();
+    let mut blank_lines = 0;
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+        if !normalized.is_empty() {
+            normalized.push_str(if blank_lines > 0 { "\n\n" } else { "\n" });
+        }
+        normalized.push_str(&line);
+        blank_lines = 0;
+    }
+    if !normalized.is_empty() {
+        normalized.push('\n');
+    }
+    normalized
+}
+
+/// Save the visible text of the page, or of the element matching `selector`, to `path`
+async fn save_text(driver: &WebDriver, path: &Path, selector: Option<&str>, no_clobber: bool, is_piped: bool) -> Result<()> {
+    let text = normalize_text(&element_text(driver, selector).await?);
+    create_output_file(path, no_clobber)?.write_all(text.as_bytes())
+        .with_context(|| format!("Failed to save text to {}", path.display()))?;
+    if !is_piped {
+        eprintln!("Text saved to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Save the page's performance metrics as JSON to `path`
+///
+/// Timings come from the page's navigation and paint entries, in milliseconds
+/// since navigation started, or `null` for events that haven't happened. The
+/// heap sizes, and everything else Chrome counts under `metrics`, come from
+/// CDP's `Performance.getMetrics`.
+async fn save_perf(driver: &WebDriver, path: &Path, no_clobber: bool, is_piped: bool) -> Result<()> {
+    let timings = driver.execute(r#"
+        const navigation = performance.getEntriesByType('navigation')[0];
+        const paint = name => performance.getEntriesByName(name)[0]?.startTime ?? null;
+        const at = time => navigation && time > 0 ? time : null;
+        return {
+            time_to_first_byte_ms: at(navigation?.responseStart),
+            first_paint_ms: paint('first-paint'),
+            first_contentful_paint_ms: paint('first-contentful-paint'),
+            dom_content_loaded_ms: at(navigation?.domContentLoadedEventEnd),
+            load_ms: at(navigation?.loadEventEnd),
+            transfer_bytes: navigation ? navigation.transferSize : null,
+        };
+    "#, Vec::new()).await?.json().clone();
+    
+    let dev_tools = ChromeDevTools::new(driver.handle.clone());
+    dev_tools.execute_cdp("Performance.enable").await?;
+    let result = dev_tools.execute_cdp("Performance.getMetrics").await;
+    dev_tools.execute_cdp("Performance.disable").await.ok();
+    let metrics: serde_json::Map<String, serde_json::Value> = result?["metrics"].as_array()
+        .map(|metrics| metrics.iter()
+            .filter_map(|metric| Some((metric["name"].as_str()?.to_string(), metric["value"].clone())))
+            .collect())
+        .unwrap_or_default();
+    
+    let mut perf = timings.as_object().cloned().unwrap_or_default();
+    perf.insert("js_heap_used_bytes".to_string(), metrics.get("JSHeapUsedSize").cloned().unwrap_or_default());
+    perf.insert("js_heap_total_bytes".to_string(), metrics.get("JSHeapTotalSize").cloned().unwrap_or_default());
+    perf.insert("metrics".to_string(), serde_json::Value::Object(metrics));
+    
+    let json = serde_json::to_string_pretty(&perf)?;
+    create_output_file(path, no_clobber)?.write_all(format!("{}\n", json).as_bytes())
+        .with_context(|| format!("Failed to save performance metrics to {}", path.display()))?;
+    if !is_piped {
+        eprintln!("Performance metrics saved to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Save the computed styles `queries` ask for as JSON next to the output, returning where
+///
+/// The file maps each selector to its properties and their values. A selector
+/// matching no element fails the capture, as its styles can't be confirmed.
+async fn save_styles(driver: &WebDriver, output_path: &Path, queries: &[StyleQuery], no_clobber: bool, is_piped: bool) -> Result<PathBuf> {
+    let mut styles = serde_json::Map::new();
+    for query in queries {
+        let values = driver.execute(r#"
+            const [selector, properties] = arguments;
+            const element = document.querySelector(selector);
+            if (!element) {
+                return null;
+            }
+            const style = getComputedStyle(element);
+            const values = {};
+            for (const name of properties.length ? properties : Array.from(style)) {
+                values[name] = style.getPropertyValue(name).trim();
+            }
+            return values;
+        "#, vec![serde_json::json!(query.selector), serde_json::json!(query.properties)]).await?;
+        let values = values.json();
+        if values.is_null() {
+            return Err(anyhow::anyhow!("No element matches {} to read styles from", query.selector));
+        }
+        styles.insert(query.selector.clone(), values.clone());
+    }
+    
+    let path = styles_path(output_path);
+    let json = serde_json::to_string_pretty(&styles)?;
+    create_output_file(&path, no_clobber)?.write_all(format!("{}\n", json).as_bytes())
+        .with_context(|| format!("Failed to save styles to {}", path.display()))?;
+    if !is_piped {
+        eprintln!("Styles saved to {}", path.display());
+    }
+    Ok(path)
+}
+
+/// Attach to the session kept open by `weblook serve`, if there is a live one
+async fn attach_warm_session(debug: bool) -> Option<WebDriver> {
+    let session = WarmSession::load()?;
+    match session.attach().await {
+        Ok(driver) => Some(driver),
+        Err(e) => {
+            if debug {
+                eprintln!("Not reusing the warm session: {:#}", e);
+            }
+            None
+        }
+    }
+}
+
+/// Command-line switches Chrome is launched with, and the executable to launch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LaunchFlags {
+    pub enable_gpu: bool,
+    /// Run without a window; a headful Chrome uses the display ChromeDriver was started on
+    pub headless: bool,
+    pub no_sandbox: bool,
+    pub ignore_cert_errors: bool,
+    /// Chrome executable; ChromeDriver finds one itself when this is `None`
+    pub binary: Option<PathBuf>,
+    /// How ChromeDriver answers dialogs that are open when a command arrives
+    pub dialog: DialogAction,
+    /// Keep Chrome's DevTools events in the performance log, to read redirects from
+    pub performance_log: bool,
+    /// Ask ChromeDriver for a WebDriver BiDi connection to the session
+    pub bidi: bool,
+}
+
+impl Default for LaunchFlags {
+    /// Headless, without the GPU, and sandboxed unless running as root
+    fn default() -> Self {
+        LaunchFlags {
+            enable_gpu: false,
+            headless: true,
+            no_sandbox: running_as_root(),
+            ignore_cert_errors: false,
+            binary: None,
+            dialog: DialogAction::default(),
+            performance_log: false,
+            bidi: false,
+        }
+    }
+}
+
+impl LaunchFlags {
+    fn from_options(options: &CaptureOptions) -> Self {
+        LaunchFlags {
+            enable_gpu: options.enable_gpu,
+            headless: options.display.is_none(),
+            no_sandbox: options.no_sandbox || running_as_root(),
+            ignore_cert_errors: options.ignore_cert_errors,
+            binary: options.chrome_binary.clone(),
+            dialog: options.dialog,
+            performance_log: options.capture_redirects,
+            bidi: options.bidi,
+        }
+    }
+}
+
+/// Launch Chrome through ChromeDriver on `port`
+///
+/// The user agent is picked at random from `user_agents`, or from the built-in
+/// ones if it is empty.
+pub(crate) async fn setup_webdriver(viewport: ViewportSize, position: WindowPosition, port: u16, flags: LaunchFlags, domain_filter: Option<&DomainFilter>, user_agents: &[String], prefs: &serde_json::Map<String, serde_json::Value>) -> Result<WebDriver> {
+    let mut caps = ChromeCapabilities::new();
+    
+    // Select a random user agent
+    let user_agent = pick_user_agent(user_agents);
+    
+    // Configure headless mode and user agent
+    if flags.headless {
+        caps.add_arg("--headless=new")?;
+    }
+    if !flags.enable_gpu {
+        // Some Linux/container setups render blank pages with the GPU enabled
+        caps.add_arg("--disable-gpu")?;
+    }
+    if flags.ignore_cert_errors {
+        caps.add_arg("--ignore-certificate-errors")?;
+        caps.accept_insecure_certs(true)?;
+    }
+    if let Some(binary) = &flags.binary {
+        caps.set_binary(&binary.to_string_lossy())?;
+    }
+    // Dialogs block the page until they are answered, so answer them instead
+    // of failing the next command
+    caps.set_base_capability("unhandledPromptBehavior", flags.dialog.capability())?;
+    if flags.performance_log {
+        caps.set_base_capability("goog:loggingPrefs", serde_json::json!({ "performance": "ALL" }))?;
+    }
+    if flags.bidi {
+        caps.set_base_capability("webSocketUrl", true)?;
+    }
+    if flags.no_sandbox {
+        // Chrome refuses to start its sandbox as root, and containers often
+        // give /dev/shm too little space for it
+        caps.add_arg("--no-sandbox")?;
+        caps.add_arg("--disable-dev-shm-usage")?;
+    }
     caps.add_arg(&format!("--window-size={},{}", viewport.width, viewport.height))?;
+    caps.add_arg(&format!("--window-position={},{}", position.x, position.y))?;
     caps.add_arg(&format!("--user-agent={}", user_agent))?;
+    if let Some(filter) = domain_filter {
+        caps.add_arg(&format!("--host-resolver-rules={}", filter.resolver_rules()))?;
+    }
+    if !prefs.is_empty() {
+        // Many settings, such as notification prompts, have no command-line switch
+        caps.add_experimental_option("prefs", prefs)?;
+    }
+    
+    // Enable browser logging - we'll handle this differently
+    // by using the Chrome DevTools Protocol directly
+    
+    // Connect to WebDriver
+    let driver = WebDriver::new(&format!("http://localhost:{}", port), caps).await?;
+    
+    // Set window position and viewport size
+    driver.set_window_rect(position.x as i64, position.y as i64, viewport.width, viewport.height).await?;
+    
+    Ok(driver)
+}
+
+/// Subscribe to the BiDi events of a session launched with [`LaunchFlags::bidi`]
+///
+/// ChromeDriver serves the session's BiDi WebSocket on its own port, and names
+/// the top-level browsing context after the window handle.
+async fn subscribe_events(driver: &WebDriver, port: u16) -> Result<BidiEvents> {
+    let context = driver.window().await?.to_string();
+    let url = bidi::websocket_url(port, &driver.session_id().to_string());
+    BidiEvents::connect(&url, context).await
+}
+
+/// Whether WebLook is running as root, where Chrome can't use its sandbox
+pub(crate) fn running_as_root() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::fs::MetadataExt;
+        fs::metadata("/proc/self").is_ok_and(|meta| meta.uid() == 0)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        false
+    }
+}
+
+/// Navigate to `url`, sending `referer` as the referer if given
+async fn navigate(driver: &WebDriver, url: &Url, referer: Option<&Url>) -> Result<()> {
+    match referer {
+        Some(referer) => navigate_with_referer(driver, url, referer).await?,
+        None => driver.goto(url.as_str()).await?,
+    }
+    Ok(())
+}
+
+/// Wait the fixed time before the capture, with a countdown unless piped
+async fn wait_for_page(wait_time: Duration, is_piped: bool, debug: bool) {
+    // Wait for the specified time with a nice countdown
+    if !is_piped {
+        // Force flush stdout to ensure messages appear
+        eprintln!("Page loaded. Waiting for {:?}...", wait_time);
+        std::io::stderr().flush().ok();
+        
+        display_countdown(wait_time, "Loading page", debug).await;
+    } else {
+        sleep(wait_time).await;
+    }
+}
+
+/// ChromeDriver's command for reading one of the browser's logs, which
+/// thirtyfour has no method for
+#[derive(Debug)]
+struct ReadLog(&'static str);
+
+impl ExtensionCommand for ReadLog {
+    fn parameters_json(&self) -> Option<serde_json::Value> {
+        Some(serde_json::json!({ "type": self.0 }))
+    }
+
+    fn method(&self) -> reqwest::Method {
+        reqwest::Method::POST
+    }
+
+    fn endpoint(&self) -> Arc<str> {
+        Arc::from("/se/log")
+    }
+}
+
+/// The DevTools events logged since the performance log was last read
+///
+/// Each is an object with the event's `method` and `params`, as the `Network`
+/// domain would send them to a DevTools client.
+async fn read_performance_log(driver: &WebDriver) -> Result<Vec<serde_json::Value>> {
+    let entries: Vec<serde_json::Value> = driver.handle.cmd(WebDriverCommand::ExtensionCommand(Box::new(ReadLog("performance")))).await?
+        .value()?;
+    Ok(entries.iter()
+        .filter_map(|entry| entry["message"].as_str())
+        .filter_map(|message| serde_json::from_str::<serde_json::Value>(message).ok())
+        .map(|message| message["message"].clone())
+        .collect())
+}
+
+/// The pages the top-level document went through, in order, according to
+/// the `Network.requestWillBeSent` events in `events`
+///
+/// An HTTP redirect arrives as a new request carrying the redirect response,
+/// whose status is noted on the page it redirected away from. A document
+/// request without one is a fresh navigation, e.g. by a script. Requests for
+/// frames other than the first document's are left out.
+pub fn redirect_chain(events: &[serde_json::Value]) -> Vec<RedirectHop> {
+    let mut chain: Vec<RedirectHop> = Vec::new();
+    let mut main_frame = None;
+    let mut requests = Vec::new();
+    for event in events {
+        let params = &event["params"];
+        if event["method"] != "Network.requestWillBeSent" || params["type"] != "Document" {
+            continue;
+        }
+        let frame = params["frameId"].as_str();
+        if *main_frame.get_or_insert(frame) != frame {
+            continue;
+        }
+        let Some(url) = params["request"]["url"].as_str() else {
+            continue;
+        };
+        let request = params["requestId"].as_str();
+        let redirect = &params["redirectResponse"];
+        if redirect.is_object() {
+            let from = redirect["url"].as_str().unwrap_or_default();
+            let status = redirect["status"].as_u64().and_then(|status| u16::try_from(status).ok());
+            match chain.last_mut() {
+                Some(last) if last.url == from => last.status = status,
+                _ => chain.push(RedirectHop { url: from.to_string(), status }),
+            }
+        } else if requests.contains(&request) {
+            // Chrome sometimes reports a request twice
+            continue;
+        }
+        requests.push(request);
+        chain.push(RedirectHop { url: url.to_string(), status: None });
+    }
+    chain
+}
+
+/// Print the redirect chain, one page per line with the status that left it
+fn report_redirects(chain: &[RedirectHop]) {
+    eprintln!("Redirect chain ({} redirects):", chain.len().saturating_sub(1));
+    for hop in chain {
+        match hop.status {
+            Some(status) => eprintln!("  {} {}", status, hop.url),
+            None => eprintln!("      {}", hop.url),
+        }
+    }
+}
+
+/// Longest to wait for a page loaded with a referer to finish loading
+const REFERER_LOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Load `url` as if a link on `referer` had been followed
+///
+/// WebDriver navigation cannot set the referer, so the page is loaded over CDP,
+/// which returns before the load finishes. A marker left on the old document
+/// tells when the new one has replaced it.
+async fn navigate_with_referer(driver: &WebDriver, url: &Url, referer: &Url) -> Result<()> {
+    driver.execute("window.__weblookLeaving = true;", vec![]).await?;
+    
+    // Send the full referer, not just its origin as the default policy would cross-origin
+    let devtools = ChromeDevTools::new(driver.handle.clone());
+    let result = devtools.execute_cdp_with_params(
+        "Page.navigate",
+        serde_json::json!({ "url": url.as_str(), "referrer": referer.as_str(), "referrerPolicy": "unsafeUrl" }),
+    ).await?;
+    if let Some(error) = result["errorText"].as_str() {
+        anyhow::bail!("Failed to load {}: {}", url, error);
+    }
+    
+    let loaded = poll_until(REFERER_LOAD_TIMEOUT, || async {
+        let state = driver.execute("return !window.__weblookLeaving && document.readyState === 'complete';", vec![]).await;
+        Ok(state.map(|state| state.json().as_bool() == Some(true)).unwrap_or(false))
+    }).await?;
+    if !loaded {
+        anyhow::bail!("{} did not finish loading within {:?}", url, REFERER_LOAD_TIMEOUT);
+    }
+    Ok(())
+}
+
+/// Current `document.readyState` of the page
+async fn ready_state(driver: &WebDriver) -> Result<ReadyState> {
+    let state = driver.execute("return document.readyState;", Vec::new()).await?;
+    match state.json().as_str() {
+        Some("complete") => Ok(ReadyState::Complete),
+        Some("interactive") => Ok(ReadyState::Interactive),
+        _ => Ok(ReadyState::Loading),
+    }
+}
+
+/// The ready state the page's BiDi load events show it has reached
+fn events_ready_state(page: &PageEvents) -> ReadyState {
+    if page.loaded {
+        ReadyState::Complete
+    } else if page.dom_content_loaded {
+        ReadyState::Interactive
+    } else {
+        ReadyState::Loading
+    }
+}
+
+/// Wait until `document.readyState` has reached `target` or gone past it
+///
+/// With BiDi `events` this waits for the page's load events instead of
+/// polling the document.
+async fn wait_for_ready_state(driver: &WebDriver, events: Option<&BidiEvents>, target: ReadyState, is_piped: bool) -> Result<()> {
+    if !is_piped {
+        eprintln!("Waiting for the document to be {}...", target.name());
+        std::io::stderr().flush().ok();
+    }
+    
+    let reached = std::sync::Mutex::new(ReadyState::Loading);
+    let ready = match events {
+        Some(events) => {
+            let ready = events.wait_for(READY_STATE_TIMEOUT, |page| events_ready_state(page) >= target).await;
+            *reached.lock().unwrap() = events_ready_state(&events.snapshot());
+            ready
+        }
+        None => poll_until(READY_STATE_TIMEOUT, || async {
+            let state = ready_state(driver).await?;
+            *reached.lock().unwrap() = state;
+            Ok(state >= target)
+        }).await?,
+    };
+    if !ready {
+        let reached = reached.into_inner().unwrap();
+        return Err(anyhow::anyhow!(
+            "Timed out after {:?} waiting for the document to be {}; it is still {}",
+            READY_STATE_TIMEOUT, target.name(), reached.name()
+        ));
+    }
+    Ok(())
+}
+
+// Display a colorful countdown timer
+async fn display_countdown(duration: Duration, message: &str, debug: bool) {
+    if duration < Duration::from_secs(1) {
+        // Too short for a per-second bar
+        sleep(duration).await;
+    } else if !debug {
+        eprintln!("Starting countdown: {} for {:?}", message, duration);
+        std::io::stderr().flush().ok();
+        
+        let pb = ProgressBar::new(duration.as_secs());
+        pb.set_style(ProgressStyle::default_bar()
+            .template("{spinner:.green} {msg} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len}s")
+            .unwrap()
+            .progress_chars("#>-"));
+        
+        pb.set_message(message.bright_green().to_string());
+        
+        for i in 1..=duration.as_secs() {
+            // Rainbow text for the countdown
+            match i % 6 {
+                0 => pb.set_message(message.red().to_string()),
+                1 => pb.set_message(message.yellow().to_string()),
+                2 => pb.set_message(message.green().to_string()),
+                3 => pb.set_message(message.cyan().to_string()),
+                4 => pb.set_message(message.blue().to_string()),
+                _ => pb.set_message(message.magenta().to_string()),
+            }
+            
+            pb.set_position(i);
+            sleep(Duration::from_secs(1)).await;
+        }
+        
+        // Sleep off any fraction of a second left over
+        sleep(duration - Duration::from_secs(duration.as_secs())).await;
+        
+        pb.finish_with_message(format!("{} complete!", message).green().to_string());
+    } else {
+        eprintln!("Waiting for {:?}...", duration);
+        sleep(duration).await;
+    }
+}
+
+async fn run_flow(driver: &WebDriver, steps: &[FlowStep], debug: bool) -> Result<()> {
+    for (i, step) in steps.iter().enumerate() {
+        if debug {
+            eprintln!("Flow step {}: {:?}", i + 1, step);
+        }
+        
+        let result: Result<()> = async {
+            match step {
+                FlowStep::Goto { url } => {
+                    let url = Url::parse(url).context("Failed to parse URL")?;
+                    driver.goto(url.as_str()).await?;
+                }
+                FlowStep::Fill { selector, value } => {
+                    let element = driver.find(By::Css(selector.as_str())).await?;
+                    element.clear().await?;
+                    element.send_keys(value.as_str()).await?;
+                }
+                FlowStep::Click { selector } => {
+                    driver.find(By::Css(selector.as_str())).await?.click().await?;
+                }
+                FlowStep::Wait { seconds } => {
+                    sleep(Duration::from_secs_f64(seconds.max(0.0))).await;
+                }
+                FlowStep::WaitFor { selector, timeout } => {
+                    let found = poll_until(Duration::from_secs(*timeout), || async {
+                        Ok(!driver.find_all(By::Css(selector.as_str())).await?.is_empty())
+                    }).await?;
+                    if !found {
+                        return Err(anyhow::anyhow!("Timed out waiting for {}", selector));
+                    }
+                }
+            }
+            Ok(())
+        }.await;
+        
+        result.with_context(|| format!("Flow step {} failed", i + 1))?;
+    }
     
-    // Enable browser logging - we'll handle this differently
-    // by using the Chrome DevTools Protocol directly
+    Ok(())
+}
+
+/// Poll `done` every 250ms until it returns true, or return false once `timeout` has passed
+async fn poll_until<F, Fut>(timeout: Duration, mut done: F) -> Result<bool>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<bool>>,
+{
+    let start_time = std::time::Instant::now();
+    while !done().await? {
+        if start_time.elapsed() > timeout {
+            return Ok(false);
+        }
+        sleep(Duration::from_millis(250)).await;
+    }
+    Ok(true)
+}
+
+/// Whether any element matching `selector` is visible, i.e. takes up space on
+/// the page and is not styled `visibility: hidden`
+async fn is_selector_visible(driver: &WebDriver, selector: &str) -> Result<bool> {
+    let script = r#"
+        return Array.from(document.querySelectorAll(arguments[0])).some(element =>
+            element.getClientRects().length > 0 && getComputedStyle(element).visibility !== 'hidden'
+        );
+    "#;
+    let visible = driver.execute(script, vec![serde_json::json!(selector)]).await?;
+    Ok(visible.json().as_bool().unwrap_or(false))
+}
+
+/// Wait until no element matching `selector`, e.g. a loading spinner, is visible
+async fn wait_until_gone(driver: &WebDriver, selector: &str, is_piped: bool, debug: bool) -> Result<()> {
+    if !is_piped {
+        eprintln!("Waiting for {} to disappear...", selector);
+        std::io::stderr().flush().ok();
+    }
     
-    // Connect to WebDriver
-    let driver = WebDriver::new(&format!("http://localhost:{}", port), caps).await?;
+    let gone = poll_until(WAIT_GONE_TIMEOUT, || async { Ok(!is_selector_visible(driver, selector).await?) }).await?;
+    if !gone {
+        return Err(anyhow::anyhow!(
+            "Timed out after {:?} waiting for {} to disappear; it is still present",
+            WAIT_GONE_TIMEOUT, selector
+        ));
+    }
+    if debug {
+        eprintln!("{} is gone", selector);
+    }
+    Ok(())
+}
+
+/// Number of characters of visible text on the page
+async fn text_length(driver: &WebDriver) -> Result<usize> {
+    let length = driver.execute("return document.body ? document.body.innerText.length : 0;", Vec::new()).await?;
+    Ok(length.json().as_u64().unwrap_or(0) as usize)
+}
+
+/// Wait until the page has more than `min_chars` characters of visible text,
+/// e.g. once a client-rendered app has replaced its empty shell
+async fn wait_for_text(driver: &WebDriver, min_chars: usize, is_piped: bool, debug: bool) -> Result<()> {
+    if !is_piped {
+        eprintln!("Waiting for more than {} characters of text...", min_chars);
+        std::io::stderr().flush().ok();
+    }
+    
+    let length = std::sync::atomic::AtomicUsize::new(0);
+    let enough = poll_until(WAIT_MIN_CHARS_TIMEOUT, || async {
+        let current = text_length(driver).await?;
+        length.store(current, std::sync::atomic::Ordering::Relaxed);
+        Ok(current > min_chars)
+    }).await?;
+    let length = length.into_inner();
+    if !enough {
+        return Err(anyhow::anyhow!(
+            "Timed out after {:?} waiting for more than {} characters of text; the page has {}",
+            WAIT_MIN_CHARS_TIMEOUT, min_chars, length
+        ));
+    }
+    if debug {
+        eprintln!("The page has {} characters of text", length);
+    }
+    Ok(())
+}
+
+/// Number of network responses the page has received in full, its own document included
+///
+/// Counted from the Resource Timing entries, whose buffer is enlarged on the
+/// first call so that busy pages are not cut off at the default 250 entries.
+async fn response_count(driver: &WebDriver) -> Result<usize> {
+    let count = driver.execute(r#"
+        if (!window.__weblookResponses) {
+            window.__weblookResponses = true;
+            performance.setResourceTimingBufferSize(100000);
+        }
+        const complete = (entry) => entry.responseEnd > 0;
+        return performance.getEntriesByType('navigation').filter(complete).length
+            + performance.getEntriesByType('resource').filter(complete).length;
+    "#, Vec::new()).await?;
+    Ok(count.json().as_u64().unwrap_or(0) as usize)
+}
+
+/// Wait until at least `min_responses` network responses have completed, for
+/// pages that load in waves and never go fully idle
+///
+/// With BiDi `events` the responses are counted as the browser reports them
+/// instead of from the page's Resource Timing entries.
+async fn wait_for_responses(driver: &WebDriver, events: Option<&BidiEvents>, min_responses: usize, is_piped: bool, debug: bool) -> Result<()> {
+    if !is_piped {
+        eprintln!("Waiting for {} network responses...", min_responses);
+        std::io::stderr().flush().ok();
+    }
     
-    // Set viewport size
-    driver.set_window_rect(0, 0, viewport.width, viewport.height).await?;
+    let count = std::sync::atomic::AtomicUsize::new(0);
+    let enough = match events {
+        Some(events) => {
+            let enough = events.wait_for(WAIT_RESPONSES_TIMEOUT, |page| page.responses >= min_responses).await;
+            count.store(events.snapshot().responses, std::sync::atomic::Ordering::Relaxed);
+            enough
+        }
+        None => poll_until(WAIT_RESPONSES_TIMEOUT, || async {
+            let current = response_count(driver).await?;
+            count.store(current, std::sync::atomic::Ordering::Relaxed);
+            Ok(current >= min_responses)
+        }).await?,
+    };
+    let count = count.into_inner();
+    if !enough {
+        return Err(anyhow::anyhow!(
+            "Timed out after {:?} waiting for {} network responses; only {} completed",
+            WAIT_RESPONSES_TIMEOUT, min_responses, count
+        ));
+    }
+    if debug {
+        eprintln!("{} network responses have completed", count);
+    }
+    Ok(())
+}
+
+/// Wait until two consecutive screenshots, taken 250ms apart, are pixel-identical
+///
+/// A page still changing after [`STABLE_TIMEOUT`] is captured anyway, with a warning.
+async fn wait_until_stable(driver: &WebDriver, is_piped: bool, debug: bool) -> Result<()> {
+    if !is_piped {
+        eprintln!("Waiting for the page to stop changing...");
+        std::io::stderr().flush().ok();
+    }
     
-    Ok(driver)
+    let previous = std::sync::Mutex::new(None::<image::RgbaImage>);
+    let stable = poll_until(STABLE_TIMEOUT, || async {
+        let current = image::load_from_memory(&driver.screenshot_as_png().await?)?.to_rgba8();
+        let mut previous = previous.lock().unwrap();
+        let settled = previous.as_ref() == Some(&current);
+        *previous = Some(current);
+        Ok(settled)
+    }).await?;
+    if !stable && !is_piped {
+        eprintln!("{} {}", "!".yellow(), format!("The page was still changing after {:?}; capturing it anyway", STABLE_TIMEOUT).yellow());
+    } else if stable && debug {
+        eprintln!("The page has stopped changing");
+    }
+    Ok(())
+}
+
+/// User agent the browser reports to pages
+async fn browser_user_agent(driver: &WebDriver) -> Result<String> {
+    let user_agent = driver.execute("return navigator.userAgent;", vec![]).await?;
+    user_agent.json().as_str()
+        .map(str::to_string)
+        .context("The browser did not report a user agent")
+}
+
+/// Render the page for `media`, or for its normal media when `None`, and
+/// with `prefers-reduced-motion: reduce` when `reduced_motion` is set
+///
+/// Each call replaces the media and features of the last, so leaving both
+/// out restores the page's normal rendering.
+async fn emulate_media(driver: &WebDriver, media: Option<MediaType>, reduced_motion: bool) -> Result<()> {
+    let devtools = ChromeDevTools::new(driver.handle.clone());
+    let media = media.map(MediaType::cdp_name).unwrap_or("");
+    let features: Vec<serde_json::Value> = if reduced_motion {
+        vec![serde_json::json!({ "name": "prefers-reduced-motion", "value": "reduce" })]
+    } else {
+        Vec::new()
+    };
+    devtools.execute_cdp_with_params("Emulation.setEmulatedMedia", serde_json::json!({ "media": media, "features": features })).await?;
+    Ok(())
+}
+
+/// Render at `dpr` device pixels per CSS pixel, or the display's own ratio when `None`
+///
+/// Width and height are left at 0 so the viewport keeps following the window.
+async fn set_device_scale(driver: &WebDriver, dpr: Option<f64>) -> Result<()> {
+    let devtools = ChromeDevTools::new(driver.handle.clone());
+    match dpr {
+        Some(dpr) => {
+            let params = serde_json::json!({ "width": 0, "height": 0, "deviceScaleFactor": dpr, "mobile": false });
+            devtools.execute_cdp_with_params("Emulation.setDeviceMetricsOverride", params).await?;
+        }
+        None => {
+            devtools.execute_cdp("Emulation.clearDeviceMetricsOverride").await?;
+        }
+    }
+    Ok(())
+}
+
+/// Present the browser as using `locale`, or its own language when `None`
+///
+/// Pages see the locale in `Accept-Language`, with its base language as a
+/// fallback (`fr-CA,fr;q=0.9`), in `navigator.language` and as the default
+/// locale of `Intl` formatting.
+async fn emulate_locale(driver: &WebDriver, locale: Option<&str>) -> Result<()> {
+    let devtools = ChromeDevTools::new(driver.handle.clone());
+    let Some(locale) = locale else {
+        // An empty user agent drops the override, and with it the language
+        devtools.execute_cdp_with_params("Emulation.setUserAgentOverride", serde_json::json!({ "userAgent": "" })).await?;
+        devtools.execute_cdp_with_params("Emulation.setLocaleOverride", serde_json::json!({})).await?;
+        return Ok(());
+    };
+    let accept_language = match locale.split_once('-') {
+        Some((language, _)) => format!("{},{};q=0.9", locale, language),
+        None => locale.to_string(),
+    };
+    let user_agent = browser_user_agent(driver).await?;
+    devtools.execute_cdp_with_params(
+        "Emulation.setUserAgentOverride",
+        serde_json::json!({ "userAgent": user_agent, "acceptLanguage": accept_language }),
+    ).await?;
+    // Chrome refuses to replace one locale override with another directly
+    devtools.execute_cdp_with_params("Emulation.setLocaleOverride", serde_json::json!({})).await?;
+    devtools.execute_cdp_with_params("Emulation.setLocaleOverride", serde_json::json!({ "locale": locale.replace('-', "_") })).await?;
+    Ok(())
+}
+
+/// Paint the page over transparency instead of white where it sets no
+/// background, or restore the default
+async fn set_transparent_background(driver: &WebDriver, transparent: bool) -> Result<()> {
+    let devtools = ChromeDevTools::new(driver.handle.clone());
+    let params = if transparent {
+        serde_json::json!({ "color": { "r": 0, "g": 0, "b": 0, "a": 0 } })
+    } else {
+        serde_json::json!({})
+    };
+    devtools.execute_cdp_with_params("Emulation.setDefaultBackgroundColorOverride", params).await?;
+    Ok(())
+}
+
+/// Script run in every new document to freeze its clocks at `time` into its timeline
+///
+/// `performance.now()` always returns `time`, and `Date` always gives the
+/// matching wall-clock instant. Timers still fire, and `requestAnimationFrame`
+/// callbacks are still passed the real time.
+fn freeze_time_script(time: Duration) -> String {
+    format!(r#"
+        (() => {{
+            const frozen = {};
+            const wallClock = Math.round(performance.timeOrigin + frozen);
+            const RealDate = Date;
+            performance.now = () => frozen;
+            function FrozenDate(...args) {{
+                if (!new.target) {{
+                    return new RealDate(wallClock).toString();
+                }}
+                return new RealDate(...(args.length ? args : [wallClock]));
+            }}
+            FrozenDate.prototype = RealDate.prototype;
+            FrozenDate.now = () => wallClock;
+            FrozenDate.parse = RealDate.parse;
+            FrozenDate.UTC = RealDate.UTC;
+            window.Date = FrozenDate;
+        }})();
+    "#, time.as_millis())
+}
+
+/// Have every document loaded from now on freeze its clocks (see [`freeze_time_script`])
+///
+/// Returns the identifier to remove the script with.
+async fn install_time_freeze(driver: &WebDriver, time: Duration) -> Result<String> {
+    let devtools = ChromeDevTools::new(driver.handle.clone());
+    let result = devtools.execute_cdp_with_params(
+        "Page.addScriptToEvaluateOnNewDocument",
+        serde_json::json!({ "source": freeze_time_script(time) }),
+    ).await?;
+    Ok(result["identifier"].as_str().unwrap_or_default().to_string())
+}
+
+/// Stop freezing the clocks of documents loaded from now on
+async fn remove_time_freeze(driver: &WebDriver, identifier: String) -> Result<()> {
+    remove_script(driver, identifier).await
+}
+
+/// Stop running a script added with `Page.addScriptToEvaluateOnNewDocument`
+async fn remove_script(driver: &WebDriver, identifier: String) -> Result<()> {
+    let devtools = ChromeDevTools::new(driver.handle.clone());
+    devtools.execute_cdp_with_params(
+        "Page.removeScriptToEvaluateOnNewDocument",
+        serde_json::json!({ "identifier": identifier }),
+    ).await?;
+    Ok(())
+}
+
+/// Have every document loaded from now on report its tab as visible and focused
+///
+/// `document.visibilityState` and `document.hidden` always say visible, and
+/// visibility change events no longer reach the page. Chrome's focus emulation
+/// makes the page count as focused too. Returns the identifier to remove the
+/// script with.
+async fn install_visibility_override(driver: &WebDriver) -> Result<String> {
+    let script = r#"
+        (() => {
+            const visible = { visibilityState: 'visible', webkitVisibilityState: 'visible', hidden: false, webkitHidden: false };
+            for (const [name, value] of Object.entries(visible)) {
+                Object.defineProperty(Document.prototype, name, { get: () => value, configurable: true });
+            }
+            Document.prototype.hasFocus = () => true;
+            for (const type of ['visibilitychange', 'webkitvisibilitychange']) {
+                window.addEventListener(type, event => event.stopImmediatePropagation(), true);
+            }
+        })();
+    "#;
+    let devtools = ChromeDevTools::new(driver.handle.clone());
+    devtools.execute_cdp_with_params("Emulation.setFocusEmulationEnabled", serde_json::json!({ "enabled": true })).await?;
+    let result = devtools.execute_cdp_with_params(
+        "Page.addScriptToEvaluateOnNewDocument",
+        serde_json::json!({ "source": script }),
+    ).await?;
+    Ok(result["identifier"].as_str().unwrap_or_default().to_string())
+}
+
+/// Undo [`install_visibility_override`] for later captures in the session
+async fn remove_visibility_override(driver: &WebDriver, identifier: String) -> Result<()> {
+    let devtools = ChromeDevTools::new(driver.handle.clone());
+    devtools.execute_cdp_with_params("Emulation.setFocusEmulationEnabled", serde_json::json!({ "enabled": false })).await?;
+    remove_script(driver, identifier).await
+}
+
+/// Have every document loaded from now on render each substituted family
+/// with its replacement
+///
+/// Each family is added to `document.fonts` as a font face whose source is
+/// the installed replacement, which takes precedence over installed fonts of
+/// that name. Returns the identifier to remove the script with.
+async fn install_font_substitutes(driver: &WebDriver, substitutes: &[FontSubstitute]) -> Result<String> {
+    let substitutes: Vec<(&str, &str)> = substitutes.iter()
+        .map(|substitute| (substitute.family.as_str(), substitute.replacement.as_str()))
+        .collect();
+    let script = format!(r#"
+        (() => {{
+            const missing = [];
+            Object.defineProperty(window, '__weblookMissingFonts', {{ value: missing, configurable: true }});
+            for (const [family, replacement] of {}) {{
+                const face = new FontFace(family, `local(${{JSON.stringify(replacement)}})`);
+                document.fonts.add(face);
+                face.load().catch(() => missing.includes(replacement) || missing.push(replacement));
+            }}
+        }})();
+    "#, serde_json::to_string(&substitutes)?);
+    let devtools = ChromeDevTools::new(driver.handle.clone());
+    let result = devtools.execute_cdp_with_params(
+        "Page.addScriptToEvaluateOnNewDocument",
+        serde_json::json!({ "source": script }),
+    ).await?;
+    Ok(result["identifier"].as_str().unwrap_or_default().to_string())
+}
+
+/// Warn about replacement fonts that aren't installed, which leave their
+/// family to Chrome's usual fallback
+async fn report_missing_fonts(driver: &WebDriver, is_piped: bool) -> Result<()> {
+    let missing = driver.execute("return window.__weblookMissingFonts || [];", vec![]).await?;
+    if is_piped {
+        return Ok(());
+    }
+    for font in missing.json().as_array().into_iter().flatten().filter_map(|font| font.as_str()) {
+        eprintln!("{} {}", "!".yellow(), format!("Font {} is not installed, so it could not be substituted", font).yellow());
+    }
+    Ok(())
+}
+
+/// Keep a list of the dialogs each document loaded from now on opens
+///
+/// ChromeDriver answers dialogs without saying so, so their messages are
+/// noted as they open, for [`report_dialogs`] to log.
+async fn install_dialog_log(driver: &WebDriver) -> Result<String> {
+    let script = r#"
+        (() => {
+            const dialogs = [];
+            Object.defineProperty(window, '__weblookDialogs', { value: dialogs, configurable: true });
+            for (const kind of ['alert', 'confirm', 'prompt']) {
+                const open = window[kind];
+                window[kind] = function (message) {
+                    dialogs.push({ kind, message: String(message ?? '') });
+                    return open.apply(this, arguments);
+                };
+            }
+        })();
+    "#;
+    let devtools = ChromeDevTools::new(driver.handle.clone());
+    let result = devtools.execute_cdp_with_params(
+        "Page.addScriptToEvaluateOnNewDocument",
+        serde_json::json!({ "source": script }),
+    ).await?;
+    Ok(result["identifier"].as_str().unwrap_or_default().to_string())
+}
+
+/// Log the dialogs the page has opened since the last call, and how they were answered
+async fn report_dialogs(driver: &WebDriver, action: DialogAction, is_piped: bool) -> Result<()> {
+    let dialogs = driver.execute("return window.__weblookDialogs ? window.__weblookDialogs.splice(0) : [];", vec![]).await?;
+    let Some(dialogs) = dialogs.json().as_array().filter(|dialogs| !dialogs.is_empty()).cloned() else {
+        return Ok(());
+    };
+    if is_piped {
+        return Ok(());
+    }
+    for dialog in dialogs {
+        let kind = dialog["kind"].as_str().unwrap_or("dialog");
+        let message = dialog["message"].as_str().unwrap_or_default();
+        eprintln!("{} {}", "!".yellow(), format!("{} {} dialog: {}", action.past_tense(), kind, message).yellow());
+    }
+    Ok(())
+}
+
+/// Pause every CSS animation and transition, and any other Web Animation, `time` into it
+async fn pause_animations(driver: &WebDriver, time: Duration) -> Result<()> {
+    let script = r#"
+        for (const animation of document.getAnimations()) {
+            animation.pause();
+            animation.currentTime = arguments[0];
+        }
+    "#;
+    driver.execute(script, vec![serde_json::json!(time.as_millis() as u64)]).await?;
+    Ok(())
+}
+
+async fn execute_javascript(driver: &WebDriver, js_code: &str) -> Result<()> {
+    // Execute the JavaScript code
+    driver.execute(js_code, vec![]).await?;
+    
+    // Give a short time for any JS effects to complete
+    sleep(Duration::from_millis(500)).await;
+    
+    Ok(())
+}
+
+async fn scroll_to_target(driver: &WebDriver, target: &ScrollTarget) -> Result<()> {
+    match target {
+        ScrollTarget::Pixels(offset) => {
+            driver.execute("window.scrollTo(0, arguments[0]);", vec![serde_json::json!(offset)]).await?;
+        }
+        ScrollTarget::Selector(selector) => {
+            let script = r#"
+                const element = document.querySelector(arguments[0]);
+                if (!element) return false;
+                element.scrollIntoView({ block: 'start' });
+                return true;
+            "#;
+            let found = driver.execute(script, vec![serde_json::json!(selector)]).await?;
+            if !found.json().as_bool().unwrap_or(false) {
+                return Err(anyhow::anyhow!("No element matches scroll target {}", selector));
+            }
+        }
+    }
+    
+    // Give lazily loaded content a moment to appear
+    sleep(Duration::from_millis(500)).await;
+    
+    Ok(())
+}
+
+/// Open a capture, going by its contents rather than its extension, which
+/// needn't name the format it was written in (see [`output_format`])
+pub fn open_capture(path: &Path) -> Result<image::ImageReader<io::BufReader<fs::File>>> {
+    Ok(image::ImageReader::open(path)?.with_guessed_format()?)
+}
+
+/// Create an output file, refusing to replace an existing one when `no_clobber` is set
+pub fn create_output_file(path: &Path, no_clobber: bool) -> Result<fs::File> {
+    if !no_clobber {
+        return Ok(fs::File::create(path)?);
+    }
+    
+    fs::OpenOptions::new().write(true).create_new(true).open(path)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => anyhow::anyhow!("{} already exists and no-clobber is set", path.display()),
+            _ => e.into(),
+        })
+}
+
+async fn take_screenshot(driver: &WebDriver, output_path: &Path, region: Option<&ScreenshotRegion>, label: Option<&Label>, options: &CaptureOptions, is_piped: bool) -> Result<()> {
+    // Take screenshot
+    if !is_piped && !options.debug {
+        eprintln!("{}", "Taking screenshot...".bright_cyan());
+        std::io::stderr().flush().ok();
+    }
+    
+    let screenshot = screenshot_region_png(driver, region, is_piped).await?;
+    
+    // Apply the colour effects, stamp the label onto the image and frame it
+    let screenshot = options.effects.apply_png(screenshot)?;
+    let screenshot = polish_png(stamp_label(screenshot, label)?, options.polish.as_ref())?;
+    
+    write_screenshot(&screenshot, output_path, options, is_piped)
+}
+
+/// Screenshot `region` of the page, or the whole viewport, as a PNG
+async fn screenshot_region_png(driver: &WebDriver, region: Option<&ScreenshotRegion>, is_piped: bool) -> Result<Vec<u8>> {
+    let screenshot = match region {
+        Some(ScreenshotRegion::Frame(frame)) => {
+            let result = screenshot_frame(driver, frame).await;
+            // Always return to the top-level document
+            driver.enter_default_frame().await?;
+            result?
+        }
+        Some(ScreenshotRegion::Element(selector)) => driver.find(By::Css(selector.as_str())).await
+            .with_context(|| format!("No element matches selector: {}", selector))?
+            .screenshot_as_png().await?,
+        Some(ScreenshotRegion::Between(start, end)) => screenshot_between(driver, start, end, is_piped).await?,
+        None => driver.screenshot_as_png().await?,
+    };
+    check_screenshot(&screenshot)?;
+    Ok(screenshot)
+}
+
+/// Check that a screenshot is a PNG that decodes and has pixels in it
+///
+/// A crashed renderer can return empty or truncated data, which would
+/// otherwise be written out as a broken file.
+pub fn check_screenshot(png: &[u8]) -> Result<()> {
+    let image = image::load_from_memory_with_format(png, image::ImageFormat::Png)
+        .with_context(|| format!("The browser returned a broken screenshot ({} bytes); its renderer may have crashed", png.len()))?;
+    if image.width() == 0 || image.height() == 0 {
+        return Err(anyhow::anyhow!(
+            "The browser returned an empty {}x{} screenshot; its renderer may have crashed",
+            image.width(), image.height()
+        ));
+    }
+    Ok(())
+}
+
+/// Write a finished PNG screenshot to `output_path` or stdout
+fn write_screenshot(screenshot: &[u8], output_path: &Path, options: &CaptureOptions, is_piped: bool) -> Result<()> {
+    let (no_clobber, debug) = (options.no_clobber, options.debug);
+    let screenshot = encode_screenshot(screenshot, output_format(options))?;
+    if output_path.to_str() == Some("-") {
+        // Write to stdout
+        io::stdout().write_all(&screenshot)?;
+    } else {
+        // Write to file
+        create_output_file(output_path, no_clobber)?.write_all(&screenshot)?;
+        
+        if !is_piped && !debug {
+            eprintln!("{} {}", "✓".green(), format!("Screenshot saved to {}", output_path.display()).bright_green());
+            std::io::stderr().flush().ok();
+        } else if !is_piped && debug {
+            eprintln!("Screenshot saved to {}", output_path.display());
+        }
+    }
+    
+    Ok(())
+}
+
+/// Capture every state of the page and write them side by side as one sprite sheet
+///
+/// Before each frame, `state_js` is run with the state's index. Returns the
+/// individual frames saved with `state_frames`.
+async fn take_state_sheet(driver: &WebDriver, output_path: &Path, region: Option<&ScreenshotRegion>, label: Option<&Label>, options: &CaptureOptions, is_piped: bool) -> Result<Vec<PathBuf>> {
+    let states = options.states.unwrap_or(1);
+    let state_js = options.state_js.as_deref().unwrap_or_default();
+    if !is_piped && !options.debug {
+        eprintln!("{}", format!("Capturing {} states...", states).bright_cyan());
+        std::io::stderr().flush().ok();
+    }
+    
+    let mut frames = Vec::new();
+    let mut paths = Vec::new();
+    for index in 0..states {
+        driver.execute(state_js, vec![serde_json::json!(index)]).await
+            .with_context(|| format!("The state script failed for state {}", index))?;
+        // Give the state's styles and transitions a moment to apply
+        sleep(Duration::from_millis(500)).await;
+        
+        let screenshot = options.effects.apply_png(screenshot_region_png(driver, region, is_piped).await?)?;
+        if options.state_frames {
+            let path = numbered_path(output_path, index as usize);
+            let frame = polish_png(screenshot.clone(), options.polish.as_ref())?;
+            create_output_file(&path, options.no_clobber)?.write_all(&encode_screenshot(&frame, output_format(options))?)?;
+            if options.debug {
+                eprintln!("State {} saved to {}", index, path.display());
+            }
+            paths.push(path);
+        }
+        frames.push(image::load_from_memory(&screenshot)?.to_rgba8());
+    }
+    
+    let sheet = montage::build_sprite_sheet(&frames)?;
+    let mut png = Vec::new();
+    sheet.write_to(&mut io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+    write_screenshot(&polish_png(stamp_label(png, label)?, options.polish.as_ref())?, output_path, options, is_piped)?;
+    Ok(paths)
+}
+
+/// Check that the states of a sprite sheet can be captured as asked
+fn check_states(options: &CaptureOptions) -> Result<()> {
+    let Some(states) = options.states else {
+        if options.state_js.is_some() || options.state_frames {
+            return Err(anyhow::anyhow!("A state script and state frames need a number of states"));
+        }
+        return Ok(());
+    };
+    if states == 0 {
+        return Err(anyhow::anyhow!("A sprite sheet needs at least 1 state"));
+    }
+    if options.state_js.is_none() {
+        return Err(anyhow::anyhow!("A sprite sheet needs a script to advance to each state"));
+    }
+    if options.is_recording || options.pdf.is_some() || options.each {
+        return Err(anyhow::anyhow!("A sprite sheet cannot be combined with recordings, PDFs or capturing each match"));
+    }
+    if options.state_frames && options.output_path.to_str() == Some("-") {
+        return Err(anyhow::anyhow!("State frames are saved as numbered files, so they cannot be written to stdout"));
+    }
+    Ok(())
+}
+
+/// How long the page is left to settle after each resize of a resize sequence
+const DEFAULT_RESIZE_SETTLE: Duration = Duration::from_millis(500);
+
+/// Check that a resize sequence is only combined with what it supports
+fn check_resize_sequence(options: &CaptureOptions) -> Result<()> {
+    if options.resize_sequence.is_empty() {
+        if options.resize_settle.is_some() {
+            return Err(anyhow::anyhow!("A resize settle delay needs a resize sequence"));
+        }
+        return Ok(());
+    }
+    if options.resize_sequence.iter().any(|size| size.width == 0 || size.height == 0) {
+        return Err(anyhow::anyhow!("Every size of a resize sequence must be at least 1x1"));
+    }
+    if options.is_recording || options.pdf.is_some() || options.each || options.states.is_some() {
+        return Err(anyhow::anyhow!("A resize sequence cannot be combined with recordings, PDFs, capturing each match or states"));
+    }
+    if options.output_path.to_str() == Some("-") {
+        return Err(anyhow::anyhow!("A resize sequence writes a file per size, so it cannot write to stdout"));
+    }
+    Ok(())
+}
+
+/// Resize the window of the loaded page to each size of the resize sequence
+/// in turn, screenshotting it at each into a file named after the size
+///
+/// Unlike capturing at several sizes, the page is only loaded once, so its
+/// resize handlers and media queries are exercised as they would be live.
+async fn take_resize_sequence(driver: &WebDriver, output_path: &Path, region: Option<&ScreenshotRegion>, label: Option<&Label>, options: &CaptureOptions, is_piped: bool) -> Result<Vec<PathBuf>> {
+    let settle = options.resize_settle.unwrap_or(DEFAULT_RESIZE_SETTLE);
+    let position = options.window_position;
+    
+    let mut paths = Vec::new();
+    for size in &options.resize_sequence {
+        if !is_piped && !options.debug {
+            eprintln!("{}", format!("Resizing to {}x{}...", size.width, size.height).bright_cyan());
+            std::io::stderr().flush().ok();
+        }
+        driver.set_window_rect(position.x as i64, position.y as i64, size.width, size.height).await
+            .with_context(|| format!("Failed to resize the window to {}x{}", size.width, size.height))?;
+        sleep(settle).await;
+        
+        let screenshot = screenshot_region_png(driver, region, is_piped).await?;
+        check_screenshot(&screenshot)
+            .with_context(|| format!("Failed to capture the page at {}x{}", size.width, size.height))?;
+        let screenshot = polish_png(stamp_label(options.effects.apply_png(screenshot)?, label)?, options.polish.as_ref())?;
+        let path = size_path(output_path, *size);
+        create_output_file(&path, options.no_clobber)?.write_all(&encode_screenshot(&screenshot, output_format(options))?)?;
+        if options.debug {
+            eprintln!("Screenshot saved to {}", path.display());
+        }
+        paths.push(path);
+    }
+    
+    if !is_piped && !options.debug {
+        eprintln!("{} {}", "✓".green(), format!(
+            "{} screenshots saved to {} to {}",
+            paths.len(), paths[0].display(), paths[paths.len() - 1].display()
+        ).bright_green());
+        std::io::stderr().flush().ok();
+    }
+    Ok(paths)
+}
+
+/// Path of the screenshot taken at `size` in a resize sequence, e.g. `home-375x667.png` for `home.png`
+pub fn size_path(output_path: &Path, size: ViewportSize) -> PathBuf {
+    let extension = output_path.extension().and_then(|e| e.to_str()).unwrap_or("png");
+    output_path.with_file_name(format!("{}-{}x{}.{}", output_stem(output_path), size.width, size.height, extension))
 }
 
-async fn navigate_and_wait(driver: &WebDriver, url: Url, wait_time: Duration, is_piped: bool, debug: bool) -> Result<()> {
-    // Navigate to the URL
-    driver.goto(url.as_str()).await?;
+/// Screenshot every element matching `selector` into its own numbered file
+///
+/// Returns the files written, which is none when nothing matches.
+async fn take_element_screenshots(driver: &WebDriver, output_path: &Path, selector: &str, label: Option<&Label>, options: &CaptureOptions, is_piped: bool) -> Result<Vec<PathBuf>> {
+    let elements = driver.find_all(By::Css(selector)).await?;
+    if elements.is_empty() {
+        if !is_piped {
+            eprintln!("{} {}", "!".yellow(), format!("No elements match {}; nothing was captured", selector).yellow());
+        }
+        return Ok(Vec::new());
+    }
     
-    // Wait for the specified time with a nice countdown
-    if !is_piped {
-        // Force flush stdout to ensure messages appear
-        eprintln!("Page loaded. Waiting for {} seconds...", wait_time.as_secs());
+    if !is_piped && !options.debug {
+        eprintln!("{}", format!("Taking {} screenshots...", elements.len()).bright_cyan());
         std::io::stderr().flush().ok();
-        
-        display_countdown(wait_time, "Loading page", debug).await;
-    } else {
-        sleep(wait_time).await;
     }
     
-    Ok(())
+    let mut paths = Vec::new();
+    for (index, element) in elements.iter().enumerate() {
+        let screenshot = element.screenshot_as_png().await?;
+        check_screenshot(&screenshot)
+            .with_context(|| format!("Failed to capture element {} of {}", index, selector))?;
+        let screenshot = polish_png(stamp_label(options.effects.apply_png(screenshot)?, label)?, options.polish.as_ref())?;
+        let path = numbered_path(output_path, index);
+        create_output_file(&path, options.no_clobber)?.write_all(&encode_screenshot(&screenshot, output_format(options))?)?;
+        if options.debug {
+            eprintln!("Screenshot saved to {}", path.display());
+        }
+        paths.push(path);
+    }
+    
+    if !is_piped && !options.debug {
+        eprintln!("{} {}", "✓".green(), format!(
+            "{} screenshots saved to {} to {}",
+            paths.len(), paths[0].display(), paths[paths.len() - 1].display()
+        ).bright_green());
+        std::io::stderr().flush().ok();
+    }
+    Ok(paths)
 }
 
-// Display a colorful countdown timer
-async fn display_countdown(duration: Duration, message: &str, debug: bool) {
-    if !debug {
-        eprintln!("Starting countdown: {} for {} seconds", message, duration.as_secs());
-        std::io::stderr().flush().ok();
-        
-        let pb = ProgressBar::new(duration.as_secs());
-        pb.set_style(ProgressStyle::default_bar()
-            .template("{spinner:.green} {msg} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len}s")
-            .unwrap()
-            .progress_chars("#>-"));
-        
-        pb.set_message(message.bright_green().to_string());
-        
-        for i in 1..=duration.as_secs() {
-            // Rainbow text for the countdown
-            match i % 6 {
-                0 => pb.set_message(message.red().to_string()),
-                1 => pb.set_message(message.yellow().to_string()),
-                2 => pb.set_message(message.green().to_string()),
-                3 => pb.set_message(message.cyan().to_string()),
-                4 => pb.set_message(message.blue().to_string()),
-                _ => pb.set_message(message.magenta().to_string()),
+/// Path of the `index`th of several images saved for one capture, e.g. `card-0.png` for `card.png`
+pub fn numbered_path(output_path: &Path, index: usize) -> PathBuf {
+    let extension = output_path.extension().and_then(|e| e.to_str()).unwrap_or("png");
+    output_path.with_file_name(format!("{}-{}.{}", output_stem(output_path), index, extension))
+}
+
+/// Stamp `label`, if any, onto a PNG screenshot
+fn stamp_label(screenshot: Vec<u8>, label: Option<&Label>) -> Result<Vec<u8>> {
+    let Some(label) = label else {
+        return Ok(screenshot);
+    };
+    let mut image = image::load_from_memory(&screenshot)?.to_rgba8();
+    label.draw(&mut image);
+    let mut png = Vec::new();
+    image.write_to(&mut io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+    Ok(png)
+}
+
+/// Frame a PNG screenshot for presentation with `polish`, if any
+fn polish_png(screenshot: Vec<u8>, polish: Option<&Polish>) -> Result<Vec<u8>> {
+    let Some(polish) = polish else {
+        return Ok(screenshot);
+    };
+    let image = polish.apply(&image::load_from_memory(&screenshot)?.to_rgba8());
+    let mut png = Vec::new();
+    image.write_to(&mut io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+    Ok(png)
+}
+
+/// Re-encode a PNG screenshot as `format`
+///
+/// JPEG drops the alpha channel, and WebP is encoded losslessly.
+pub fn encode_screenshot(png: &[u8], format: StdoutFormat) -> Result<Vec<u8>> {
+    let mut encoded = Vec::new();
+    match format {
+        StdoutFormat::Png => encoded.extend_from_slice(png),
+        StdoutFormat::Jpeg => image::load_from_memory(png)?.to_rgb8().write_to(&mut io::Cursor::new(&mut encoded), image::ImageFormat::Jpeg)?,
+        StdoutFormat::Webp => {
+            let rgba = image::load_from_memory(png)?.to_rgba8();
+            encoded.extend_from_slice(&webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height()).encode_lossless());
+        }
+        StdoutFormat::Gif => return Err(anyhow::anyhow!("Screenshots cannot be encoded as GIF")),
+    }
+    Ok(encoded)
+}
+
+/// Save the page's favicon next to the output, returning where it was saved
+///
+/// A page without a favicon, or one that can't be fetched, only gets a warning.
+async fn save_favicon(driver: &WebDriver, output_path: &Path, domain_filter: Option<&DomainFilter>, no_clobber: bool, is_piped: bool) -> Option<PathBuf> {
+    let saved = async {
+        let (data, extension) = fetch_favicon(driver, domain_filter).await?;
+        let path = favicon_path(output_path, extension);
+        create_output_file(&path, no_clobber)?.write_all(&data)
+            .with_context(|| format!("Failed to save favicon to {}", path.display()))?;
+        Ok::<_, anyhow::Error>(path)
+    };
+    match saved.await {
+        Ok(path) => {
+            if !is_piped {
+                eprintln!("Favicon saved to {}", path.display());
             }
-            
-            pb.set_position(i);
-            sleep(Duration::from_secs(1)).await;
+            Some(path)
+        }
+        Err(e) => {
+            if !is_piped {
+                eprintln!("{} {}", "!".yellow(), format!("No favicon saved: {:#}", e).yellow());
+            }
+            None
         }
-        
-        pb.finish_with_message(format!("{} complete!", message).green().to_string());
-    } else {
-        eprintln!("Waiting for {} seconds...", duration.as_secs());
-        sleep(duration).await;
     }
 }
 
-async fn execute_javascript(driver: &WebDriver, js_code: &str) -> Result<()> {
-    // Execute the JavaScript code
-    driver.execute(js_code, vec![]).await?;
+/// Download the page's favicon, returning its data and file extension
+///
+/// The icon is taken from `<link rel="icon">`, falling back to `/favicon.ico`.
+async fn fetch_favicon(driver: &WebDriver, domain_filter: Option<&DomainFilter>) -> Result<(Vec<u8>, &'static str)> {
+    let script = r#"
+        const link = document.querySelector('link[rel~="icon" i][href]');
+        return new URL(link ? link.getAttribute('href') : '/favicon.ico', document.baseURI).href;
+    "#;
+    let href = driver.execute(script, vec![]).await?.json().as_str()
+        .map(str::to_string)
+        .context("The page did not report a favicon URL")?;
+    let url = Url::parse(&href)?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(anyhow::anyhow!("the favicon is not served over HTTP"));
+    }
+    if let (Some(filter), Some(host)) = (domain_filter, url.host_str())
+        && !filter.permits(host)
+    {
+        return Err(anyhow::anyhow!("{} is blocked by the domain filter", host));
+    }
     
-    // Give a short time for any JS effects to complete
-    sleep(Duration::from_millis(500)).await;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?;
+    let response = client.get(url.clone()).send().await?.error_for_status()?;
+    let content_type = response.headers().get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("")
+        .to_lowercase();
+    // Servers often answer a missing /favicon.ico with an HTML page
+    if content_type.starts_with("text/html") {
+        return Err(anyhow::anyhow!("{} is not an image", url));
+    }
+    let data = response.bytes().await?.to_vec();
+    if data.is_empty() {
+        return Err(anyhow::anyhow!("{} is empty", url));
+    }
     
-    Ok(())
+    Ok((data, favicon_extension(&content_type, &url)))
 }
 
-async fn take_screenshot(driver: &WebDriver, output_path: &PathBuf, is_piped: bool, debug: bool) -> Result<()> {
-    // Take screenshot
+/// File extension for a favicon, from its content type or else its URL
+fn favicon_extension(content_type: &str, url: &Url) -> &'static str {
+    let essence = content_type.split(';').next().unwrap_or("").trim();
+    let path = url.path().to_lowercase();
+    match essence {
+        "image/png" => "png",
+        "image/svg+xml" => "svg",
+        "image/gif" => "gif",
+        "image/jpeg" => "jpg",
+        "image/webp" => "webp",
+        "image/x-icon" | "image/vnd.microsoft.icon" => "ico",
+        _ => ["png", "svg", "gif", "jpg", "webp"].into_iter()
+            .find(|extension| path.ends_with(&format!(".{}", extension)))
+            .unwrap_or("ico"),
+    }
+}
+
+/// Screenshot the region of the page covered by an iframe
+async fn screenshot_frame(driver: &WebDriver, frame: &FramePath) -> Result<Vec<u8>> {
+    let element = match frame {
+        FramePath::Indices(indices) => {
+            // Enter every frame but the last, then locate the last one as an element
+            let (last, parents) = indices.split_last()
+                .context("Frame path must not be empty")?;
+            for index in parents {
+                driver.enter_frame(*index).await
+                    .with_context(|| format!("Failed to enter frame {}", index))?;
+            }
+            let frames = driver.find_all(By::Css("iframe, frame")).await?;
+            frames.into_iter().nth(*last as usize)
+                .with_context(|| format!("No frame at index {}", last))?
+        }
+        FramePath::Selector(selector) => driver.find(By::Css(selector.as_str())).await
+            .with_context(|| format!("No iframe matches selector: {}", selector))?,
+    };
+    
+    Ok(element.screenshot_as_png().await?)
+}
+
+/// Screenshot the viewport cropped from the top of the element matching
+/// `start` to the bottom of the element matching `end`
+///
+/// The region spans the full width of both elements and is clipped to the
+/// viewport.
+async fn screenshot_between(driver: &WebDriver, start: &str, end: &str, is_piped: bool) -> Result<Vec<u8>> {
+    let script = r#"
+        const start = document.querySelector(arguments[0]);
+        const end = document.querySelector(arguments[1]);
+        if (!start) return { missing: arguments[0] };
+        if (!end) return { missing: arguments[1] };
+        const a = start.getBoundingClientRect();
+        const b = end.getBoundingClientRect();
+        return {
+            left: Math.min(a.left, b.left),
+            top: a.top,
+            right: Math.max(a.right, b.right),
+            bottom: b.bottom,
+            scale: window.devicePixelRatio || 1
+        };
+    "#;
+    let rect = driver.execute(script, vec![serde_json::json!(start), serde_json::json!(end)]).await?.json().clone();
+    if let Some(missing) = rect["missing"].as_str() {
+        return Err(anyhow::anyhow!("No element matches selector: {}", missing));
+    }
+    let edge = |name: &str| rect[name].as_f64().unwrap_or(0.0) * rect["scale"].as_f64().unwrap_or(1.0);
+    let (left, top, right, bottom) = (edge("left"), edge("top"), edge("right"), edge("bottom"));
+    if bottom <= top {
+        return Err(anyhow::anyhow!("The bottom of {} is above the top of {}", end, start));
+    }
+
+    // Screenshots only cover the viewport, so clip the region to it
+    let image = image::load_from_memory(&driver.screenshot_as_png().await?)?.to_rgba8();
+    let clamp = |value: f64, max: u32| value.round().clamp(0.0, max as f64) as u32;
+    let (x0, x1) = (clamp(left, image.width()), clamp(right, image.width()));
+    let (y0, y1) = (clamp(top, image.height()), clamp(bottom, image.height()));
+    if x1 <= x0 || y1 <= y0 {
+        return Err(anyhow::anyhow!("The region between {} and {} is outside the viewport; scroll to it or use a larger --size", start, end));
+    }
+    if !is_piped && (x0 as f64 > left.round() || y0 as f64 > top.round() || (x1 as f64) < right.round() || (y1 as f64) < bottom.round()) {
+        eprintln!("{} {}", "!".yellow(), format!("The region between {} and {} extends past the viewport and was clipped", start, end).yellow());
+    }
+
+    let cropped = image::imageops::crop_imm(&image, x0, y0, x1 - x0, y1 - y0).to_image();
+    let mut png = Vec::new();
+    cropped.write_to(&mut io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+    Ok(png)
+}
+
+async fn export_pdf(driver: &WebDriver, paper_size: &PaperSize, output_path: &Path, no_clobber: bool, is_piped: bool, debug: bool) -> Result<()> {
     if !is_piped && !debug {
-        eprintln!("{}", "Taking screenshot...".bright_cyan());
+        eprintln!("{}", "Exporting PDF...".bright_cyan());
         std::io::stderr().flush().ok();
     }
     
-    let screenshot = driver.screenshot_as_png().await?;
+    let parameters = PrintParameters {
+        page: PrintPage {
+            width: paper_size.width,
+            height: paper_size.height,
+        },
+        background: true,
+        ..Default::default()
+    };
+    
+    let pdf = driver.print_page(parameters).await
+        .context("Failed to print page to PDF. PDF export requires headless Chrome.")?;
     
     // Handle output
     if output_path.to_str() == Some("-") {
         // Write to stdout
-        io::stdout().write_all(&screenshot)?;
+        io::stdout().write_all(&pdf)?;
     } else {
         // Write to file
-        std::fs::write(output_path, screenshot)?;
+        create_output_file(output_path, no_clobber)?.write_all(&pdf)?;
         
         if !is_piped && !debug {
-            eprintln!("{} {}", "✓".green(), format!("Screenshot saved to {}", output_path.display()).bright_green());
+            eprintln!("{} {}", "✓".green(), format!("PDF saved to {}", output_path.display()).bright_green());
             std::io::stderr().flush().ok();
         } else if !is_piped && debug {
-            eprintln!("Screenshot saved to {}", output_path.display());
+            eprintln!("PDF saved to {}", output_path.display());
         }
     }
     
     Ok(())
 }
 
-async fn create_recording(driver: &WebDriver, duration_secs: u64, output_path: &PathBuf, is_piped: bool, debug: bool) -> Result<()> {
-    // Create a temporary directory for frames
-    let temp_dir = tempfile::tempdir()?;
-    let frames_per_second = 10;
-    let total_frames = duration_secs * frames_per_second;
-    let frame_delay = Duration::from_millis(1000 / frames_per_second);
+async fn create_recording(driver: &WebDriver, settings: &RecordingSettings, output_path: &Path, is_piped: bool, options: &CaptureOptions) -> Result<RecordingStats> {
+    let (no_clobber, debug) = (options.no_clobber, options.debug);
+    let stop = options.stop_recording.as_deref();
+    
+    // Create somewhere to keep the frames
+    let store = FrameStore::new(options.frame_storage)?;
+    let duration_secs = settings.duration_secs;
+    let frames_per_second = settings.capture_fps as u64;
+    // On-change recordings check for changes at the capture rate, and the
+    // frame cap limits the frames kept rather than the checks
+    let total_frames = if settings.on_change { duration_secs * frames_per_second } else { settings.total_frames() };
+    let stopped = || stop.is_some_and(|stop| stop.load(Ordering::SeqCst));
+    let report_frame = |captured: u64| {
+        if let Some(callback) = &options.on_frame {
+            callback(captured, total_frames);
+        }
+        emit(options, CaptureEvent::Frame { captured, total: total_frames });
+    };
+    
+    // Warn when the frame cap cuts the recording short
+    if settings.total_frames() < duration_secs * frames_per_second && !settings.on_change && !is_piped {
+        eprintln!("{} {}", "!".yellow(), format!(
+            "Frame cap: recording will stop after {} frames ({:.1}s of the {} seconds)",
+            total_frames, total_frames as f64 / frames_per_second as f64, duration_secs
+        ).yellow());
+    }
     
     // Capture frames
     let mut frames = Vec::new();
+    let mut dropped = 0;
+    let clock = FrameClock::new(settings.capture_fps);
+    let mut changes = settings.on_change.then(|| FrameChanges::new(settings.max_frames));
     
     if !is_piped {
         if !debug {
@@ -340,11 +5063,17 @@ async fn create_recording(driver: &WebDriver, duration_secs: u64, output_path: &
             pb.set_message("Recording".bright_green().to_string());
             
             for i in 0..total_frames {
-                // Take screenshot
-                let screenshot_data = driver.screenshot_as_png().await?;
-                let frame_path = temp_dir.path().join(format!("frame_{:04}.png", i));
-                std::fs::write(&frame_path, screenshot_data)?;
-                frames.push(frame_path);
+                if stopped() {
+                    break;
+                }
+                
+                // Take screenshot, unless capture has fallen behind
+                if record_frame(driver, &store, i, &clock, &mut frames, changes.as_mut()).await? {
+                    dropped += 1;
+                }
+
+                // Report progress to any listener
+                report_frame(i + 1);
                 
                 // Update progress bar with rainbow colors every second
                 if i % frames_per_second == 0 {
@@ -362,58 +5091,311 @@ async fn create_recording(driver: &WebDriver, duration_secs: u64, output_path: &
                 }
                 
                 // Wait for next frame
-                sleep(frame_delay).await;
+                clock.wait_for(i + 1).await;
             }
             
-            pb.finish_with_message("Recording complete!".green().to_string());
-            eprintln!("{}", "Creating GIF...".bright_cyan());
+            if stopped() {
+                pb.abandon_with_message("Recording stopped".yellow().to_string());
+            } else {
+                pb.finish_with_message("Recording complete!".green().to_string());
+            }
+            eprintln!("{}", format!("Creating {}...", settings.format.label()).bright_cyan());
             std::io::stderr().flush().ok();
         } else {
             eprintln!("Recording for {} seconds...", duration_secs);
             for i in 0..total_frames {
-                // Take screenshot
-                let screenshot_data = driver.screenshot_as_png().await?;
-                let frame_path = temp_dir.path().join(format!("frame_{:04}.png", i));
-                std::fs::write(&frame_path, screenshot_data)?;
-                frames.push(frame_path);
+                if stopped() {
+                    break;
+                }
+                
+                // Take screenshot, unless capture has fallen behind
+                if record_frame(driver, &store, i, &clock, &mut frames, changes.as_mut()).await? {
+                    dropped += 1;
+                }
+
+                // Report progress to any listener
+                report_frame(i + 1);
                 
                 // Wait for next frame
-                sleep(frame_delay).await;
+                clock.wait_for(i + 1).await;
             }
-            eprintln!("Recording complete. Creating GIF...");
+            eprintln!("Recording complete. Creating {}...", settings.format.label());
         }
     } else {
         for i in 0..total_frames {
-            // Take screenshot
-            let screenshot_data = driver.screenshot_as_png().await?;
-            let frame_path = temp_dir.path().join(format!("frame_{:04}.png", i));
-            std::fs::write(&frame_path, screenshot_data)?;
-            frames.push(frame_path);
+            if stopped() {
+                break;
+            }
+            
+            // Take screenshot, unless capture has fallen behind
+            if record_frame(driver, &store, i, &clock, &mut frames, changes.as_mut()).await? {
+                dropped += 1;
+            }
+
+            // Report progress to any listener
+            report_frame(i + 1);
             
             // Wait for next frame
-            sleep(frame_delay).await;
+            clock.wait_for(i + 1).await;
+        }
+    }
+    
+    // Keep whatever was captured before the recording was stopped
+    if stopped() {
+        if frames.is_empty() {
+            return Err(anyhow::anyhow!("Recording was stopped before any frames were captured"));
+        }
+        if !is_piped {
+            eprintln!("{} {}", "!".yellow(), format!(
+                "Recording stopped early; keeping the {} of {} frames captured",
+                frames.len(), total_frames
+            ).yellow());
+        }
+    }
+    
+    if dropped > 0 && !is_piped {
+        eprintln!("{} {}", "!".yellow(), format!(
+            "Screenshots could not keep up with {}fps; {} of {} frames repeat the previous one",
+            frames_per_second, dropped, frames.len()
+        ).yellow());
+    }
+    
+    let timing = match &changes {
+        Some(changes) => FrameTiming { times: changes.times.clone(), end: clock.start.elapsed() },
+        None => FrameTiming::fixed(frames.len(), clock.interval),
+    };
+    
+    // Save full-resolution stills at the requested offsets
+    for &offset in &settings.keyframes {
+        let index = match &changes {
+            Some(changes) => changes.frame_at(offset),
+            None => settings.keyframe_index(offset) as usize,
+        };
+        let Some(frame) = frames.get(index) else {
+            continue;
+        };
+        let keyframe_path = keyframe_path(output_path, offset);
+        let mut file = create_output_file(&keyframe_path, no_clobber)?;
+        let saved = if settings.effects.is_none() {
+            frame.copy_to(&mut file)
+        } else {
+            frame.load().and_then(|image| Ok(settings.effects.apply(image).write_to(&mut file, image::ImageFormat::Png)?))
+        };
+        saved.with_context(|| format!("Failed to save keyframe to {}", keyframe_path.display()))?;
+        if !is_piped {
+            eprintln!("Keyframe at {:?} saved to {}", offset, keyframe_path.display());
         }
     }
     
-    // Create GIF from frames
-    create_gif_from_frames(&frames, output_path, is_piped, debug)?;
+    // Create the animation from frames
+    let stats = create_animation_from_frames(&frames, &timing, output_path, settings, no_clobber, is_piped, debug)?;
     
     if !is_piped && !debug {
-        eprintln!("{} {}", "✓".green(), format!("GIF saved to {}", output_path.display()).bright_green());
+        eprintln!("{} {}", "✓".green(), format!("{} saved to {} ({} frames)", settings.format.label(), output_path.display(), stats.frames).bright_green());
         std::io::stderr().flush().ok();
     } else if !is_piped && debug {
-        eprintln!("GIF saved to {}", output_path.display());
+        eprintln!("{} saved to {} ({} frames)", settings.format.label(), output_path.display(), stats.frames);
     }
     
-    Ok(())
+    Ok(stats)
+}
+
+/// When each recorded frame was captured, from the start of the recording
+struct FrameTiming {
+    times: Vec<Duration>,
+    /// When the recording stopped
+    end: Duration,
+}
+
+impl FrameTiming {
+    /// Timing of `frames` frames captured every `interval`
+    fn fixed(frames: usize, interval: Duration) -> Self {
+        FrameTiming {
+            times: (0..frames as u32).map(|i| interval * i).collect(),
+            end: interval * frames as u32,
+        }
+    }
+}
+
+/// Paces recording frames at a fixed cadence from the start of the recording
+///
+/// Frame `i` is due `i` frame intervals after the start, so time spent taking
+/// screenshots doesn't add up over a recording.
+struct FrameClock {
+    start: Instant,
+    interval: Duration,
+}
+
+impl FrameClock {
+    fn new(fps: u32) -> Self {
+        FrameClock { start: Instant::now(), interval: Duration::from_secs(1) / fps }
+    }
+
+    fn due(&self, frame: u64) -> Instant {
+        self.start + self.interval * frame as u32
+    }
+
+    /// Whether the slot for `frame` has already passed
+    fn is_behind(&self, frame: u64) -> bool {
+        Instant::now() >= self.due(frame + 1)
+    }
+
+    /// Sleep until `frame` is due
+    async fn wait_for(&self, frame: u64) {
+        sleep_until(self.due(frame)).await;
+    }
+}
+
+/// Capture recording frame `i` into `store`, returning whether it was dropped
+///
+/// When an earlier screenshot overran this frame's slot, the previous frame is
+/// repeated instead, which keeps the recording's length and frame count. With
+/// `changes`, a frame is only kept when the page changed since the last one.
+async fn record_frame(driver: &WebDriver, store: &FrameStore, i: u64, clock: &FrameClock, frames: &mut Vec<StoredFrame>, changes: Option<&mut FrameChanges>) -> Result<bool> {
+    if changes.is_none() && clock.is_behind(i) && let Some(previous) = frames.last().cloned() {
+        frames.push(previous);
+        return Ok(true);
+    }
+    if changes.as_ref().is_some_and(|changes| changes.is_full()) {
+        return Ok(false);
+    }
+    
+    // A broken screenshot is often a passing glitch, so try again before failing
+    let mut attempt = 1;
+    let captured_at = clock.start.elapsed();
+    let screenshot_data = loop {
+        let screenshot = driver.screenshot_as_png().await?;
+        match check_screenshot(&screenshot) {
+            Ok(()) => break screenshot,
+            Err(_) if attempt < FRAME_ATTEMPTS => attempt += 1,
+            Err(e) => return Err(e.context(format!("Frame {} was still broken after {} attempts", i, FRAME_ATTEMPTS))),
+        }
+    };
+    if let Some(changes) = changes && !changes.keep(&screenshot_data, captured_at)? {
+        return Ok(false);
+    }
+    frames.push(store.store(i, screenshot_data)?);
+    Ok(false)
+}
+
+/// Width of the thumbnails compared to spot changes between frames
+const CHANGE_THUMBNAIL_WIDTH: u32 = 160;
+
+/// How far a thumbnail channel may drift before the frame counts as changed,
+/// which absorbs rounding in the downscale
+const CHANGE_TOLERANCE: u8 = 2;
+
+/// Which frames an on-change recording keeps, and when each was captured
+struct FrameChanges {
+    max_frames: Option<u64>,
+    /// When each kept frame was captured, from the start of the recording
+    times: Vec<Duration>,
+    /// Downscaled copy of the last kept frame
+    thumbnail: Option<image::RgbImage>,
+}
+
+impl FrameChanges {
+    fn new(max_frames: Option<u64>) -> Self {
+        FrameChanges { max_frames, times: Vec::new(), thumbnail: None }
+    }
+    
+    /// Whether the frame cap has been reached
+    fn is_full(&self) -> bool {
+        self.max_frames.is_some_and(|max| self.times.len() as u64 >= max)
+    }
+    
+    /// Decide whether to keep the frame `png` captured at `captured_at`: the
+    /// first frame is always kept, later ones when they differ from the last
+    fn keep(&mut self, png: &[u8], captured_at: Duration) -> Result<bool> {
+        let image = image::load_from_memory_with_format(png, image::ImageFormat::Png)?;
+        let height = (image.height() as u64 * CHANGE_THUMBNAIL_WIDTH as u64 / image.width().max(1) as u64).max(1) as u32;
+        let thumbnail = image::imageops::thumbnail(&image.to_rgb8(), CHANGE_THUMBNAIL_WIDTH.min(image.width()), height.min(image.height()));
+        
+        let unchanged = self.thumbnail.as_ref().is_some_and(|last| {
+            last.dimensions() == thumbnail.dimensions()
+                && last.as_raw().iter().zip(thumbnail.as_raw()).all(|(a, b)| a.abs_diff(*b) <= CHANGE_TOLERANCE)
+        });
+        if unchanged {
+            return Ok(false);
+        }
+        self.thumbnail = Some(thumbnail);
+        self.times.push(captured_at);
+        Ok(true)
+    }
+    
+    /// Index of the frame showing at `offset` into the recording
+    fn frame_at(&self, offset: Duration) -> usize {
+        self.times.partition_point(|&time| time <= offset).saturating_sub(1)
+    }
+}
+
+/// Path of the PNG saved for the keyframe at `offset`
+///
+/// Derived from the recording's output path, e.g. `demo.gif` at 2.5 seconds
+/// gives `demo-keyframe-2.5s.png`. Recordings written to stdout use `weblook`
+/// as the stem.
+pub fn keyframe_path(output_path: &Path, offset: Duration) -> PathBuf {
+    output_path.with_file_name(format!("{}-keyframe-{}s.png", output_stem(output_path), offset.as_secs_f64()))
 }
 
-fn create_gif_from_frames(frame_paths: &[PathBuf], output_path: &PathBuf, is_piped: bool, debug: bool) -> Result<()> {
+/// Path the favicon is saved to, e.g. `home-favicon.ico` for `home.png`
+///
+/// Captures written to stdout use `weblook` as the stem.
+pub fn favicon_path(output_path: &Path, extension: &str) -> PathBuf {
+    output_path.with_file_name(format!("{}-favicon.{}", output_stem(output_path), extension))
+}
+
+/// Path computed styles are saved to, e.g. `home-styles.json` for `home.png`
+///
+/// Captures written to stdout use `weblook` as the stem.
+pub fn styles_path(output_path: &Path) -> PathBuf {
+    output_path.with_file_name(format!("{}-styles.json", output_stem(output_path)))
+}
+
+/// File stem that files saved next to the output are named after
+fn output_stem(output_path: &Path) -> String {
+    if output_path.to_str() == Some("-") {
+        "weblook".to_string()
+    } else {
+        output_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| "weblook".to_string())
+    }
+}
+
+/// Downscale a frame to `width` pixels wide with `filter`, preserving its aspect ratio
+pub fn resize_frame(img: image::DynamicImage, width: Option<u32>, filter: ResizeFilter) -> image::DynamicImage {
+    match width {
+        Some(width) if width > 0 && width < img.width() => {
+            let height = ((img.height() as u64 * width as u64 + img.width() as u64 / 2) / img.width() as u64).max(1) as u32;
+            img.resize_exact(width, height, filter.filter_type())
+        }
+        _ => img,
+    }
+}
+
+fn create_animation_from_frames(stored: &[StoredFrame], timing: &FrameTiming, output_path: &Path, settings: &RecordingSettings, no_clobber: bool, is_piped: bool, debug: bool) -> Result<RecordingStats> {
+    // Keep only the frames within the clip, shown for no longer than it lasts
+    let (stored, times, end) = match settings.clip {
+        Some(clip) => {
+            let range = clip_range(&timing.times, clip);
+            if range.is_empty() {
+                return Err(anyhow::anyhow!("No frames were captured between {:?} and {:?}", clip.start, clip.end));
+            }
+            let times: Vec<Duration> = timing.times[range.clone()].iter().map(|&time| time.max(clip.start)).collect();
+            (&stored[range], times, timing.end.min(clip.end))
+        }
+        None => (stored, timing.times.clone(), timing.end),
+    };
+    let frame_delays = if settings.on_change {
+        settings.elapsed_delays(&times, end)
+    } else {
+        settings.frame_delays(stored.len())
+    };
+    
     // Load all frames
     let mut frames = Vec::new();
     
     if !is_piped && !debug {
-        let pb = ProgressBar::new(frame_paths.len() as u64);
+        let pb = ProgressBar::new(stored.len() as u64);
         pb.set_style(ProgressStyle::default_bar()
             .template("{spinner:.green} {msg} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len}")
             .unwrap()
@@ -421,9 +5403,9 @@ fn create_gif_from_frames(frame_paths: &[PathBuf], output_path: &PathBuf, is_pip
         
         pb.set_message("Processing frames".bright_blue().to_string());
         
-        for (i, path) in frame_paths.iter().enumerate() {
-            let img = image::open(path)?;
-            let frame = img.to_rgba8();
+        for (i, stored_frame) in stored.iter().enumerate() {
+            let img = stored_frame.load()?;
+            let frame = settings.effects.apply(resize_frame(img, settings.gif_width, settings.resize_filter)).to_rgba8();
             frames.push(frame);
             
             // Update progress bar with rainbow colors
@@ -441,59 +5423,125 @@ fn create_gif_from_frames(frame_paths: &[PathBuf], output_path: &PathBuf, is_pip
         
         pb.finish_with_message("Frames processed!".green().to_string());
     } else if !is_piped && debug {
-        eprintln!("Processing {} frames...", frame_paths.len());
-        for path in frame_paths {
-            let img = image::open(path)?;
-            let frame = img.to_rgba8();
+        eprintln!("Processing {} frames...", stored.len());
+        for stored_frame in stored {
+            let img = stored_frame.load()?;
+            let frame = settings.effects.apply(resize_frame(img, settings.gif_width, settings.resize_filter)).to_rgba8();
             frames.push(frame);
         }
-        eprintln!("Frames processed. Creating GIF...");
+        eprintln!("Frames processed. Creating {}...", settings.format.label());
     } else {
-        for path in frame_paths {
-            let img = image::open(path)?;
-            let frame = img.to_rgba8();
+        for stored_frame in stored {
+            let img = stored_frame.load()?;
+            let frame = settings.effects.apply(resize_frame(img, settings.gif_width, settings.resize_filter)).to_rgba8();
             frames.push(frame);
         }
     }
     
-    // Create GIF
+    // Make sure all frames share the same dimensions
+    let (frames, fitted) = fit_frames(frames, settings.frame_fit)?;
+    if fitted && !is_piped {
+        eprintln!("{} {}", "!".yellow(), format!("Frame sizes changed during recording; frames were {} to a uniform size", if settings.frame_fit == FrameFit::Pad { "padded" } else { "cropped" }).yellow());
+    }
+    
+    // Encode the animation
     if output_path.to_str() == Some("-") {
         // Write to stdout
         let mut buffer = Vec::new();
-        write_gif_to_buffer(&frames, &mut buffer)?;
+        write_animation_to_buffer(&frames, settings, &frame_delays, &mut buffer)?;
         io::stdout().write_all(&buffer)?;
     } else {
         // Write to file
-        let mut file = std::fs::File::create(output_path)?;
-        write_gif_to_buffer(&frames, &mut file)?;
+        let mut file = create_output_file(output_path, no_clobber)?;
+        write_animation_to_buffer(&frames, settings, &frame_delays, &mut file)?;
     }
     
-    Ok(())
+    Ok(RecordingStats {
+        frames: frames.len(),
+        duration: Duration::from_millis(frame_delays.iter().map(|&delay| delay as u64 * 10).sum()),
+    })
+}
+
+fn write_animation_to_buffer<W: Write>(frames: &[image::RgbaImage], settings: &RecordingSettings, frame_delays: &[u16], buffer: &mut W) -> Result<()> {
+    match settings.format {
+        RecordingFormat::Gif => write_gif_to_buffer(frames, frame_delays, settings.colors, buffer),
+        RecordingFormat::Webp => write_webp_to_buffer(frames, frame_delays, buffer),
+    }
 }
 
-fn write_gif_to_buffer<W: Write>(frames: &[image::RgbaImage], buffer: &mut W) -> Result<()> {
+/// Encode frames as an infinitely looping GIF with up to `colors` colours
+/// in each frame's palette
+pub fn write_gif_to_buffer<W: Write>(frames: &[image::RgbaImage], frame_delays: &[u16], colors: u16, buffer: &mut W) -> Result<()> {
     let (width, height) = (frames[0].width(), frames[0].height());
     
-    let mut encoder = gif::Encoder::new(buffer, width as u16, height as u16, &[])?;
+    let (Ok(width), Ok(height)) = (u16::try_from(width), u16::try_from(height)) else {
+        return Err(anyhow::anyhow!("GIF frames can be at most {} pixels wide and tall, not {}x{}", u16::MAX, width, height));
+    };
+    
+    let mut encoder = gif::Encoder::new(buffer, width, height, &[])?;
     encoder.set_repeat(gif::Repeat::Infinite)?;
     
-    for frame in frames {
-        let mut frame_data = Vec::new();
-        for pixel in frame.pixels() {
-            frame_data.push(pixel[0]);
-            frame_data.push(pixel[1]);
-            frame_data.push(pixel[2]);
-        }
-        
-        let mut frame = gif::Frame::from_rgb(width as u16, height as u16, &frame_data);
-        frame.delay = 10; // 1/10th of a second
+    for (frame, &frame_delay) in frames.iter().zip(frame_delays) {
+        let mut frame = quantize_frame(frame, colors);
+        frame.delay = frame_delay; // in 1/100ths of a second
         encoder.write_frame(&frame)?;
     }
     
     Ok(())
 }
+
+/// Reduce an opaque copy of `frame` to a palette of at most `colors` colours
+///
+/// Frames that already use few enough colours keep them exactly; others are
+/// quantized with NeuQuant.
+fn quantize_frame(frame: &image::RgbaImage, colors: u16) -> gif::Frame<'static> {
+    let (width, height) = (frame.width() as u16, frame.height() as u16);
+    let mut pixels = frame.as_raw().clone();
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel[3] = 255;
+    }
+    
+    let mut distinct = std::collections::HashSet::new();
+    let exact = pixels.chunks_exact(4).all(|pixel| {
+        distinct.insert([pixel[0], pixel[1], pixel[2]]);
+        distinct.len() <= colors as usize
+    });
+    if exact {
+        return gif::Frame::from_rgba_speed(width, height, &mut pixels, 1);
+    }
+    
+    let quantizer = color_quant::NeuQuant::new(1, colors as usize, &pixels);
+    let indices: Vec<u8> = pixels.chunks_exact(4).map(|pixel| quantizer.index_of(pixel) as u8).collect();
+    gif::Frame::from_palette_pixels(width, height, indices, quantizer.color_map_rgb(), None)
+}
+
+/// Encode frames as an infinitely looping animated WebP
+///
+/// `frame_delays` holds each frame's delay in hundredths of a second, as for
+/// GIFs, so both formats play back at the same speed.
+pub fn write_webp_to_buffer<W: Write>(frames: &[image::RgbaImage], frame_delays: &[u16], buffer: &mut W) -> Result<()> {
+    let (width, height) = (frames[0].width(), frames[0].height());
+    
+    let config = webp::WebPConfig::new()
+        .map_err(|_| anyhow::anyhow!("Failed to initialise the WebP encoder"))?;
+    let mut encoder = webp::AnimEncoder::new(width, height, &config);
+    encoder.set_loop_count(0);
+    
+    let mut timestamp = 0; // in milliseconds
+    for (frame, &frame_delay) in frames.iter().zip(frame_delays) {
+        encoder.add_frame(webp::AnimFrame::from_rgba(frame.as_raw(), width, height, timestamp));
+        timestamp += frame_delay as i32 * 10;
+    }
+    
+    let data = encoder.try_encode()
+        .map_err(|e| anyhow::anyhow!("Failed to encode WebP animation: {:?}", e))?;
+    buffer.write_all(&data)?;
+    
+    Ok(())
+}
 /// Capture browser console logs and save to file
-async fn capture_console_logs(driver: &WebDriver, log_path: &str, is_piped: bool, debug: bool) -> Result<()> {
+async fn capture_console_logs(driver: &WebDriver, events: Option<&BidiEvents>, log_path: &Path, options: &CaptureOptions, is_piped: bool) -> Result<()> {
+    let debug = options.debug;
     if !is_piped && !debug {
         eprintln!("{}", "Capturing console logs...".bright_cyan());
         std::io::stderr().flush().ok();
@@ -501,6 +5549,89 @@ async fn capture_console_logs(driver: &WebDriver, log_path: &str, is_piped: bool
         eprintln!("Capturing console logs...");
     }
     
+    // Write logs to file
+    let logs = console_logs(driver, events).await?;
+    if options.console_log_mode == ConsoleLogMode::Append {
+        // One write, so pages captured at once don't interleave
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(log_path)
+            .with_context(|| format!("Failed to open {}", log_path.display()))?;
+        file.write_all(console_log_section(&options.url, &logs).as_bytes())?;
+    } else {
+        fs::write(log_path, logs)?;
+    }
+    
+    if !is_piped && !debug {
+        eprintln!("{} {}", "✓".green(), format!("Console logs saved to {}", log_path.display()).bright_green());
+        std::io::stderr().flush().ok();
+    } else if !is_piped && debug {
+        eprintln!("Console logs saved to {}", log_path.display());
+    }
+    
+    Ok(())
+}
+
+/// File the console log of a capture saved to `output_path` is written to
+///
+/// With [`ConsoleLogMode::PerPage`], `log_path`'s file name is added to the
+/// output's, e.g. `shots/home-console.log` for `shots/home.png` and
+/// `console.log`. Otherwise every capture uses `log_path` itself.
+pub fn console_log_path(log_path: &str, mode: ConsoleLogMode, output_path: &Path) -> PathBuf {
+    let log_path = Path::new(log_path);
+    match (mode, log_path.file_name()) {
+        (ConsoleLogMode::PerPage, Some(name)) => {
+            output_path.with_file_name(format!("{}-{}", output_stem(output_path), name.to_string_lossy()))
+        }
+        _ => log_path.to_path_buf(),
+    }
+}
+
+/// A page's console messages as appended to a shared log, after a `==> URL <==` header
+pub fn console_log_section(url: &str, logs: &str) -> String {
+    let mut section = format!("==> {} <==\n", url);
+    if !logs.is_empty() {
+        section.push_str(logs.trim_end_matches('\n'));
+        section.push('\n');
+    }
+    section.push('\n');
+    section
+}
+
+/// Fail if `pages` captures would each replace the same console log
+pub fn check_console_log(options: &CaptureOptions, pages: usize) -> Result<()> {
+    if let Some(log_path) = &options.console_log
+        && options.console_log_mode == ConsoleLogMode::Replace
+        && pages > 1
+    {
+        return Err(anyhow::anyhow!(
+            "Each of the {} pages would replace the console log {}; pass --console-log-mode append or per-page",
+            pages, log_path
+        ));
+    }
+    Ok(())
+}
+
+/// Browser console messages logged so far, one `[timestamp] [level] message` line each
+///
+/// With BiDi `events` these are every message since the page was navigated
+/// to, uncaught errors included; otherwise only those logged through the
+/// `console` methods since [`read_console_logs`] first hooked them.
+async fn console_logs(driver: &WebDriver, events: Option<&BidiEvents>) -> Result<String> {
+    let Some(events) = events else {
+        return read_console_logs(driver).await;
+    };
+    let lines = events.snapshot().console;
+    if lines.is_empty() {
+        return Ok(NO_CONSOLE_LOGS.to_string());
+    }
+    Ok(lines.iter().map(|line| format!("{}\n", line)).collect())
+}
+
+/// The console log written when the page logged nothing
+const NO_CONSOLE_LOGS: &str = "No console logs were captured during this session.\n";
+
+/// Console messages logged through the page's `console` methods, which are
+/// hooked on the first call
+async fn read_console_logs(driver: &WebDriver) -> Result<String> {
     // Execute JavaScript to retrieve console logs
     // We'll use a custom approach since thirtyfour doesn't directly expose the logs API
     let script = r#"
@@ -591,18 +5722,8 @@ async fn capture_console_logs(driver: &WebDriver, log_path: &str, is_piped: bool
     
     // If no logs were captured, add a message
     if log_content.is_empty() {
-        log_content = "No console logs were captured during this session.\n".to_string();
+        log_content = NO_CONSOLE_LOGS.to_string();
     }
     
-    // Write logs to file
-    fs::write(log_path, log_content)?;
-    
-    if !is_piped && !debug {
-        eprintln!("{} {}", "✓".green(), format!("Console logs saved to {}", log_path).bright_green());
-        std::io::stderr().flush().ok();
-    } else if !is_piped && debug {
-        eprintln!("Console logs saved to {}", log_path);
-    }
-    
-    Ok(())
+    Ok(log_content)
 }
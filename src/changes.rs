@@ -0,0 +1,130 @@
+// Skipping captures of pages that haven't changed since the last run
+//
+// Before launching a browser, a HEAD request fetches the page's `ETag` and
+// `Last-Modified` headers. When they match the ones recorded in a small state
+// file after the previous capture, the capture is skipped. Pages that send
+// neither header are always captured.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use reqwest::header::{HeaderMap, ETAG, LAST_MODIFIED, REFERER};
+use reqwest::StatusCode;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::capture::CaptureOptions;
+
+/// Caching headers that identify a version of a page
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Validators {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// The validators in a response's headers, or `None` if it sent neither
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name| headers.get(name)
+            .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+            .map(str::to_string);
+        let validators = Validators { etag: header(ETAG), last_modified: header(LAST_MODIFIED) };
+        (validators.etag.is_some() || validators.last_modified.is_some()).then_some(validators)
+    }
+}
+
+/// Whether a page needs capturing again
+#[derive(Debug, Clone, PartialEq)]
+pub enum PageStatus {
+    /// The page sent the same validators as at its last capture
+    Unchanged,
+    /// The page is new, has changed or can't be checked; the validators are
+    /// the ones to record once it has been captured
+    Changed(Option<Validators>),
+}
+
+/// Validators of the pages captured by previous runs, kept in a JSON file
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ChangeState {
+    /// Validators by page URL
+    pub pages: BTreeMap<String, Validators>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl ChangeState {
+    /// Read the state file at `path`, starting empty if it doesn't exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut state = match std::fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str::<ChangeState>(&content)
+                .with_context(|| format!("Invalid state file {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => ChangeState::default(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        state.path = path.to_path_buf();
+        Ok(state)
+    }
+
+    /// Write the state back to the file it was loaded from
+    pub fn save(&self) -> Result<()> {
+        std::fs::write(&self.path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+
+    /// Compare `validators` just fetched for `url` with the recorded ones
+    pub fn status(&self, url: &str, validators: Option<Validators>) -> PageStatus {
+        match validators {
+            Some(validators) if self.pages.get(url) == Some(&validators) => PageStatus::Unchanged,
+            validators => PageStatus::Changed(validators),
+        }
+    }
+
+    /// Check whether the page of `options` changed since its last capture
+    ///
+    /// A page that can't be fetched is reported as changed, so that the
+    /// capture goes ahead and shows what the browser makes of it.
+    pub async fn check(&self, options: &CaptureOptions) -> PageStatus {
+        match fetch_validators(options).await {
+            Ok(validators) => self.status(&options.url, validators),
+            Err(e) => {
+                eprintln!("{} {}", "!".yellow(), format!("Could not check {} for changes: {:#}", options.url, e).yellow());
+                PageStatus::Changed(None)
+            }
+        }
+    }
+
+    /// Remember `validators` for `url` after capturing it; pages without
+    /// validators are forgotten, so they are captured every time
+    pub fn record(&mut self, url: &str, validators: Option<Validators>) {
+        match validators {
+            Some(validators) => self.pages.insert(url.to_string(), validators),
+            None => self.pages.remove(url),
+        };
+    }
+}
+
+/// Fetch the validators of the page of `options` with a HEAD request,
+/// falling back to a GET for servers that don't allow HEAD
+pub async fn fetch_validators(options: &CaptureOptions) -> Result<Option<Validators>> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .danger_accept_invalid_certs(options.ignore_cert_errors)
+        .build()?;
+    let request = |method| {
+        let request = client.request(method, &options.url);
+        match &options.referer {
+            Some(referer) => request.header(REFERER, referer),
+            None => request,
+        }
+    };
+
+    let mut response = request(reqwest::Method::HEAD).send().await?;
+    if matches!(response.status(), StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED) {
+        // Only the headers are read, not the body
+        response = request(reqwest::Method::GET).send().await?;
+    }
+    let response = response.error_for_status()?;
+    Ok(Validators::from_headers(response.headers()))
+}
@@ -0,0 +1,160 @@
+// Multi-page documents assembled from batch captures
+//
+// Each capture becomes one page, sized to the capture, so a whole site can be
+// reviewed by scrolling through a single PDF or TIFF.
+
+use anyhow::{Context, Result};
+use flate2::{write::ZlibEncoder, Compression};
+use image::{Rgba, RgbaImage};
+use std::io::{BufWriter, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tiff::encoder::{colortype, TiffEncoder};
+
+use crate::capture;
+
+/// PDF points per CSS pixel, so that pages print at the size the browser drew them
+const POINTS_PER_PIXEL: f32 = 72.0 / 96.0;
+/// Colour transparent areas of a capture are flattened onto in a PDF
+const PAPER: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+/// Document format for `--combine`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CombineFormat {
+    Pdf,
+    Tiff,
+}
+
+impl CombineFormat {
+    pub const ALL: [CombineFormat; 2] = [CombineFormat::Pdf, CombineFormat::Tiff];
+
+    /// File extension of the document, e.g. `pdf`
+    pub fn extension(self) -> &'static str {
+        match self {
+            CombineFormat::Pdf => "pdf",
+            CombineFormat::Tiff => "tiff",
+        }
+    }
+
+    /// Where the document goes when no path is given, e.g. `combined.pdf`
+    pub fn default_path(self) -> PathBuf {
+        PathBuf::from(format!("combined.{}", self.extension()))
+    }
+}
+
+impl FromStr for CombineFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pdf" => Ok(CombineFormat::Pdf),
+            "tiff" | "tif" => Ok(CombineFormat::Tiff),
+            _ => Err(anyhow::anyhow!("Invalid document format. Expected pdf or tiff")),
+        }
+    }
+}
+
+/// Encode `pages` as a PDF with one page per image
+///
+/// Pages are stored losslessly, with any transparency flattened onto white.
+pub fn write_pdf(pages: &[RgbaImage]) -> Result<Vec<u8>> {
+    if pages.is_empty() {
+        return Err(anyhow::anyhow!("A document needs at least one page"));
+    }
+
+    // Objects 1 and 2 are the catalog and page tree; each page then takes
+    // three: the page, its content stream and its image
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 3 + i * 3).collect();
+    let mut pdf = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::new();
+
+    let mut object = |pdf: &mut Vec<u8>, body: &[u8]| {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", offsets.len()).as_bytes());
+        pdf.extend_from_slice(body);
+        pdf.extend_from_slice(b"\nendobj\n");
+    };
+
+    object(&mut pdf, b"<< /Type /Catalog /Pages 2 0 R >>");
+    let kids = page_ids.iter().map(|id| format!("{} 0 R", id)).collect::<Vec<_>>().join(" ");
+    object(&mut pdf, format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids, pages.len()).as_bytes());
+
+    for (page, &id) in pages.iter().zip(&page_ids) {
+        let (width, height) = page.dimensions();
+        let (page_width, page_height) = (width as f32 * POINTS_PER_PIXEL, height as f32 * POINTS_PER_PIXEL);
+        object(&mut pdf, format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>",
+            page_width, page_height, id + 2, id + 1
+        ).as_bytes());
+
+        let contents = format!("q {:.2} 0 0 {:.2} 0 0 cm /Im0 Do Q", page_width, page_height);
+        object(&mut pdf, &stream(&format!("<< /Length {} >>", contents.len()), contents.as_bytes()));
+
+        let pixels = deflate(&flatten(page))?;
+        let header = format!(
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode /Length {} >>",
+            width, height, pixels.len()
+        );
+        object(&mut pdf, &stream(&header, &pixels));
+    }
+
+    let xref = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).as_bytes());
+    for offset in &offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", offsets.len() + 1, xref).as_bytes());
+    Ok(pdf)
+}
+
+/// Encode `pages` as a multi-page TIFF, keeping transparency
+pub fn write_tiff(pages: &[RgbaImage]) -> Result<Vec<u8>> {
+    if pages.is_empty() {
+        return Err(anyhow::anyhow!("A document needs at least one page"));
+    }
+
+    let mut tiff = Cursor::new(Vec::new());
+    {
+        let mut encoder = TiffEncoder::new(&mut tiff)?
+            .with_compression(tiff::encoder::Compression::Deflate(Default::default()));
+        for page in pages {
+            encoder.write_image::<colortype::RGBA8>(page.width(), page.height(), page.as_raw())?;
+        }
+    }
+    Ok(tiff.into_inner())
+}
+
+/// Write `pages` to `path` as a single document
+pub fn save_document(pages: &[RgbaImage], format: CombineFormat, path: &Path, no_clobber: bool) -> Result<()> {
+    let document = match format {
+        CombineFormat::Pdf => write_pdf(pages)?,
+        CombineFormat::Tiff => write_tiff(pages)?,
+    };
+    let file = capture::create_output_file(path, no_clobber)?;
+    BufWriter::new(file).write_all(&document)
+        .with_context(|| format!("Failed to write document to {}", path.display()))?;
+    Ok(())
+}
+
+fn stream(header: &str, data: &[u8]) -> Vec<u8> {
+    let mut stream = format!("{}\nstream\n", header).into_bytes();
+    stream.extend_from_slice(data);
+    stream.extend_from_slice(b"\nendstream");
+    stream
+}
+
+/// RGB bytes of `image`, blended onto [`PAPER`]
+fn flatten(image: &RgbaImage) -> Vec<u8> {
+    image.pixels()
+        .flat_map(|Rgba([r, g, b, a])| {
+            let blend = |channel: u8, paper: u8| ((channel as u32 * *a as u32 + paper as u32 * (255 - *a as u32)) / 255) as u8;
+            [blend(*r, PAPER.0[0]), blend(*g, PAPER.0[1]), blend(*b, PAPER.0[2])]
+        })
+        .collect()
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
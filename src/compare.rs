@@ -0,0 +1,133 @@
+// Comparing captures of two live pages, e.g. production against staging
+//
+// Both pages are captured with the same options in the same browser, then
+// laid out side by side and diffed pixel by pixel.
+
+use anyhow::Context;
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+
+use crate::capture::{self, CaptureError, CaptureOptions, Session};
+use crate::montage::{self, Tile};
+
+/// Colour of pixels that differ between the two captures
+const CHANGED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+/// Result of comparing two images pixel by pixel
+pub struct Comparison {
+    /// Percentage of pixels that are identical in both images
+    pub similarity: f64,
+    /// The first image faded to grey, with the differing pixels in red
+    pub diff: RgbaImage,
+}
+
+/// Compare `a` and `b` pixel by pixel
+///
+/// Images of different sizes are compared over the larger of the two, with
+/// any area only one of them covers counted as different.
+pub fn compare_images(a: &RgbaImage, b: &RgbaImage) -> Comparison {
+    let width = a.width().max(b.width());
+    let height = a.height().max(b.height());
+    let mut diff = RgbaImage::from_pixel(width, height, CHANGED);
+    let mut identical = 0u64;
+
+    for (x, y, pixel) in diff.enumerate_pixels_mut() {
+        if let (Some(pa), Some(pb)) = (a.get_pixel_checked(x, y), b.get_pixel_checked(x, y))
+            && pa == pb
+        {
+            identical += 1;
+            *pixel = faded(pa);
+        }
+    }
+
+    let total = width as u64 * height as u64;
+    let similarity = if total == 0 { 100.0 } else { identical as f64 * 100.0 / total as f64 };
+    Comparison { similarity, diff }
+}
+
+/// Light grey version of `pixel`, so that changes stand out on top of it
+fn faded(pixel: &Rgba<u8>) -> Rgba<u8> {
+    let [r, g, b, _] = pixel.0;
+    let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+    let light = (255 - (255 - luma) / 4) as u8;
+    Rgba([light, light, light, 255])
+}
+
+/// Path of a file written next to the output of a comparison, e.g.
+/// `home-diff.png` for `home.png` and `diff`
+pub fn compare_path(output_path: &Path, suffix: &str) -> PathBuf {
+    let stem = output_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| "weblook".to_string());
+    output_path.with_file_name(format!("{}-{}.png", stem, suffix))
+}
+
+/// Files written by [`compare_pages`] and how similar the pages were
+pub struct CompareReport {
+    /// Capture of the primary URL, at the output path
+    pub primary: PathBuf,
+    /// Capture of the other URL, as `<name>-compare.png`
+    pub other: PathBuf,
+    /// Both captures next to each other, as `<name>-side-by-side.png`
+    pub side_by_side: PathBuf,
+    /// The differences highlighted on the primary capture, as `<name>-diff.png`
+    pub diff: PathBuf,
+    /// Percentage of pixels that are identical in both captures
+    pub similarity: f64,
+}
+
+/// Capture `options.url` and `other_url` with the same options, in one
+/// browser, and write a side-by-side and a diff image of the two
+pub async fn compare_pages(options: &CaptureOptions, other_url: &str) -> Result<CompareReport, CaptureError> {
+    let other_options = CaptureOptions {
+        url: other_url.to_string(),
+        output_path: compare_path(&options.output_path, "compare"),
+        ..options.clone()
+    };
+
+    let mut session = Session::start(options).await?;
+    let primary = session.screenshot(options.clone()).await?.output_path;
+    let other = session.screenshot(other_options).await?.output_path;
+    session.close().await?;
+
+    let load = |path: &Path| capture::open_capture(path).and_then(|reader| Ok(reader.decode()?))
+        .with_context(|| format!("Failed to read {}", path.display()))
+        .map(|image| image.to_rgba8())
+        .map_err(CaptureError::Io);
+    let (primary_image, other_image) = (load(&primary)?, load(&other)?);
+
+    let tiles = [
+        Tile { image: primary_image.clone(), caption: Some(options.url.clone()) },
+        Tile { image: other_image.clone(), caption: Some(other_url.to_string()) },
+    ];
+    let side_by_side = compare_path(&options.output_path, "side-by-side");
+    montage::save_montage(&montage::build_montage(&tiles, 2), &side_by_side, options.no_clobber)
+        .map_err(CaptureError::Io)?;
+
+    let comparison = compare_images(&primary_image, &other_image);
+    let diff = compare_path(&options.output_path, "diff");
+    montage::save_montage(&comparison.diff, &diff, options.no_clobber)
+        .map_err(CaptureError::Io)?;
+
+    Ok(CompareReport { primary, other, side_by_side, diff, similarity: comparison.similarity })
+}
+
+/// Check that `options` describe a capture [`compare_pages`] can diff
+pub fn check_compare(options: &CaptureOptions) -> anyhow::Result<()> {
+    if options.output_path.to_str() == Some("-") {
+        return Err(anyhow::anyhow!("Comparing pages writes several files, so it cannot write to stdout"));
+    }
+    if options.is_recording || options.pdf.is_some() || options.each || options.states.is_some() || !options.resize_sequence.is_empty() {
+        return Err(anyhow::anyhow!("Only screenshots can be compared; not recordings, PDFs, --each, --states or --resize-sequence"));
+    }
+    Ok(())
+}
+
+
+/// Parse a `--min-similarity` percentage between 0 and 100
+pub fn parse_similarity(s: &str) -> Result<f64, String> {
+    let percent: f64 = s.trim_end_matches('%').parse()
+        .map_err(|_| format!("invalid percentage: {}", s))?;
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(format!("{} is not between 0 and 100", s));
+    }
+    Ok(percent)
+}
@@ -0,0 +1,290 @@
+// Environment checks behind `weblook doctor` and `weblook list-browsers`
+//
+// Most "it doesn't work" reports come down to a missing or mismatched
+// ChromeDriver/Chrome, so these checks let users diagnose that themselves.
+
+use colored::*;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Executables tried, in order, when looking for Chrome
+const CHROME_BINARIES: &[&str] = &[
+    "google-chrome",
+    "google-chrome-stable",
+    "chromium",
+    "chromium-browser",
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+];
+
+/// Executables tried, in order, when looking for Firefox
+const FIREFOX_BINARIES: &[&str] = &[
+    "firefox",
+    "/Applications/Firefox.app/Contents/MacOS/firefox",
+];
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    /// Something looks wrong, but captures may still work
+    Warn,
+    /// Captures cannot work until this is fixed
+    Fail,
+}
+
+/// A named check and what it found
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Check { name, status, detail: detail.into() }
+    }
+}
+
+/// Extract the first dotted version number from a `--version` line,
+/// e.g. `120.0.6099.109` from `ChromeDriver 120.0.6099.109 (3419140ab6...)`
+pub fn parse_version(output: &str) -> Option<String> {
+    output.split_whitespace()
+        .find(|word| {
+            word.contains('.')
+                && word.split('.').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(str::to_string)
+}
+
+/// Major component of a dotted version number
+pub fn major_version(version: &str) -> Option<u32> {
+    version.split('.').next()?.parse().ok()
+}
+
+/// Run `program --version` and return its trimmed output, if it ran successfully
+fn version_output(program: &str) -> Option<String> {
+    let output = Command::new(program).arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn check_chromedriver() -> (Check, Option<String>) {
+    match version_output("chromedriver") {
+        Some(output) => {
+            let version = parse_version(&output);
+            let detail = version.clone().unwrap_or(output);
+            (Check::new("ChromeDriver", CheckStatus::Pass, detail), version)
+        }
+        None => (
+            Check::new("ChromeDriver", CheckStatus::Fail, "not found on PATH (e.g. sudo apt install chromium-chromedriver)"),
+            None,
+        ),
+    }
+}
+
+fn check_chrome() -> (Check, Option<String>) {
+    for binary in CHROME_BINARIES {
+        if let Some(output) = version_output(binary) {
+            let version = parse_version(&output);
+            let detail = format!("{} ({})", version.clone().unwrap_or(output), binary);
+            return (Check::new("Chrome", CheckStatus::Pass, detail), version);
+        }
+    }
+    (
+        Check::new("Chrome", CheckStatus::Fail, format!("not found (tried {})", CHROME_BINARIES.join(", "))),
+        None,
+    )
+}
+
+/// ChromeDriver only drives a Chrome with the same major version
+fn check_compatibility(chromedriver: Option<&str>, chrome: Option<&str>) -> Check {
+    let (Some(chromedriver), Some(chrome)) = (chromedriver, chrome) else {
+        return Check::new("Versions match", CheckStatus::Warn, "skipped, a version could not be determined");
+    };
+    match (major_version(chromedriver), major_version(chrome)) {
+        (Some(driver_major), Some(chrome_major)) if driver_major == chrome_major => {
+            Check::new("Versions match", CheckStatus::Pass, format!("both are version {}", driver_major))
+        }
+        (Some(driver_major), Some(chrome_major)) => Check::new(
+            "Versions match",
+            CheckStatus::Fail,
+            format!("ChromeDriver {} cannot drive Chrome {}; install matching versions", driver_major, chrome_major),
+        ),
+        _ => Check::new("Versions match", CheckStatus::Warn, "skipped, a version could not be parsed"),
+    }
+}
+
+fn check_cwd_writable() -> Check {
+    match tempfile::NamedTempFile::new_in(Path::new(".")) {
+        Ok(_) => Check::new("Working directory", CheckStatus::Pass, "writable"),
+        Err(e) => Check::new("Working directory", CheckStatus::Fail, format!("not writable: {}", e)),
+    }
+}
+
+/// Run every environment check
+pub fn run_checks() -> Vec<Check> {
+    let (chromedriver, chromedriver_version) = check_chromedriver();
+    let (chrome, chrome_version) = check_chrome();
+    let compatibility = check_compatibility(chromedriver_version.as_deref(), chrome_version.as_deref());
+    vec![chromedriver, chrome, compatibility, check_cwd_writable()]
+}
+
+/// Print a coloured report of `checks`, returning whether none of them failed
+pub fn print_report(checks: &[Check]) -> bool {
+    for check in checks {
+        let mark = match check.status {
+            CheckStatus::Pass => "✓".green(),
+            CheckStatus::Warn => "!".yellow(),
+            CheckStatus::Fail => "✗".bright_red(),
+        };
+        println!("{} {}: {}", mark, check.name.bold(), check.detail);
+    }
+
+    let healthy = checks.iter().all(|check| check.status != CheckStatus::Fail);
+    if healthy {
+        println!("{}", "Everything WebLook needs is in place".bright_green());
+    } else {
+        println!("{}", "Some checks failed; captures will not work until they are fixed".bright_red());
+    }
+    healthy
+}
+
+/// Whether a binary found by [`list_browsers`] is a browser or a WebDriver server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BinaryKind {
+    Browser,
+    Driver,
+}
+
+/// A browser or driver executable found on this system
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct FoundBinary {
+    /// Browser family the binary belongs to: `chrome` or `firefox`
+    pub family: &'static str,
+    pub kind: BinaryKind,
+    pub path: PathBuf,
+    /// Version from `--version`, if it could be parsed
+    pub version: Option<String>,
+}
+
+/// A browser family and whether WebLook can capture with it here
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct Backend {
+    pub name: &'static str,
+    pub usable: bool,
+    pub detail: String,
+}
+
+/// Everything `weblook list-browsers` reports
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BrowserList {
+    pub binaries: Vec<FoundBinary>,
+    pub backends: Vec<Backend>,
+}
+
+/// Locate `program` on PATH, or check it exists when given as a path
+fn find_executable(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        let path = PathBuf::from(program);
+        return path.is_file().then_some(path);
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
+/// Scan for browsers and WebDriver servers and work out which backends are usable
+pub fn list_browsers() -> BrowserList {
+    let candidates = [
+        ("chrome", BinaryKind::Driver, &["chromedriver"][..]),
+        ("chrome", BinaryKind::Browser, CHROME_BINARIES),
+        ("firefox", BinaryKind::Driver, &["geckodriver"][..]),
+        ("firefox", BinaryKind::Browser, FIREFOX_BINARIES),
+    ];
+    let mut binaries = Vec::new();
+    let mut seen = Vec::new();
+    for (family, kind, programs) in candidates {
+        for program in programs {
+            let Some(path) = find_executable(program) else {
+                continue;
+            };
+            // Several names often lead to the same binary, e.g. google-chrome and google-chrome-stable
+            let resolved = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            if seen.contains(&resolved) {
+                continue;
+            }
+            seen.push(resolved);
+            let version = version_output(&path.to_string_lossy()).as_deref().and_then(parse_version);
+            binaries.push(FoundBinary { family, kind, path, version });
+        }
+    }
+    let backends = backends(&binaries);
+    BrowserList { binaries, backends }
+}
+
+/// Which backends the found binaries make usable
+///
+/// WebLook drives Chrome through ChromeDriver, which needs a Chrome of the
+/// same major version. Firefox is listed for completeness but is not supported.
+pub fn backends(binaries: &[FoundBinary]) -> Vec<Backend> {
+    let find = |family: &str, kind: BinaryKind| binaries.iter().find(|b| b.family == family && b.kind == kind);
+
+    let chrome = match (find("chrome", BinaryKind::Driver), find("chrome", BinaryKind::Browser)) {
+        (None, _) => Backend { name: "chrome", usable: false, detail: "ChromeDriver not found".to_string() },
+        (_, None) => Backend { name: "chrome", usable: false, detail: "Chrome not found".to_string() },
+        (Some(driver), Some(browser)) => {
+            let driver_major = driver.version.as_deref().and_then(major_version);
+            let browser_major = browser.version.as_deref().and_then(major_version);
+            match (driver_major, browser_major) {
+                (Some(d), Some(b)) if d != b => Backend {
+                    name: "chrome",
+                    usable: false,
+                    detail: format!("ChromeDriver {} cannot drive Chrome {}", d, b),
+                },
+                _ => Backend {
+                    name: "chrome",
+                    usable: true,
+                    detail: format!("{} with {}", driver.path.display(), browser.path.display()),
+                },
+            }
+        }
+    };
+
+    let firefox_found = find("firefox", BinaryKind::Driver).is_some() || find("firefox", BinaryKind::Browser).is_some();
+    let firefox = Backend {
+        name: "firefox",
+        usable: false,
+        detail: if firefox_found { "found, but WebLook does not support Firefox yet" } else { "not found" }.to_string(),
+    };
+
+    vec![chrome, firefox]
+}
+
+/// Print the binaries and backends found
+pub fn print_browser_list(list: &BrowserList) {
+    if list.binaries.is_empty() {
+        println!("No browsers or drivers found");
+    }
+    for binary in &list.binaries {
+        let kind = match binary.kind {
+            BinaryKind::Browser => "browser",
+            BinaryKind::Driver => "driver",
+        };
+        println!(
+            "{} {} {}: {}",
+            binary.family.bold(),
+            kind,
+            binary.version.as_deref().unwrap_or("(unknown version)"),
+            binary.path.display()
+        );
+    }
+    println!();
+    for backend in &list.backends {
+        let mark = if backend.usable { "✓".green() } else { "✗".bright_red() };
+        println!("{} {} backend: {}", mark, backend.name.bold(), backend.detail);
+    }
+}
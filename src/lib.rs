@@ -1,11 +1,23 @@
+pub mod capabilities;
+pub mod bidi;
 pub mod capture;
+pub mod changes;
+pub mod combine;
+pub mod compare;
+pub mod doctor;
+pub mod label;
+pub mod locales;
+pub mod montage;
+pub mod polish;
+pub mod upload;
+pub mod warm;
 
 // MCP module is only available when the mcp_experimental feature is enabled
 #[cfg(feature = "mcp_experimental")]
 pub mod mcp;
 
 // Re-export main components for easier use in tests
-pub use capture::CaptureOptions;
+pub use capture::{CaptureError, CaptureEvent, CaptureOptions, Session};
 
 // Re-export MCP components only when the feature is enabled
 #[cfg(feature = "mcp_experimental")]
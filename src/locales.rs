@@ -0,0 +1,74 @@
+// Capturing a page in several languages in one run
+//
+// The page is captured once per locale in the same browser, presenting each
+// locale to it in turn, so localization reviewers can see how the text of
+// every translation fits the layout.
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::capture::{check_console_log, CaptureError, CaptureInfo, CaptureOptions, Session};
+
+/// Check that `s` looks like a locale such as `en`, `fr-CA` or `zh-Hant-TW`
+///
+/// An underscore is taken as a hyphen, so `pt_BR` works too.
+pub fn parse_locale(s: &str) -> Result<String> {
+    let locale = s.trim().replace('_', "-");
+    let mut parts = locale.split('-');
+    let language = parts.next().unwrap_or_default();
+    let valid = (2..=8).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && parts.all(|part| (1..=8).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric()));
+    if !valid {
+        return Err(anyhow::anyhow!("Invalid locale {}. Expected a language tag such as en, fr-CA or zh-Hant-TW", s));
+    }
+    Ok(locale)
+}
+
+/// Path of the capture in `locale`, e.g. `home-fr-CA.png` for `home.png`
+pub fn locale_path(output_path: &Path, locale: &str) -> PathBuf {
+    let stem = output_path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| "weblook".to_string());
+    match output_path.extension() {
+        Some(extension) => output_path.with_file_name(format!("{}-{}.{}", stem, locale, extension.to_string_lossy())),
+        None => output_path.with_file_name(format!("{}-{}", stem, locale)),
+    }
+}
+
+/// Check that `options` can be captured once per locale
+pub fn check_locales(options: &CaptureOptions, locales: &[String]) -> Result<()> {
+    if options.output_path.to_str() == Some("-") {
+        return Err(anyhow::anyhow!("Capturing several locales writes a file for each, so it cannot write to stdout"));
+    }
+    if let Some(duplicate) = locales.iter().enumerate().find_map(|(i, locale)| locales[..i].contains(locale).then_some(locale)) {
+        return Err(anyhow::anyhow!("Locale {} is given more than once", duplicate));
+    }
+    check_console_log(options, locales.len())
+}
+
+/// The options to capture `options` in `locale` with
+pub fn locale_options(options: &CaptureOptions, locale: &str) -> CaptureOptions {
+    CaptureOptions {
+        locale: Some(locale.to_string()),
+        output_path: locale_path(&options.output_path, locale),
+        ..options.clone()
+    }
+}
+
+/// Capture the page of `options` once per locale, reusing one browser
+///
+/// Each capture is written next to the output path, named after its locale.
+pub async fn capture_locales(options: &CaptureOptions, locales: &[String]) -> Result<Vec<CaptureInfo>, CaptureError> {
+    let mut session = Session::start(options).await?;
+    let mut infos = Vec::new();
+    for locale in locales {
+        let locale_options = locale_options(options, locale);
+        let info = if options.is_recording {
+            session.record(locale_options).await?
+        } else {
+            session.screenshot(locale_options).await?
+        };
+        infos.push(info);
+    }
+    session.close().await?;
+    Ok(infos)
+}
@@ -1,37 +1,332 @@
 use anyhow::{Context, Result};
-use base64::Engine;
-use clap::Parser;
-use std::io::{self, Read, Write};
-use std::net::SocketAddr;
-use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use colored::*;
+use futures::StreamExt;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::future::Future;
+use std::time::Duration;
 use tokio::signal;
 use url::Url;
 
-mod capture;
 #[cfg(feature = "mcp_experimental")]
-mod mcp;
+use base64::Engine;
+#[cfg(feature = "mcp_experimental")]
+use std::io::Write;
+#[cfg(feature = "mcp_experimental")]
+use std::net::SocketAddr;
 
-use capture::CaptureOptions;
+use weblook::capture::{self, CaptureOptions};
+use weblook::changes::{ChangeState, PageStatus};
+use weblook::combine::CombineFormat;
+use weblook::compare;
+use weblook::locales;
+use weblook::polish::Polish;
+use weblook::upload::{self, UploadTarget};
+#[cfg(feature = "mcp_experimental")]
+use weblook::mcp;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Capture screenshots and recordings of web pages")]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+    
     /// URL to capture (default: http://127.0.0.1:8080)
     #[arg(index = 1)]
     url: Option<String>,
 
-    /// Output file path (default: weblook.png or weblook.gif)
+    /// Output file path, or an s3://BUCKET/KEY or http(s) URL to upload to (default: weblook.png or weblook.gif)
     #[arg(short, long)]
     output: Option<String>,
 
-    /// Wait time before capture in seconds (default: 10)
-    #[arg(short, long, default_value = "10")]
-    wait: u64,
+    /// Name the output file after the page's URL once redirects have been followed
+    #[arg(long)]
+    name_by_final_url: bool,
+
+    /// Name the output file after the page's title, adding -2, -3, ... rather than replacing an existing file
+    #[arg(long, conflicts_with = "name_by_final_url")]
+    name_by_title: bool,
+
+    /// Record each URL the page was redirected through, shown with --print-info and, with --debug, on stderr
+    #[arg(long)]
+    capture_redirects: bool,
+
+    /// Follow console messages, network responses and page loads through WebDriver BiDi events instead of polling the page (needs a ChromeDriver with BiDi support)
+    #[arg(long)]
+    bidi: bool,
+
+    /// Fail instead of replacing an output file that already exists
+    #[arg(long, conflicts_with = "overwrite")]
+    no_clobber: bool,
+
+    /// Replace an existing output file (the default)
+    #[arg(long)]
+    overwrite: bool,
+
+    /// Skip pages whose ETag and Last-Modified headers match those recorded in this state file by the previous run
+    #[arg(long, value_name = "STATE_FILE", conflicts_with_all = ["compare_url", "montage", "combine"])]
+    if_changed: Option<PathBuf>,
+
+    /// Wait time before capture, e.g. 500ms, 1.5s or 2m; a bare number is seconds (default: 10)
+    #[arg(short, long, default_value = "10", value_parser = capture::parse_duration)]
+    wait: Duration,
+
+    /// Send this URL as the Referer of the request for the page
+    #[arg(long, value_name = "URL")]
+    referer: Option<String>,
+
+    /// Add a random extra delay to the wait, from a MIN-MAX range (e.g. 500ms-2s) or up to a single duration
+    #[arg(long, value_name = "RANGE")]
+    jitter: Option<capture::Jitter>,
+
+    /// Before the wait, wait (up to 30 seconds) until the document is interactive (parsed) or complete (fully loaded)
+    #[arg(long, value_name = "STATE")]
+    ready_state: Option<capture::ReadyState>,
+
+    /// Answer JavaScript dialogs (alert, confirm, prompt) the page opens: accept or dismiss (default: dismiss)
+    #[arg(long, value_name = "ACTION", default_value = "dismiss")]
+    dialog: capture::DialogAction,
+
+    /// Abort if the whole capture (navigation, waits, JavaScript and capture) takes longer than this
+    #[arg(long, value_parser = capture::parse_duration)]
+    max_wait: Option<Duration>,
+
+    /// If --max-wait runs out, save a screenshot, the console log and the current URL to this directory first
+    #[arg(long, value_name = "DIR", requires = "max_wait")]
+    on_timeout_dump: Option<PathBuf>,
+
+    /// After the wait, also wait until no element matching this CSS selector (e.g. a loading spinner) is visible
+    #[arg(long, value_name = "SELECTOR")]
+    wait_gone: Option<String>,
+
+    /// After the wait, also wait (up to 30 seconds) until the page has more than N characters of visible text
+    #[arg(long, value_name = "N")]
+    wait_min_chars: Option<usize>,
+
+    /// After the wait, also wait (up to 30 seconds) until at least N network responses have completed
+    #[arg(long, value_name = "N")]
+    wait_responses: Option<usize>,
+
+    /// Freeze Date and performance.now() at this many milliseconds into the page's timeline, and pause CSS animations there before capture
+    #[arg(long, value_name = "MS", conflicts_with = "record")]
+    freeze_time: Option<u64>,
+
+    /// Make the page believe its tab is visible and focused, for pages that pause animations or lazy content while hidden
+    #[arg(long)]
+    force_visible: bool,
+
+    /// Render a font family with another installed font, e.g. "Brand Sans=DejaVu Sans" (repeatable)
+    #[arg(long = "font-substitute", value_name = "FAMILY=REPLACEMENT")]
+    font_substitutes: Vec<capture::FontSubstitute>,
+
+    /// Before the screenshot, wait (up to 10 seconds) until two screenshots 250ms apart are identical
+    #[arg(long, conflicts_with_all = ["record", "pdf"])]
+    stable: bool,
+
+    /// Only let the browser contact this host; *.example.com covers subdomains (repeatable)
+    #[arg(long = "allow-domain", value_name = "DOMAIN", conflicts_with = "deny_domain")]
+    allow_domain: Vec<String>,
+
+    /// Block requests to this host; *.example.com covers subdomains (repeatable)
+    #[arg(long = "deny-domain", value_name = "DOMAIN")]
+    deny_domain: Vec<String>,
 
     /// Create a recording instead of screenshot (value is length in seconds)
     #[arg(short, long)]
     record: Option<Option<u64>>,
 
+    /// Frames captured per second while recording (default: 10)
+    #[arg(long, requires = "record", value_parser = clap::value_parser!(u32).range(1..=50))]
+    capture_fps: Option<u32>,
+
+    /// Frames per second when the GIF plays back (default: capture rate; lower = slow motion)
+    #[arg(long, requires = "record", value_parser = clap::value_parser!(u32).range(1..=50))]
+    playback_fps: Option<u32>,
+
+    /// Downscale recorded GIF frames to this width in pixels (aspect ratio is kept)
+    #[arg(long, requires = "record")]
+    gif_width: Option<u32>,
+
+    /// Colours in each GIF frame's palette, 2 to 256; fewer give smaller files, more keep gradients (default: 256)
+    #[arg(long, requires = "record", value_parser = clap::value_parser!(u16).range(2..=256))]
+    colors: Option<u16>,
+
+    /// Resampling filter for downscaled frames: nearest, triangle, catmull or lanczos3 (default: lanczos3)
+    #[arg(long, requires = "gif_width", default_value = "lanczos3")]
+    resize_filter: capture::ResizeFilter,
+
+    /// How to handle recorded frames that change size: pad, crop or error (default: pad)
+    #[arg(long, requires = "record", default_value = "pad")]
+    frame_fit: capture::FrameFit,
+
+    /// Stop the recording after this many frames, whatever its length and frame rate
+    #[arg(long, requires = "record", value_parser = clap::value_parser!(u64).range(1..))]
+    max_frames: Option<u64>,
+
+    /// How the delay between frames varies: linear (constant) or ease (slower at the start and end)
+    #[arg(long, requires = "record", default_value = "linear")]
+    delay_ramp: capture::DelayRamp,
+
+    /// Only keep a frame when the page changed, each shown for as long as it really lasted
+    #[arg(long, requires = "record", conflicts_with = "delay_ramp")]
+    record_on_change: bool,
+
+    /// Where to keep recorded frames until they are encoded: disk (a temporary directory) or memory
+    #[arg(long, requires = "record", default_value = "disk")]
+    frame_storage: capture::FrameStorage,
+
+    /// Also save a full-resolution PNG at this offset into the recording (repeatable, e.g. 2.5s)
+    #[arg(long = "keyframe-at", requires = "record", value_parser = capture::parse_duration)]
+    keyframe_at: Vec<Duration>,
+
+    /// Only encode this part of the recording into the animation, e.g. 2s-5s; the whole length is still recorded
+    #[arg(long, value_name = "START-END", requires = "record")]
+    clip_time: Option<capture::ClipTime>,
+
+    /// Encode the recording as an animated WebP instead of a GIF
+    #[arg(long, requires = "record")]
+    webp: bool,
+
+    /// Encoding of output written to stdout with --output -: png, jpeg or webp for screenshots, gif or webp for recordings
+    #[arg(long, value_name = "FORMAT", conflicts_with_all = ["pdf", "webp"])]
+    stdout_format: Option<capture::StdoutFormat>,
+
+    /// Export the page as a PDF instead of a screenshot (value is paper size: letter, a4, ... or WIDTHxHEIGHT in cm)
+    #[arg(long, conflicts_with = "record")]
+    pdf: Option<Option<String>>,
+
+    /// Capture only an iframe (frame index path like 0 or 0.1, or a CSS selector)
+    #[arg(long, conflicts_with_all = ["record", "pdf"])]
+    frame: Option<String>,
+
+    /// Capture only the element matching a CSS selector
+    #[arg(long, value_name = "SELECTOR", conflicts_with_all = ["frame", "record", "pdf"])]
+    selector: Option<String>,
+
+    /// With --selector, capture every matching element into its own numbered file (<name>-0.png, <name>-1.png, ...)
+    #[arg(long, requires = "selector", conflicts_with = "montage")]
+    each: bool,
+
+    /// Capture N states of the page side by side in one sprite sheet, running --state-js before each
+    #[arg(long, value_name = "N", requires = "state_js", conflicts_with_all = ["record", "pdf", "each"], value_parser = clap::value_parser!(u32).range(1..))]
+    states: Option<u32>,
+
+    /// JavaScript that puts the page into the next state for --states; it gets the state's index, from 0, as arguments[0]
+    #[arg(long, value_name = "CODE", requires = "states")]
+    state_js: Option<String>,
+
+    /// With --states, also save each state to its own numbered file (<name>-0.png, <name>-1.png, ...)
+    #[arg(long, requires = "states")]
+    state_frames: bool,
+
+    /// Load the page once, then resize the window to each size in turn and save a screenshot at each (<name>-WxH.png)
+    #[arg(long, value_name = "WxH,...", value_delimiter = ',', conflicts_with_all = ["record", "pdf", "each", "states"])]
+    resize_sequence: Vec<capture::ViewportSize>,
+
+    /// How long to let the page settle after each resize of --resize-sequence (default: 500ms)
+    #[arg(long, value_name = "DURATION", requires = "resize_sequence", value_parser = capture::parse_duration)]
+    resize_settle: Option<Duration>,
+
+    /// Capture only the region from the top of the first selector's element to the bottom of the second's
+    #[arg(long, num_args = 2, value_names = ["START", "END"], conflicts_with_all = ["record", "pdf", "frame", "selector"])]
+    clip_between: Option<Vec<String>>,
+
+    /// Render the page for a CSS media type: screen or print (to see its print stylesheet)
+    #[arg(long, value_name = "TYPE", conflicts_with = "pdf")]
+    emulate_media: Option<capture::MediaType>,
+
+    /// Tell the page the user prefers reduced motion, to check that its animations calm down
+    #[arg(long)]
+    reduced_motion: bool,
+
+    /// Capture the page once per locale, e.g. en,fr,de, into files named after each (home-fr.png)
+    #[arg(long, value_delimiter = ',', value_parser = locales::parse_locale, conflicts_with_all = ["manifest", "compare_url", "if_changed"])]
+    locales: Vec<String>,
+
+    /// Render pages that set no background of their own over transparency instead of white (PNG or WebP output)
+    #[arg(long, conflicts_with = "pdf")]
+    transparent: bool,
+
+    /// Scroll to a vertical pixel offset or a CSS selector before capture
+    #[arg(long, conflicts_with = "pdf")]
+    scroll_to: Option<String>,
+
+    /// Also save the page's favicon next to the output, as <name>-favicon.<ext>
+    #[arg(long)]
+    favicon: bool,
+
+    /// Also save the computed styles of the element matching SELECTOR next to the output, as <name>-styles.json (repeatable; * for every property)
+    #[arg(long, value_name = "SELECTOR=PROP,...")]
+    styles: Vec<capture::StyleQuery>,
+
+    /// Also save the page's visible text to this file, for searching captures without OCR
+    #[arg(long, value_name = "PATH")]
+    text: Option<PathBuf>,
+
+    /// Only save the text of the element matching this CSS selector
+    #[arg(long, value_name = "SELECTOR", requires = "text")]
+    text_selector: Option<String>,
+
+    /// Save the page's performance metrics (first contentful paint, DOM content loaded, load time, JS heap size) as JSON to this file
+    #[arg(long, value_name = "PATH")]
+    perf: Option<PathBuf>,
+
+    /// Stamp a text label, e.g. an environment name, onto the screenshot
+    #[arg(long, conflicts_with_all = ["record", "pdf"])]
+    label: Option<String>,
+
+    /// Stamp the capture time onto the screenshot (after any --label text)
+    #[arg(long, conflicts_with_all = ["record", "pdf"])]
+    label_timestamp: bool,
+
+    /// Corner for the label: top-left, top-right, bottom-left or bottom-right (default: bottom-right)
+    #[arg(long, default_value = "bottom-right")]
+    label_position: weblook::label::LabelPosition,
+
+    /// Background colour of the label as #rgb, #rrggbb or #rrggbbaa (default: translucent black)
+    #[arg(long)]
+    label_background: Option<String>,
+
+    /// Cover the elements matching this CSS selector with a solid box before capture, e.g. to redact names or account numbers (repeatable)
+    #[arg(long, value_name = "SELECTOR")]
+    mask: Vec<String>,
+
+    /// Colour of the --mask boxes as #rgb, #rrggbb or #rrggbbaa (default: black)
+    #[arg(long, value_name = "COLOR", requires = "mask")]
+    mask_color: Option<String>,
+
+    /// Convert the screenshot, or every recorded frame, to grayscale, e.g. to review contrast
+    #[arg(long, conflicts_with = "pdf")]
+    grayscale: bool,
+
+    /// Invert the colours of the screenshot, or every recorded frame, e.g. to review a dark mode conversion
+    #[arg(long, conflicts_with = "pdf")]
+    invert: bool,
+
+    /// Frame the screenshot for presentation: rounded corners and a drop shadow on a padded background
+    #[arg(long, conflicts_with_all = ["record", "pdf"])]
+    polish: bool,
+
+    /// Space around a polished screenshot, in pixels (default: 64)
+    #[arg(long, value_name = "PIXELS", requires = "polish")]
+    polish_padding: Option<u32>,
+
+    /// Radius of a polished screenshot's corners, in pixels (default: 12)
+    #[arg(long, value_name = "PIXELS", requires = "polish")]
+    polish_radius: Option<u32>,
+
+    /// How far a polished screenshot's drop shadow spreads, in pixels; 0 for none (default: 24)
+    #[arg(long, value_name = "PIXELS", requires = "polish")]
+    polish_shadow: Option<u32>,
+
+    /// Background colour around a polished screenshot as #rgb, #rrggbb or #rrggbbaa (default: #e5e7eb)
+    #[arg(long, value_name = "COLOR", requires = "polish")]
+    polish_background: Option<String>,
+
     /// Set viewport size (format: WIDTHxHEIGHT, default: 1280x720)
     #[arg(short, long, default_value = "1280x720")]
     size: String,
@@ -40,9 +335,117 @@ struct Args {
     #[arg(short = 'j', long)]
     js: Option<String>,
     
+    /// Expand ${VAR} environment variables in --js and manifest js; unset variables are an error, or empty with --expand-env=empty
+    #[arg(long, value_name = "UNSET", num_args = 0..=1, require_equals = true, default_missing_value = "error")]
+    expand_env: Option<capture::MissingVar>,
+    
+    /// Capture every entry of a JSON manifest file, each with its own url, output, size, wait, selector and js
+    #[arg(long, conflicts_with = "url")]
+    manifest: Option<String>,
+
+    /// Name manifest entries without an output by this template, e.g. shots/{host}-{size}.png; placeholders are {host}, {path}, {date}, {index} and {size}
+    #[arg(long, value_name = "TEMPLATE", requires = "manifest")]
+    output_template: Option<capture::OutputTemplate>,
+
+    /// Number of manifest entries to capture at once (default: 1)
+    #[arg(long, requires = "manifest", conflicts_with = "url", default_value = "1", value_parser = clap::value_parser!(u16).range(1..=16))]
+    jobs: u16,
+    
+    /// Also assemble the manifest's screenshots into a contact sheet this many columns wide
+    #[arg(long, value_name = "COLS", requires = "manifest", conflicts_with_all = ["url", "record", "pdf"], value_parser = clap::value_parser!(u32).range(1..))]
+    montage: Option<u32>,
+    
+    /// Where to write the contact sheet (default: montage.png)
+    #[arg(long, requires = "montage")]
+    montage_output: Option<String>,
+    
+    /// Write each page's URL under its tile on the contact sheet
+    #[arg(long, requires = "montage")]
+    montage_labels: bool,
+    
+    /// Collect the manifest's screenshots into one multi-page document, pdf or tiff, instead of a file per page
+    #[arg(long, value_name = "FORMAT", requires = "manifest", conflicts_with_all = ["url", "record", "pdf"])]
+    combine: Option<CombineFormat>,
+    
+    /// Where to write the combined document (default: combined.pdf or combined.tiff)
+    #[arg(long, requires = "combine")]
+    combine_output: Option<String>,
+
+    /// Also capture this URL with the same options and write a side-by-side and a diff image of the two pages
+    #[arg(long, value_name = "URL", conflicts_with_all = ["manifest", "record", "pdf", "print_info"])]
+    compare_url: Option<Url>,
+
+    /// Fail when fewer than this percentage of pixels match between the compared pages (default: 100)
+    #[arg(long, value_name = "PERCENT", requires = "compare_url", default_value = "100", value_parser = compare::parse_similarity)]
+    min_similarity: f64,
+
+    /// Run a JSON flow file of steps (goto, fill, click, wait, wait_for) before capture
+    #[arg(long)]
+    flow: Option<String>,
+    
+    /// Let Chrome use hardware acceleration (omits --disable-gpu); fixes blank captures on some systems
+    #[arg(long)]
+    enable_gpu: bool,
+    
+    /// Run Chrome without its sandbox (adds --no-sandbox and --disable-dev-shm-usage), as Docker containers usually need; automatic when running as root
+    #[arg(long)]
+    no_sandbox: bool,
+    
+    /// Accept invalid TLS certificates (adds --ignore-certificate-errors), e.g. self-signed ones on staging servers; never use on untrusted networks
+    #[arg(long)]
+    ignore_cert_errors: bool,
+    
+    /// Launch this Chrome or Chromium executable instead of the one ChromeDriver finds, e.g. Chrome Beta or a pinned version
+    #[arg(long, value_name = "PATH")]
+    chrome_binary: Option<PathBuf>,
+    
+    /// Pick the browser's user agent at random from this file, one per line, instead of the built-in ones
+    #[arg(long, value_name = "PATH")]
+    user_agents_file: Option<PathBuf>,
+    
+    /// Set a Chrome preference, e.g. profile.default_content_setting_values.notifications=2 to block notification prompts (repeatable)
+    #[arg(long = "pref", value_name = "KEY=VALUE")]
+    prefs: Vec<capture::ChromePref>,
+    
+    /// Run a headful Chrome on this X display (e.g. :99 for Xvfb) instead of headless
+    #[arg(long)]
+    display: Option<String>,
+
+    /// Place the browser window at this screen position, e.g. 1920,0 for a second monitor (headful captures with --display)
+    #[arg(long, value_name = "X,Y", default_value = "0,0", allow_hyphen_values = true)]
+    window_position: capture::WindowPosition,
+
+    /// Grow the window until the page's viewport is exactly --size, making up for a headful browser's toolbars
+    #[arg(long)]
+    exact_viewport: bool,
+
+    /// Render at this many device pixels per CSS pixel, e.g. 2 for a crisp screenshot twice the size of --size; the viewport and user agent are unchanged
+    #[arg(long, value_name = "N")]
+    dpr: Option<f64>,
+    
     /// Capture browser console logs and save to specified file
     #[arg(long = "console-log")]
     console_log: Option<String>,
+
+    /// How several captures share --console-log: replace (a single page only), append (under a header per URL) or per-page (a log next to each output, e.g. home-console.log)
+    #[arg(long, value_name = "MODE", default_value = "replace", requires = "console_log")]
+    console_log_mode: capture::ConsoleLogMode,
+    
+    /// Start a fresh browser even if `weblook serve` is keeping one warm
+    #[arg(long)]
+    cold: bool,
+    
+    /// Validate the options, ChromeDriver and output path without capturing anything
+    #[arg(long)]
+    dry_run: bool,
+    
+    /// Print the output path, dimensions and size to stdout after a successful capture
+    #[arg(long)]
+    print_info: bool,
+
+    /// Run a shell command after a successful capture; {path} is replaced by the quoted output path
+    #[arg(long, value_name = "COMMAND")]
+    on_complete: Option<String>,
     
     /// Enable debug output
     #[arg(short, long)]
@@ -57,16 +460,61 @@ struct Args {
     #[cfg(feature = "mcp_experimental")]
     #[arg(long)]
     mcp_client: Option<String>,
+    
+    /// [EXPERIMENTAL] Bearer token required by the MCP server / sent by the MCP client
+    #[cfg(feature = "mcp_experimental")]
+    #[arg(long)]
+    mcp_token: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Check that ChromeDriver, Chrome and the working directory are ready for captures
+    Doctor,
+    /// Keep a browser open so that later captures can reuse it instead of starting Chrome
+    Serve,
+    /// List the browsers and WebDriver servers installed, with versions, and which backends are usable
+    ListBrowsers {
+        /// Print the list as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the version, features, output formats and browser backends of this build as JSON
+    Capabilities,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
     
+    match args.command {
+        Some(Commands::Doctor) => {
+            if !weblook::doctor::print_report(&weblook::doctor::run_checks()) {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(Commands::Serve) => return run_serve(args.debug).await,
+        Some(Commands::ListBrowsers { json }) => {
+            let list = weblook::doctor::list_browsers();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&list)?);
+            } else {
+                weblook::doctor::print_browser_list(&list);
+            }
+            return Ok(());
+        }
+        Some(Commands::Capabilities) => {
+            println!("{}", serde_json::to_string_pretty(&weblook::capabilities::capabilities())?);
+            return Ok(());
+        }
+        None => {}
+    }
+    
     // Check if we're running in MCP server mode
     #[cfg(feature = "mcp_experimental")]
     if let Some(addr_str) = args.mcp_server {
-        return run_mcp_server(addr_str).await;
+        return run_mcp_server(addr_str, args.mcp_token).await;
     }
     
     // Check if we're running in MCP client mode
@@ -80,8 +528,10 @@ async fn main() -> Result<()> {
 }
 
 async fn run_capture(args: Args) -> Result<()> {
-    // Handle piped input for URL
-    let url_str = if args.url.is_none() && !atty::is(atty::Stream::Stdin) {
+    // Handle piped input for URL; manifest entries carry their own URLs
+    let url_str = if args.manifest.is_some() {
+        String::new()
+    } else if args.url.is_none() && !atty::is(atty::Stream::Stdin) {
         let mut input = String::new();
         io::stdin().read_to_string(&mut input)?;
         input.trim().to_string()
@@ -90,34 +540,544 @@ async fn run_capture(args: Args) -> Result<()> {
     };
     
     // Parse URL
-    let _url = Url::parse(&url_str).context("Failed to parse URL")?;
+    if args.manifest.is_none() {
+        Url::parse(&url_str).context("Failed to parse URL")?;
+    }
     
     // Determine if we're recording and for how long
     let is_recording = args.record.is_some();
     let recording_length = args.record.flatten();
+    let recording_format = if args.webp { capture::RecordingFormat::Webp } else { capture::RecordingFormat::Gif };
+    
+    // Determine if we're exporting a PDF and at what paper size
+    let pdf = args.pdf.map(|paper| paper.unwrap_or_else(|| "letter".to_string()));
+    
+    // Determine output path; an s3:// or http(s):// output is captured to a
+    // temporary file and uploaded from there
+    let upload = args.output.as_deref().map(UploadTarget::from_output).transpose()?.flatten();
+    let upload_dir = upload.as_ref().map(|_| tempfile::tempdir()).transpose()?;
+    let output_path = match (&upload, &upload_dir) {
+        (Some(target), Some(dir)) => {
+            // Keep the target's extension, which decides the format captured in
+            let path = determine_output_path(None, is_recording.then_some(recording_format), pdf.is_some())?;
+            dir.path().join(match target.extension() {
+                Some(extension) => path.with_extension(extension),
+                None => path,
+            })
+        }
+        _ => determine_output_path(args.output, is_recording.then_some(recording_format), pdf.is_some())?,
+    };
     
-    // Determine output path
-    let output_path = determine_output_path(args.output, is_recording)?;
+    // Load the scripted flow, if any
+    let flow = args.flow.as_deref().map(capture::load_flow).transpose()?;
+    
+    // Frame the screenshot for presentation, filling in unset settings
+    let polish = if args.polish {
+        let defaults = Polish::default();
+        Some(Polish {
+            padding: args.polish_padding.unwrap_or(defaults.padding),
+            radius: args.polish_radius.unwrap_or(defaults.radius),
+            shadow: args.polish_shadow.unwrap_or(defaults.shadow),
+            background: args.polish_background.as_deref().map(weblook::label::parse_color).transpose()?.unwrap_or(defaults.background),
+        })
+    } else {
+        None
+    };
     
     // Set up capture options
-    let options = CaptureOptions {
+    let mut options = CaptureOptions {
         url: url_str,
+        referer: args.referer,
         output_path,
         wait: args.wait,
+        jitter: args.jitter,
+        ready_state: args.ready_state,
+        dialog: args.dialog,
+        max_wait: args.max_wait,
+        on_timeout_dump: args.on_timeout_dump,
         size: args.size,
         js: args.js,
+        expand_env: args.expand_env,
         debug: args.debug,
         is_recording,
         recording_length,
+        capture_fps: args.capture_fps,
+        playback_fps: args.playback_fps,
+        gif_width: args.gif_width,
+        colors: args.colors,
+        resize_filter: args.resize_filter,
+        frame_fit: args.frame_fit,
+        recording_format,
+        keyframes: args.keyframe_at,
+        clip_time: args.clip_time,
+        max_frames: args.max_frames,
+        delay_ramp: args.delay_ramp,
+        record_on_change: args.record_on_change,
+        frame_storage: args.frame_storage,
         console_log: args.console_log,
+        console_log_mode: args.console_log_mode,
+        pdf,
+        reuse_session: !args.cold && args.manifest.is_none(),
+        on_frame: None,
+        on_event: None,
+        stop_recording: None,
+        frame: args.frame,
+        selector: args.selector,
+        each: args.each,
+        states: args.states,
+        state_js: args.state_js,
+        state_frames: args.state_frames,
+        resize_sequence: args.resize_sequence,
+        resize_settle: args.resize_settle,
+        clip_between: args.clip_between.map(|selectors| (selectors[0].clone(), selectors[1].clone())),
+        flow,
+        enable_gpu: args.enable_gpu,
+        no_sandbox: args.no_sandbox,
+        ignore_cert_errors: args.ignore_cert_errors,
+        chrome_binary: args.chrome_binary,
+        user_agents_file: args.user_agents_file,
+        prefs: args.prefs,
+        display: args.display,
+        window_position: args.window_position,
+        exact_viewport: args.exact_viewport,
+        dpr: args.dpr,
+        wait_gone: args.wait_gone,
+        wait_min_chars: args.wait_min_chars,
+        wait_responses: args.wait_responses,
+        freeze_time: args.freeze_time.map(Duration::from_millis),
+        force_visible: args.force_visible,
+        font_substitutes: args.font_substitutes,
+        stable: args.stable,
+        allow_domains: args.allow_domain,
+        deny_domains: args.deny_domain,
+        emulate_media: args.emulate_media,
+        reduced_motion: args.reduced_motion,
+        locale: None,
+        transparent: args.transparent,
+        stdout_format: args.stdout_format,
+        scroll_to: args.scroll_to,
+        no_clobber: args.no_clobber,
+        name_by_final_url: args.name_by_final_url,
+        name_by_title: args.name_by_title,
+        capture_redirects: args.capture_redirects,
+        bidi: args.bidi,
+        favicon: args.favicon,
+        styles: args.styles,
+        text_path: args.text,
+        text_selector: args.text_selector,
+        perf_path: args.perf,
+        effects: capture::ImageEffects { grayscale: args.grayscale, invert: args.invert },
+        polish,
+        masks: args.mask,
+        mask_color: args.mask_color,
+        label: args.label,
+        label_timestamp: args.label_timestamp,
+        label_position: args.label_position,
+        label_background: args.label_background,
+    };
+    
+    // The summary would be mixed into the binary output on stdout
+    if args.print_info && options.output_path.to_str() == Some("-") {
+        return Err(anyhow::anyhow!("--print-info cannot be used when writing output to stdout"));
+    }
+    if args.on_complete.is_some() && options.output_path.to_str() == Some("-") {
+        return Err(anyhow::anyhow!("--on-complete needs an output file; it cannot be used when writing output to stdout"));
+    }
+    
+    // Uploads take a single file, and fail early without credentials
+    if let Some(target) = &upload {
+        if args.manifest.is_some() || args.compare_url.is_some() || !args.locales.is_empty() || options.each || options.state_frames || !options.resize_sequence.is_empty() || options.favicon || !options.styles.is_empty() || !options.keyframes.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Uploading to {} takes a single file; it cannot be combined with --manifest, --compare-url, --locales, --each, --state-frames, --resize-sequence, --favicon, --styles or --keyframe-at",
+                target
+            ));
+        }
+        if let UploadTarget::S3 { .. } = target {
+            upload::S3Credentials::from_env()?;
+        }
+    }
+    
+    // Capture each manifest entry, using the command line options as defaults
+    if let Some(manifest_path) = &args.manifest {
+        if options.output_path.to_str() == Some("-") {
+            return Err(anyhow::anyhow!("--manifest cannot write to stdout; give each entry an output file instead"));
+        }
+        let entries = capture::load_manifest(manifest_path)?;
+        let captures = entries.iter().enumerate()
+            .map(|(index, entry)| match &args.output_template {
+                Some(template) => entry.to_templated_options(&options, template, index),
+                None => entry.to_options(&options),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        capture::check_console_log(&options, captures.len())?;
+        if args.output_template.is_some() {
+            // Templates may sort captures into directories of their own
+            for capture_options in &captures {
+                if let Some(parent) = capture_options.output_path.parent()
+                    && !parent.as_os_str().is_empty()
+                {
+                    std::fs::create_dir_all(parent)
+                        .with_context(|| format!("Failed to create {}", parent.display()))?;
+                }
+            }
+        }
+        
+        if args.dry_run {
+            for capture_options in &captures {
+                capture::dry_run(capture_options)?;
+            }
+            return Ok(());
+        }
+        let settings = ManifestSettings {
+            jobs: args.jobs as usize,
+            print_info: args.print_info,
+            on_complete: args.on_complete,
+            montage: args.montage.map(|columns| MontageSettings {
+                columns,
+                output: PathBuf::from(args.montage_output.as_deref().unwrap_or("montage.png")),
+                labels: args.montage_labels,
+            }),
+            combine: args.combine.map(|format| DocumentSettings {
+                format,
+                output: args.combine_output.as_deref().map(PathBuf::from).unwrap_or_else(|| format.default_path()),
+            }),
+            changes: args.if_changed.as_deref().map(ChangeState::load).transpose()?,
+        };
+        let run = run_manifest(captures, &options, settings);
+        return until_shutdown(run, None).await;
+    }
+    
+    // Capture a second page with the same options and diff the two
+    if let Some(other_url) = &args.compare_url {
+        compare::check_compare(&options)?;
+        if args.dry_run {
+            capture::dry_run(&options)?;
+            capture::dry_run(&CaptureOptions { url: other_url.to_string(), ..options })?;
+            return Ok(());
+        }
+        let report = until_shutdown(async { Ok(compare::compare_pages(&options, other_url.as_str()).await?) }, None).await?;
+        eprintln!(
+            "{} {}",
+            "✓".green(),
+            format!("Wrote {} and {}", report.side_by_side.display(), report.diff.display()).bright_green()
+        );
+        if let Some(command) = &args.on_complete {
+            for path in [&report.primary, &report.other, &report.side_by_side, &report.diff] {
+                run_on_complete(command, path)?;
+            }
+        }
+        if report.similarity < args.min_similarity {
+            return Err(anyhow::anyhow!(
+                "Pages are {:.2}% similar, below --min-similarity {}%",
+                report.similarity, args.min_similarity
+            ));
+        }
+        eprintln!("{} {}", "✓".green(), format!("Pages are {:.2}% similar", report.similarity).bright_green());
+        return Ok(());
+    }
+    
+    // Capture the page in each locale, in one browser
+    if !args.locales.is_empty() {
+        locales::check_locales(&options, &args.locales)?;
+        if args.dry_run {
+            for locale in &args.locales {
+                capture::dry_run(&locales::locale_options(&options, locale))?;
+            }
+            return Ok(());
+        }
+        let infos = until_shutdown(async { Ok(locales::capture_locales(&options, &args.locales).await?) }, None).await?;
+        for info in &infos {
+            if args.print_info {
+                println!("{}", info);
+            }
+            if let Some(command) = &args.on_complete {
+                for path in info.written_files() {
+                    run_on_complete(command, path)?;
+                }
+            }
+        }
+        return Ok(());
+    }
+    
+    // Only validate when doing a dry run
+    if args.dry_run {
+        capture::dry_run(&options)?;
+        return Ok(());
+    }
+    
+    // Skip the capture if the page sends the same caching headers as last time
+    let mut changes = args.if_changed.as_deref().map(ChangeState::load).transpose()?;
+    let mut validators = None;
+    if let Some(changes) = &changes {
+        match changes.check(&options).await {
+            PageStatus::Unchanged => {
+                eprintln!("{} {}", "✓".green(), format!("{} is unchanged since the last capture; skipping it", options.url).bright_green());
+                return Ok(());
+            }
+            PageStatus::Changed(fetched) => validators = fetched,
+        }
+    }
+    
+    // Perform capture; a recording keeps what it has if it is interrupted
+    let stop_recording = is_recording.then(|| Arc::new(AtomicBool::new(false)));
+    options.stop_recording = stop_recording.clone();
+    let url = options.url.clone();
+    let info = until_shutdown(async { Ok(capture::perform_capture(options).await?) }, stop_recording.as_deref()).await?;
+    if let Some(target) = &upload {
+        let data = std::fs::read(&info.output_path)
+            .with_context(|| format!("Failed to read {}", info.output_path.display()))?;
+        let extension = info.output_path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        target.upload(data, upload::content_type(extension)).await?;
+        eprintln!("{} {}", "✓".green(), format!("Uploaded to {}", target).bright_green());
+    }
+    if args.print_info {
+        println!("{}", info);
+    }
+    if let Some(command) = &args.on_complete {
+        for path in info.written_files() {
+            run_on_complete(command, path)?;
+        }
+    }
+    if let Some(changes) = &mut changes {
+        changes.record(&url, validators);
+        changes.save()?;
+    }
+    
+    Ok(())
+}
+
+/// Run the `--on-complete` command for a capture saved to `output_path`
+///
+/// `{path}` in the command is replaced by the shell-quoted path, which is
+/// also passed in the `WEBLOOK_OUTPUT` environment variable.
+fn run_on_complete(command: &str, output_path: &Path) -> Result<()> {
+    let path = output_path.to_string_lossy();
+    #[cfg(unix)]
+    let (shell, flag, quoted) = ("sh", "-c", format!("'{}'", path.replace('\'', "'\\''")));
+    #[cfg(windows)]
+    let (shell, flag, quoted) = ("cmd", "/C", format!("\"{}\"", path));
+    
+    let status = Command::new(shell)
+        .arg(flag)
+        .arg(command.replace("{path}", &quoted))
+        .env("WEBLOOK_OUTPUT", output_path)
+        .status()
+        .with_context(|| format!("Failed to run --on-complete command: {}", command))?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("--on-complete command failed ({}): {}", status, command));
+    }
+    Ok(())
+}
+
+/// How to run a manifest and what to build from its captures
+struct ManifestSettings {
+    /// Number of entries to capture at once
+    jobs: usize,
+    print_info: bool,
+    on_complete: Option<String>,
+    montage: Option<MontageSettings>,
+    combine: Option<DocumentSettings>,
+    /// Caching headers of the previous run, to skip unchanged pages
+    changes: Option<ChangeState>,
+}
+
+/// Contact sheet to build from a manifest's captures
+struct MontageSettings {
+    columns: u32,
+    output: PathBuf,
+    labels: bool,
+}
+
+/// Multi-page document to collect a manifest's captures into
+struct DocumentSettings {
+    format: CombineFormat,
+    output: PathBuf,
+}
+
+/// Run the captures of a manifest, `settings.jobs` at a time, reporting every failure
+async fn run_manifest(captures: Vec<CaptureOptions>, base: &CaptureOptions, mut settings: ManifestSettings) -> Result<()> {
+    // Keep one ChromeDriver running for the whole manifest so concurrent
+    // captures don't stop it from under each other
+    let mut chromedriver = capture::ChromeDriverManager::new(9515, base.debug)
+        .with_display(base.display.as_deref());
+    chromedriver.start()?;
+    
+    let total = captures.len();
+    let changes = settings.changes.as_ref();
+    let results: Vec<_> = futures::stream::iter(captures)
+        .map(|options| async move {
+            let url = options.url.clone();
+            let validators = match changes {
+                Some(changes) => match changes.check(&options).await {
+                    PageStatus::Unchanged => return (url, None, Ok(None)),
+                    PageStatus::Changed(validators) => validators,
+                },
+                None => None,
+            };
+            (url, validators, capture::perform_capture(options).await.map(Some))
+        })
+        // Keep manifest order so a montage lays pages out as listed
+        .buffered(settings.jobs)
+        .collect()
+        .await;
+    
+    let mut failed = 0;
+    let mut captured = Vec::new();
+    for (url, validators, result) in results {
+        match result {
+            Ok(Some(info)) => {
+                if settings.print_info {
+                    println!("{}", info);
+                }
+                if let Some(command) = &settings.on_complete
+                    && let Err(e) = info.written_files().into_iter().try_for_each(|path| run_on_complete(command, path))
+                {
+                    failed += 1;
+                    eprintln!("Captured {}, but {:#}", url, e);
+                }
+                if let Some(changes) = &mut settings.changes {
+                    changes.record(&url, validators);
+                }
+                captured.push((url, info.output_path));
+            }
+            Ok(None) => {
+                eprintln!("{} is unchanged since the last capture; skipped it", url);
+            }
+            Err(e) => {
+                failed += 1;
+                eprintln!("Failed to capture {}: {:#}", url, e);
+            }
+        }
+    }
+    
+    if let Some(changes) = &settings.changes {
+        changes.save()?;
+    }
+    if let Some(montage) = &settings.montage {
+        save_montage(montage, &captured, base.no_clobber)?;
+    }
+    if let Some(combine) = &settings.combine {
+        save_document(combine, &captured, base.no_clobber)?;
+    }
+    
+    if failed > 0 {
+        return Err(anyhow::anyhow!("{} of {} manifest captures failed", failed, total));
+    }
+    Ok(())
+}
+
+/// Assemble the successful manifest captures into a contact sheet
+fn save_montage(montage: &MontageSettings, captured: &[(String, PathBuf)], no_clobber: bool) -> Result<()> {
+    if captured.is_empty() {
+        return Err(anyhow::anyhow!("No captures succeeded, so there is nothing to put in the montage"));
+    }
+    
+    let tiles = captured.iter()
+        .map(|(url, path)| {
+            let image = capture::open_capture(path).and_then(|reader| Ok(reader.decode()?))
+                .with_context(|| format!("Failed to read {} for the montage", path.display()))?
+                .to_rgba8();
+            let caption = montage.labels.then(|| url.clone());
+            Ok(weblook::montage::Tile { image, caption })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    
+    let sheet = weblook::montage::build_montage(&tiles, montage.columns);
+    weblook::montage::save_montage(&sheet, &montage.output, no_clobber)?;
+    eprintln!("{} {}", "✓".green(), format!("Montage of {} captures saved to {}", tiles.len(), montage.output.display()).bright_green());
+    
+    Ok(())
+}
+
+/// Collect the successful manifest captures into one document, one page
+/// each, and remove their separate files
+fn save_document(combine: &DocumentSettings, captured: &[(String, PathBuf)], no_clobber: bool) -> Result<()> {
+    if captured.is_empty() {
+        return Err(anyhow::anyhow!("No captures succeeded, so there is nothing to put in the document"));
+    }
+    
+    let pages = captured.iter()
+        .map(|(_, path)| {
+            Ok(capture::open_capture(path).and_then(|reader| Ok(reader.decode()?))
+                .with_context(|| format!("Failed to read {} for the document", path.display()))?
+                .to_rgba8())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    
+    weblook::combine::save_document(&pages, combine.format, &combine.output, no_clobber)?;
+    for (_, path) in captured {
+        std::fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))?;
+    }
+    eprintln!("{} {}", "✓".green(), format!("{} pages saved to {}", pages.len(), combine.output.display()).bright_green());
+    
+    Ok(())
+}
+
+/// Keep a warm browser session open until Ctrl+C or SIGTERM
+async fn run_serve(debug: bool) -> Result<()> {
+    let server = weblook::warm::serve(debug).await?;
+    eprintln!("{}", "Warm browser session ready; captures will reuse it until this stops. Press Ctrl+C to stop.".bright_green());
+    if debug {
+        eprintln!("Session {} on ChromeDriver port {}", server.info.session_id, server.info.port);
+    }
+    
+    let signal = shutdown_signal().await;
+    eprintln!("Stopping the warm browser session...");
+    server.stop().await?;
+    signal?;
+    Ok(())
+}
+
+/// Wait for Ctrl+C or, on Unix, SIGTERM, returning the name of the signal
+async fn shutdown_signal() -> Result<&'static str> {
+    let interrupt = async {
+        signal::ctrl_c().await?;
+        Ok::<_, anyhow::Error>("SIGINT")
     };
     
-    // Perform capture
-    capture::perform_capture(options).await
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())?.recv().await;
+        Ok::<_, anyhow::Error>("SIGTERM")
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<Result<&'static str>>();
+    
+    tokio::select! {
+        name = interrupt => name,
+        name = terminate => name,
+    }
+}
+
+/// Run `work` unless a shutdown signal arrives first
+///
+/// On a signal `work` is dropped, which quits the browser session and stops
+/// the ChromeDriver it started before the error is returned. When `stop` is
+/// given, the first signal instead sets it and lets `work` finish, so that a
+/// recording can save the frames captured so far; a second signal drops it.
+async fn until_shutdown<T>(work: impl Future<Output = Result<T>>, stop: Option<&AtomicBool>) -> Result<T> {
+    tokio::pin!(work);
+    
+    tokio::select! {
+        result = &mut work => return result,
+        name = shutdown_signal() => {
+            let name = name?;
+            let Some(stop) = stop else {
+                return Err(anyhow::anyhow!("Capture interrupted by {}", name));
+            };
+            eprintln!("{} {}", "!".yellow(), format!("Received {}; saving the frames recorded so far (send it again to abort)", name).yellow());
+            stop.store(true, Ordering::SeqCst);
+        }
+    }
+    
+    tokio::select! {
+        result = work => result,
+        name = shutdown_signal() => Err(anyhow::anyhow!("Capture interrupted by {}", name?)),
+    }
 }
 
 #[cfg(feature = "mcp_experimental")]
-async fn run_mcp_server(addr_str: String) -> Result<()> {
+async fn run_mcp_server(addr_str: String, token: Option<String>) -> Result<()> {
     // Parse socket address
     let addr: SocketAddr = addr_str.parse()
         .context("Invalid MCP server address format. Expected format: host:port")?;
@@ -126,12 +1086,15 @@ async fn run_mcp_server(addr_str: String) -> Result<()> {
     
     // Create and start MCP server
     let mut server = mcp::MCPServer::new();
-    server.start(addr).await?;
+    if let Some(token) = &token {
+        server = server.with_auth_token(token);
+    }
+    let bound_addr = server.start(addr).await?;
     
-    println!("MCP server started. Press Ctrl+C to stop.");
+    println!("MCP server listening on {}. Press Ctrl+C to stop.", bound_addr);
     
-    // Wait for Ctrl+C
-    signal::ctrl_c().await?;
+    // Wait for Ctrl+C or SIGTERM
+    shutdown_signal().await?;
     
     println!("Stopping MCP server...");
     server.stop().await?;
@@ -145,7 +1108,7 @@ async fn run_mcp_client(endpoint: String, args: &Args) -> Result<()> {
     println!("Connecting to MCP server at {}... (EXPERIMENTAL FEATURE)", endpoint);
     
     // Create MCP client
-    let client = mcp::MCPClient::new(&endpoint).await?;
+    let client = mcp::MCPClient::new(&endpoint, mcp::client::DEFAULT_TIMEOUT, args.mcp_token.as_deref()).await?;
     
     // Get available actions
     let actions = client.get_available_actions().await?;
@@ -159,13 +1122,21 @@ async fn run_mcp_client(endpoint: String, args: &Args) -> Result<()> {
         let params = serde_json::json!({
             "url": args.url.clone().unwrap_or_else(|| "http://127.0.0.1:8080".to_string()),
             "duration": args.record.flatten().unwrap_or(10),
-            "wait": args.wait,
+            "wait": args.wait.as_secs(),
             "size": args.size,
             "js": args.js,
         });
         
         println!("Invoking record_interaction action...");
-        let response = client.invoke_action("record_interaction", params).await?;
+        let response = client.invoke_action_with_progress("record_interaction", params, |event| {
+            if let (Some(frame), Some(total)) = (event["frame"].as_u64(), event["total_frames"].as_u64()) {
+                eprint!("\rRecording frame {}/{}", frame, total);
+                std::io::stderr().flush().ok();
+                if frame == total {
+                    eprintln!();
+                }
+            }
+        }).await?;
         
         // Handle response
         if let Some(image_data) = response["image_data"].as_str() {
@@ -173,7 +1144,7 @@ async fn run_mcp_client(endpoint: String, args: &Args) -> Result<()> {
             let decoded = base64::engine::general_purpose::STANDARD.decode(image_data)?;
             
             // Determine output path
-            let output_path = determine_output_path(args.output.clone(), true)?;
+            let output_path = determine_output_path(args.output.clone(), Some(capture::RecordingFormat::Gif), false)?;
             
             // Write to file or stdout
             if output_path.to_str() == Some("-") {
@@ -185,11 +1156,40 @@ async fn run_mcp_client(endpoint: String, args: &Args) -> Result<()> {
         } else {
             println!("Error: No image data in response");
         }
+    } else if let Some(paper_size) = &args.pdf {
+        // Invoke capture_pdf action
+        let params = serde_json::json!({
+            "url": args.url.clone().unwrap_or_else(|| "http://127.0.0.1:8080".to_string()),
+            "wait": args.wait.as_secs(),
+            "paper_size": paper_size.clone().unwrap_or_else(|| "letter".to_string()),
+        });
+        
+        println!("Invoking capture_pdf action...");
+        let response = client.invoke_action("capture_pdf", params).await?;
+        
+        // Handle response
+        if let Some(image_data) = response["image_data"].as_str() {
+            // Decode base64 data
+            let decoded = base64::engine::general_purpose::STANDARD.decode(image_data)?;
+            
+            // Determine output path
+            let output_path = determine_output_path(args.output.clone(), None, true)?;
+            
+            // Write to file or stdout
+            if output_path.to_str() == Some("-") {
+                io::stdout().write_all(&decoded)?;
+            } else {
+                std::fs::write(&output_path, decoded)?;
+                println!("PDF saved to {}", output_path.display());
+            }
+        } else {
+            println!("Error: No image data in response");
+        }
     } else {
         // Invoke capture_screenshot action
         let params = serde_json::json!({
             "url": args.url.clone().unwrap_or_else(|| "http://127.0.0.1:8080".to_string()),
-            "wait": args.wait,
+            "wait": args.wait.as_secs(),
             "size": args.size,
             "js": args.js,
         });
@@ -203,7 +1203,7 @@ async fn run_mcp_client(endpoint: String, args: &Args) -> Result<()> {
             let decoded = base64::engine::general_purpose::STANDARD.decode(image_data)?;
             
             // Determine output path
-            let output_path = determine_output_path(args.output.clone(), false)?;
+            let output_path = determine_output_path(args.output.clone(), None, false)?;
             
             // Write to file or stdout
             if output_path.to_str() == Some("-") {
@@ -220,7 +1220,7 @@ async fn run_mcp_client(endpoint: String, args: &Args) -> Result<()> {
     Ok(())
 }
 
-fn determine_output_path(output: Option<String>, is_recording: bool) -> Result<PathBuf> {
+fn determine_output_path(output: Option<String>, recording: Option<capture::RecordingFormat>, is_pdf: bool) -> Result<PathBuf> {
     match output {
         Some(path) => {
             if path == "-" {
@@ -232,8 +1232,10 @@ fn determine_output_path(output: Option<String>, is_recording: bool) -> Result<P
         },
         None => {
             // Default output path
-            if is_recording {
-                Ok(PathBuf::from("weblook.gif"))
+            if let Some(format) = recording {
+                Ok(PathBuf::from(format!("weblook.{}", format.extension())))
+            } else if is_pdf {
+                Ok(PathBuf::from("weblook.pdf"))
             } else {
                 Ok(PathBuf::from("weblook.png"))
             }
@@ -1,16 +1,24 @@
 use anyhow::Result;
+#[cfg(not(test))]
 use base64::Engine;
 use super::mcp_sdk::server::context_action::{ContextAction, Parameter, ParameterType};
 use super::mcp_sdk::server::Server;
-use serde_json::Value;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::capture::{self, CaptureOptions};
+#[cfg(not(test))]
+use crate::capture;
+use crate::capture::CaptureOptions;
 
 /// Type alias for context action handler functions
-pub type ContextActionHandler = Arc<dyn Fn(Value) -> Result<Value> + Send + Sync>;
+pub type ContextActionHandler = super::mcp_sdk::ActionHandler;
 
 /// Register all WebLook context actions with the MCP server
+///
+/// Every action drives a browser through ChromeDriver on port 9515, and a
+/// capture stops the ChromeDriver it started when it finishes, so the actions
+/// are exclusive: overlapping requests run one after another.
 pub fn register_actions(server: &mut Server) -> Result<()> {
     // Register capture_screenshot action
     let capture_screenshot = ContextAction::new(
@@ -21,9 +29,10 @@ pub fn register_actions(server: &mut Server) -> Result<()> {
             Parameter::new("wait", "Wait time before capture in seconds", ParameterType::Integer, false),
             Parameter::new("size", "Viewport size (format: WIDTHxHEIGHT)", ParameterType::String, false),
             Parameter::new("js", "JavaScript to execute before capture", ParameterType::String, false),
+            Parameter::new("selector", "CSS selector of the element to capture (default: the whole viewport)", ParameterType::String, false),
         ],
         capture_screenshot_handler(),
-    );
+    ).exclusive();
     server.register_action(capture_screenshot)?;
 
     // Register record_interaction action
@@ -38,47 +47,86 @@ pub fn register_actions(server: &mut Server) -> Result<()> {
             Parameter::new("js", "JavaScript to execute before recording", ParameterType::String, false),
         ],
         record_interaction_handler(),
-    );
+    ).exclusive();
     server.register_action(record_interaction)?;
 
+    // Register capture_pdf action
+    let capture_pdf = ContextAction::new(
+        "capture_pdf",
+        "Export a web page as a PDF document",
+        vec![
+            Parameter::new("url", "URL to export", ParameterType::String, true),
+            Parameter::new("wait", "Wait time before export in seconds", ParameterType::Integer, false),
+            Parameter::new("paper_size", "Paper size (letter, legal, tabloid, a3, a4, a5 or WIDTHxHEIGHT in cm)", ParameterType::String, false),
+        ],
+        capture_pdf_handler(),
+    ).exclusive();
+    server.register_action(capture_pdf)?;
+
+    // Register extract_text action
+    let extract_text = ContextAction::new(
+        "extract_text",
+        "Extract the visible text and title of a web page",
+        vec![
+            Parameter::new("url", "URL to extract text from", ParameterType::String, true),
+            Parameter::new("wait", "Wait time before extraction in seconds", ParameterType::Integer, false),
+            Parameter::new("selector", "CSS selector of the element to extract (default: body)", ParameterType::String, false),
+        ],
+        extract_text_handler(),
+    ).exclusive();
+    server.register_action(extract_text)?;
+
     Ok(())
 }
 
+/// Capture options for an MCP action, with WebLook's defaults for everything
+/// the action's parameters don't set
+fn mcp_options(url: String, output_path: PathBuf, wait: Duration, size: String, js: Option<String>) -> CaptureOptions {
+    CaptureOptions {
+        url,
+        output_path,
+        wait,
+        size,
+        js,
+        ..Default::default()
+    }
+}
+
+/// Capture options for the capture_screenshot action's parameters
+///
+/// `selector` is always a CSS selector for the element to capture, never an
+/// iframe path, even when it looks like a frame index.
+pub fn screenshot_options(params: &serde_json::Value, output_path: PathBuf) -> CaptureOptions {
+    let url = params["url"].as_str().unwrap_or("http://127.0.0.1:8080").to_string();
+    let wait = Duration::from_secs(params["wait"].as_u64().unwrap_or(10));
+    let size = params["size"].as_str().unwrap_or("1280x720").to_string();
+    let js = params["js"].as_str().map(|s| s.to_string());
+    
+    CaptureOptions {
+        selector: params["selector"].as_str().map(|s| s.to_string()),
+        ..mcp_options(url, output_path, wait, size, js)
+    }
+}
+
 /// Handler for the capture_screenshot action
 fn capture_screenshot_handler() -> ContextActionHandler {
-    Arc::new(|params| {
-        let rt = tokio::runtime::Runtime::new()?;
-        
-        rt.block_on(async {
-            // Extract parameters
-            let url = params["url"].as_str().unwrap_or("http://127.0.0.1:8080").to_string();
-            let wait = params["wait"].as_u64().unwrap_or(10);
-            let size = params["size"].as_str().unwrap_or("1280x720").to_string();
-            let js = params["js"].as_str().map(|s| s.to_string());
-            
-            // Create temporary file for output
-            let temp_file = tempfile::NamedTempFile::new()?;
+    Arc::new(|params, _progress| {
+        Box::pin(async move {
+            // Create a temporary output file, named so its format is explicit
+            let temp_file = tempfile::Builder::new().suffix(".png").tempfile()?;
             let output_path = temp_file.path().to_path_buf();
             
             // Set up capture options
-            let options = CaptureOptions {
-                url,
-                output_path: output_path.clone(),
-                wait,
-                size,
-                js,
-                debug: false,
-                is_recording: false,
-                recording_length: None,
-            };
+            let options = screenshot_options(&params, output_path.clone());
             
             // For testing purposes, just return mock data
             #[cfg(test)]
             {
-                return Ok(serde_json::json!({
+                let _ = (options, output_path);
+                Ok(serde_json::json!({
                     "image_data": "iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP8z8BQDwAEhQGAhKmMIQAAAABJRU5ErkJggg==",
                     "format": "png",
-                }));
+                }))
             }
             
             // Perform capture
@@ -101,41 +149,45 @@ fn capture_screenshot_handler() -> ContextActionHandler {
 }
 
 /// Handler for the record_interaction action
+///
+/// Reports a `{"stage": "recording", "frame": n, "total_frames": total}` progress
+/// event after each captured frame when invoked through the streaming endpoint.
 fn record_interaction_handler() -> ContextActionHandler {
-    Arc::new(|params| {
-        let rt = tokio::runtime::Runtime::new()?;
-        
-        rt.block_on(async {
+    Arc::new(|params, progress| {
+        Box::pin(async move {
             // Extract parameters
             let url = params["url"].as_str().unwrap_or("http://127.0.0.1:8080").to_string();
             let duration = params["duration"].as_u64().unwrap_or(10);
-            let wait = params["wait"].as_u64().unwrap_or(10);
+            let wait = Duration::from_secs(params["wait"].as_u64().unwrap_or(10));
             let size = params["size"].as_str().unwrap_or("1280x720").to_string();
             let js = params["js"].as_str().map(|s| s.to_string());
             
-            // Create temporary file for output
-            let temp_file = tempfile::NamedTempFile::new()?;
+            // Create a temporary output file, named so its format is explicit
+            let temp_file = tempfile::Builder::new().suffix(".gif").tempfile()?;
             let output_path = temp_file.path().to_path_buf();
             
             // Set up capture options
             let options = CaptureOptions {
-                url,
-                output_path: output_path.clone(),
-                wait,
-                size,
-                js,
-                debug: false,
                 is_recording: true,
                 recording_length: Some(duration),
+                on_frame: Some(Arc::new(move |frame, total_frames| {
+                    progress.report(serde_json::json!({
+                        "stage": "recording",
+                        "frame": frame,
+                        "total_frames": total_frames,
+                    }));
+                })),
+                ..mcp_options(url, output_path.clone(), wait, size, js)
             };
             
             // For testing purposes, just return mock data
             #[cfg(test)]
             {
-                return Ok(serde_json::json!({
+                let _ = (options, output_path);
+                Ok(serde_json::json!({
                     "image_data": "R0lGODlhAQABAIAAAAAAAP///yH5BAEAAAAALAAAAAABAAEAAAIBRAA7",
                     "format": "gif",
-                }));
+                }))
             }
             
             // Perform capture
@@ -156,3 +208,85 @@ fn record_interaction_handler() -> ContextActionHandler {
         })
     })
 }
+
+/// Handler for the capture_pdf action
+fn capture_pdf_handler() -> ContextActionHandler {
+    Arc::new(|params, _progress| {
+        Box::pin(async move {
+            // Extract parameters
+            let url = params["url"].as_str().unwrap_or("http://127.0.0.1:8080").to_string();
+            let wait = Duration::from_secs(params["wait"].as_u64().unwrap_or(10));
+            let paper_size = params["paper_size"].as_str().unwrap_or("letter").to_string();
+            
+            // Create a temporary output file, named so its format is explicit
+            let temp_file = tempfile::Builder::new().suffix(".pdf").tempfile()?;
+            let output_path = temp_file.path().to_path_buf();
+            
+            // Set up capture options
+            let options = CaptureOptions {
+                pdf: Some(paper_size),
+                ..mcp_options(url, output_path.clone(), wait, "1280x720".to_string(), None)
+            };
+            
+            // For testing purposes, just return mock data
+            #[cfg(test)]
+            {
+                let _ = (options, output_path);
+                Ok(serde_json::json!({
+                    "image_data": "JVBERi0xLjQKJcOkw7zDtsOfCg==",
+                    "format": "pdf",
+                }))
+            }
+            
+            // Perform capture
+            #[cfg(not(test))]
+            {
+                capture::perform_capture(options).await?;
+                
+                // Read the exported PDF and encode as base64
+                let pdf_data = std::fs::read(output_path)?;
+                let base64_data = base64::engine::general_purpose::STANDARD.encode(&pdf_data);
+                
+                // Return the result
+                Ok(serde_json::json!({
+                    "image_data": base64_data,
+                    "format": "pdf",
+                }))
+            }
+        })
+    })
+}
+
+/// Handler for the extract_text action
+fn extract_text_handler() -> ContextActionHandler {
+    Arc::new(|params, _progress| {
+        Box::pin(async move {
+            // Extract parameters
+            let url = params["url"].as_str().unwrap_or("http://127.0.0.1:8080").to_string();
+            let wait = Duration::from_secs(params["wait"].as_u64().unwrap_or(10));
+            let selector = params["selector"].as_str().map(|s| s.to_string());
+            
+            // For testing purposes, just return mock data
+            #[cfg(test)]
+            {
+                let _ = (url, wait, selector);
+                Ok(serde_json::json!({
+                    "title": "Example Domain",
+                    "text": "Example Domain\nThis domain is for use in illustrative examples in documents.",
+                }))
+            }
+            
+            // Perform extraction
+            #[cfg(not(test))]
+            {
+                let page = capture::extract_text(&url, wait, "1280x720", selector.as_deref(), false).await?;
+                
+                // Return the result
+                Ok(serde_json::json!({
+                    "title": page.title,
+                    "text": page.text,
+                }))
+            }
+        })
+    })
+}
@@ -3,6 +3,11 @@ use super::mcp_sdk::client::{Client, ClientConfig};
 use serde_json::Value;
 use std::time::Duration;
 
+pub use super::mcp_sdk::client::TimeoutError;
+
+/// Default time to wait for an MCP server to answer a request
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// MCP client for WebLook
 pub struct MCPClient {
     client: Client,
@@ -10,11 +15,17 @@ pub struct MCPClient {
 
 impl MCPClient {
     /// Create a new MCP client connected to the specified endpoint
-    pub async fn new(endpoint: &str) -> Result<Self> {
+    ///
+    /// Requests that take longer than `timeout` fail with a [`TimeoutError`].
+    /// When `auth_token` is given it is sent as a bearer token with every request.
+    pub async fn new(endpoint: &str, timeout: Duration, auth_token: Option<&str>) -> Result<Self> {
         let config = ClientConfig::new()
             .with_endpoint(endpoint)
-            .with_timeout(Duration::from_secs(60))
-            .with_auth_disabled(); // For simplicity; in production, use proper auth
+            .with_timeout(timeout);
+        let config = match auth_token {
+            Some(token) => config.with_auth_token(token),
+            None => config.with_auth_disabled(),
+        };
         
         let client = Client::new(config).await?;
         
@@ -27,6 +38,15 @@ impl MCPClient {
         Ok(response)
     }
 
+    /// Invoke a context action, receiving progress events while it runs
+    pub async fn invoke_action_with_progress<F>(&self, action_name: &str, params: Value, on_progress: F) -> Result<Value>
+    where
+        F: FnMut(Value),
+    {
+        let response = self.client.invoke_action_streaming(action_name, params, on_progress).await?;
+        Ok(response)
+    }
+
     /// Get available actions from the remote MCP server
     pub async fn get_available_actions(&self) -> Result<Vec<String>> {
         let actions = self.client.get_available_actions().await?;
@@ -8,11 +8,12 @@
 //
 // cargo build --features mcp_experimental
 //
-// Note: The MCP implementation currently uses a mock SDK for development purposes.
+// Note: The MCP implementation currently uses a minimal in-tree SDK until an
+// official Rust SDK is available.
 
-// Use our mock SDK implementation for now
-pub mod mock_sdk;
-pub use mock_sdk as mcp_sdk;
+// Use our in-tree SDK implementation for now
+pub mod sdk;
+pub use sdk as mcp_sdk;
 
 pub mod server;
 pub mod client;
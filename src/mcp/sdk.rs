@@ -0,0 +1,682 @@
+// Minimal MCP SDK used by WebLook until an official Rust SDK is available.
+//
+// The server side exposes registered context actions over HTTP:
+//   GET  /actions         -> list of available actions
+//   POST /actions/{name}  -> invoke an action with a JSON parameter object
+//   POST /actions/{name}/stream -> invoke an action, streaming server-sent events:
+//        `progress` events carrying the JSON reported by the action, then a single
+//        `result` event with the action's response or an `error` event
+//
+// Unless authentication is explicitly disabled, every request must carry an
+// `Authorization: Bearer <token>` header matching the server's token.
+//
+// The client side speaks the same wire format. Failed invocations are answered
+// with a non-2xx status and a `{"error": "..."}` body.
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, watch};
+
+// Server-side types
+pub mod server {
+    use super::*;
+    use axum::extract::{Path, Request, State};
+    use axum::http::{header, StatusCode};
+    use axum::middleware::{self, Next};
+    use axum::response::sse::{Event, Sse};
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use futures::Stream;
+
+    type ActionMap = Arc<Mutex<HashMap<String, ContextAction>>>;
+
+    #[derive(Clone)]
+    pub struct Server {
+        actions: ActionMap,
+        addr: SocketAddr,
+        auth_token: Option<Arc<str>>,
+        auth_disabled: bool,
+        /// Set once [`Server::shutdown`] is called, even before serving starts
+        shutdown: Arc<watch::Sender<bool>>,
+        /// Taken by exclusive actions for as long as they run
+        exclusive_turn: Arc<tokio::sync::Mutex<()>>,
+    }
+    
+    impl Server {
+        pub fn new(config: ServerConfig) -> Self {
+            Server {
+                actions: Arc::new(Mutex::new(HashMap::new())),
+                addr: config.addr,
+                auth_token: config.auth_token.map(Arc::from),
+                auth_disabled: config.auth_disabled,
+                shutdown: Arc::new(watch::Sender::new(false)),
+                exclusive_turn: Arc::new(tokio::sync::Mutex::new(())),
+            }
+        }
+        
+        /// Make `action` available to clients
+        ///
+        /// Exclusive actions (see [`ContextAction::exclusive`]) share one turn,
+        /// so only one of them runs at a time.
+        pub fn register_action(&mut self, mut action: ContextAction) -> Result<()> {
+            if action.exclusive {
+                action.turn = Some(self.exclusive_turn.clone());
+            }
+            let mut actions = self.actions.lock().unwrap();
+            actions.insert(action.name.clone(), action);
+            Ok(())
+        }
+        
+        /// Bind the server's address, letting the OS choose a port when it is 0
+        pub async fn bind(&self) -> Result<tokio::net::TcpListener> {
+            tokio::net::TcpListener::bind(self.addr)
+                .await
+                .with_context(|| format!("Failed to bind MCP server to {}", self.addr))
+        }
+        
+        pub async fn serve(&self) -> Result<()> {
+            let listener = self.bind().await?;
+            self.serve_on(listener).await
+        }
+        
+        /// Serve requests on an already bound listener
+        pub async fn serve_on(&self, listener: tokio::net::TcpListener) -> Result<()> {
+            if !self.auth_disabled && self.auth_token.is_none() {
+                return Err(anyhow::anyhow!("MCP server authentication is enabled but no token was configured"));
+            }
+            
+            let mut app = Router::new()
+                .route("/actions", get(list_actions))
+                .route("/actions/{name}", post(invoke_action))
+                .route("/actions/{name}/stream", post(invoke_action_stream))
+                .with_state(self.actions.clone());
+            
+            if let Some(token) = self.auth_token.clone().filter(|_| !self.auth_disabled) {
+                app = app.layer(middleware::from_fn_with_state(token, require_bearer_token));
+            }
+            
+            let mut shutdown = self.shutdown.subscribe();
+            axum::serve(listener, app)
+                .with_graceful_shutdown(async move {
+                    let _ = shutdown.wait_for(|&stop| stop).await;
+                })
+                .await
+                .context("MCP server error")?;
+            
+            Ok(())
+        }
+        
+        /// Stop serving, or stop as soon as serving starts if it hasn't yet
+        pub async fn shutdown(&self) -> Result<()> {
+            self.shutdown.send_replace(true);
+            Ok(())
+        }
+    }
+    
+    /// Middleware rejecting requests without the expected bearer token
+    async fn require_bearer_token(State(token): State<Arc<str>>, request: Request, next: Next) -> Response {
+        let provided = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        
+        match provided {
+            Some(provided) if constant_time_eq(provided.as_bytes(), token.as_bytes()) => next.run(request).await,
+            _ => (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({ "error": "Missing or invalid bearer token" })),
+            ).into_response(),
+        }
+    }
+    
+    /// Compare two byte strings without leaking where they differ through timing
+    fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+        a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+    
+    /// Handler for `GET /actions`
+    async fn list_actions(State(actions): State<ActionMap>) -> Json<Vec<client::ActionInfo>> {
+        let actions = actions.lock().unwrap();
+        let mut infos: Vec<client::ActionInfo> = actions
+            .values()
+            .map(|action| client::ActionInfo {
+                name: action.name.clone(),
+                description: action.description.clone(),
+                parameters: action.parameters.clone(),
+            })
+            .collect();
+        infos.sort_by(|a, b| a.name.cmp(&b.name));
+        Json(infos)
+    }
+    
+    /// Look up an action and validate the parameters for it
+    fn prepare_action(actions: &ActionMap, name: &str, params: &Value) -> std::result::Result<ContextAction, (StatusCode, Json<Value>)> {
+        let action = actions.lock().unwrap().get(name).cloned();
+        let Some(action) = action else {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(serde_json::json!({ "error": format!("Action not found: {}", name) })),
+            ));
+        };
+        
+        // Reject invalid parameters before they reach the handler
+        if let Err(fields) = action.validate(params) {
+            let summary: Vec<String> = fields
+                .iter()
+                .map(|f| format!("{} ({})", f.name, f.problem))
+                .collect();
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({
+                    "error": format!("Invalid parameters: {}", summary.join(", ")),
+                    "fields": fields,
+                })),
+            ));
+        }
+        
+        Ok(action)
+    }
+    
+    /// Run an action handler, reporting progress to `progress`
+    async fn run_action(action: ContextAction, params: Value, progress: Progress) -> std::result::Result<Value, String> {
+        // Run the handler as its own task so a panic is reported instead of
+        // tearing down the connection. Exclusive actions wait their turn in
+        // that task, so it is kept even if the client goes away.
+        let result = tokio::spawn(async move {
+            let _turn = match &action.turn {
+                Some(turn) => Some(turn.lock().await),
+                None => None,
+            };
+            (action.handler)(params, progress).await
+        }).await;
+        
+        match result {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(e)) => Err(format!("{:#}", e)),
+            Err(e) => Err(format!("Action handler panicked: {}", e)),
+        }
+    }
+    
+    /// Handler for `POST /actions/{name}`
+    async fn invoke_action(
+        State(actions): State<ActionMap>,
+        Path(name): Path<String>,
+        Json(params): Json<Value>,
+    ) -> (StatusCode, Json<Value>) {
+        let action = match prepare_action(&actions, &name, &params) {
+            Ok(action) => action,
+            Err(response) => return response,
+        };
+        
+        match run_action(action, params, Progress::disabled()).await {
+            Ok(value) => (StatusCode::OK, Json(value)),
+            Err(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({ "error": e })),
+            ),
+        }
+    }
+    
+    /// Handler for `POST /actions/{name}/stream`
+    async fn invoke_action_stream(
+        State(actions): State<ActionMap>,
+        Path(name): Path<String>,
+        Json(params): Json<Value>,
+    ) -> Response {
+        let action = match prepare_action(&actions, &name, &params) {
+            Ok(action) => action,
+            Err(response) => return response.into_response(),
+        };
+        
+        let (tx, rx) = mpsc::unbounded_channel();
+        let progress = Progress::new(tx.clone());
+        tokio::spawn(async move {
+            let event = match run_action(action, params, progress).await {
+                Ok(value) => StreamEvent::Result(value),
+                Err(e) => StreamEvent::Error(e),
+            };
+            let _ = tx.send(event);
+        });
+        
+        Sse::new(event_stream(rx)).into_response()
+    }
+    
+    /// Convert queued events into server-sent events, ending after the final one
+    fn event_stream(rx: mpsc::UnboundedReceiver<StreamEvent>) -> impl Stream<Item = std::result::Result<Event, std::convert::Infallible>> {
+        futures::stream::unfold((rx, false), |(mut rx, done)| async move {
+            if done {
+                return None;
+            }
+            let event = rx.recv().await?;
+            let (sse, done) = match event {
+                StreamEvent::Progress(value) => (Event::default().event("progress").data(value.to_string()), false),
+                StreamEvent::Result(value) => (Event::default().event("result").data(value.to_string()), true),
+                StreamEvent::Error(e) => (
+                    Event::default().event("error").data(serde_json::json!({ "error": e }).to_string()),
+                    true,
+                ),
+            };
+            Some((Ok(sse), (rx, done)))
+        })
+    }
+    
+    pub struct ServerConfig {
+        addr: SocketAddr,
+        auth_token: Option<String>,
+        auth_disabled: bool,
+    }
+    
+    impl Default for ServerConfig {
+        /// Listening on 127.0.0.1:8000, requiring a token that is yet to be set
+        fn default() -> Self {
+            ServerConfig {
+                addr: "127.0.0.1:8000".parse().unwrap(),
+                auth_token: None,
+                auth_disabled: false,
+            }
+        }
+    }
+    
+    impl ServerConfig {
+        pub fn new() -> Self {
+            Self::default()
+        }
+        
+        pub fn with_auth_token(mut self, token: &str) -> Self {
+            self.auth_token = Some(token.to_string());
+            self
+        }
+        
+        pub fn with_addr(mut self, addr: SocketAddr) -> Self {
+            self.addr = addr;
+            self
+        }
+        
+        pub fn with_auth_disabled(mut self) -> Self {
+            self.auth_disabled = true;
+            self
+        }
+    }
+    
+    pub mod context_action {
+        use super::*;
+        
+        pub type ContextAction = super::ContextAction;
+        
+        #[derive(Clone, Debug, Serialize, Deserialize)]
+        pub struct Parameter {
+            pub name: String,
+            pub description: String,
+            pub parameter_type: ParameterType,
+            pub required: bool,
+        }
+        
+        impl Parameter {
+            pub fn new(name: &str, description: &str, parameter_type: ParameterType, required: bool) -> Self {
+                Parameter {
+                    name: name.to_string(),
+                    description: description.to_string(),
+                    parameter_type,
+                    required,
+                }
+            }
+        }
+        
+        #[derive(Clone, Debug, Serialize, Deserialize)]
+        pub enum ParameterType {
+            String,
+            Integer,
+            Float,
+            Boolean,
+            Object,
+            Array,
+        }
+        
+        impl ParameterType {
+            /// Check whether a JSON value is of this type
+            pub fn matches(&self, value: &Value) -> bool {
+                match self {
+                    ParameterType::String => value.is_string(),
+                    ParameterType::Integer => value.is_i64() || value.is_u64(),
+                    ParameterType::Float => value.is_number(),
+                    ParameterType::Boolean => value.is_boolean(),
+                    ParameterType::Object => value.is_object(),
+                    ParameterType::Array => value.is_array(),
+                }
+            }
+        }
+        
+        /// A parameter that failed validation and why
+        #[derive(Clone, Debug, Serialize, Deserialize)]
+        pub struct ParameterError {
+            pub name: String,
+            pub problem: String,
+        }
+    }
+}
+
+// Client-side types
+pub mod client {
+    use super::*;
+    
+    pub struct Client {
+        http: reqwest::Client,
+        endpoint: String,
+        timeout: std::time::Duration,
+    }
+    
+    /// Error returned when the server does not answer within the configured timeout
+    #[derive(Debug)]
+    pub struct TimeoutError {
+        pub timeout: std::time::Duration,
+    }
+    
+    impl std::fmt::Display for TimeoutError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "MCP request timed out after {:?}", self.timeout)
+        }
+    }
+    
+    impl std::error::Error for TimeoutError {}
+    
+    impl Client {
+        pub async fn new(config: ClientConfig) -> Result<Self> {
+            let mut headers = reqwest::header::HeaderMap::new();
+            if let Some(token) = config.auth_token.as_ref().filter(|_| !config.auth_disabled) {
+                let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
+                    .context("MCP auth token contains invalid characters")?;
+                value.set_sensitive(true);
+                headers.insert(reqwest::header::AUTHORIZATION, value);
+            }
+            
+            let http = reqwest::Client::builder()
+                .timeout(config.timeout)
+                .default_headers(headers)
+                .build()
+                .context("Failed to create MCP HTTP client")?;
+            
+            Ok(Client {
+                http,
+                endpoint: config.endpoint.trim_end_matches('/').to_string(),
+                timeout: config.timeout,
+            })
+        }
+        
+        /// Attach context to a request error, surfacing timeouts as `TimeoutError`
+        fn request_error(&self, err: reqwest::Error, context: String) -> anyhow::Error {
+            if err.is_timeout() {
+                anyhow::Error::new(TimeoutError { timeout: self.timeout }).context(context)
+            } else {
+                anyhow::Error::new(err).context(context)
+            }
+        }
+        
+        pub async fn get_available_actions(&self) -> Result<Vec<ActionInfo>> {
+            let url = format!("{}/actions", self.endpoint);
+            let response = self.http.get(&url).send().await
+                .map_err(|e| self.request_error(e, format!("Failed to reach MCP server at {}", url)))?;
+            
+            let response = check_status(response).await?;
+            let actions = response.json::<Vec<ActionInfo>>().await
+                .map_err(|e| self.request_error(e, "Invalid action list returned by MCP server".to_string()))?;
+            
+            Ok(actions)
+        }
+        
+        pub async fn invoke_action(&self, action_name: &str, params: Value) -> Result<Value> {
+            let url = format!("{}/actions/{}", self.endpoint, action_name);
+            let response = self.http.post(&url).json(&params).send().await
+                .map_err(|e| self.request_error(e, format!("Failed to invoke action {} at {}", action_name, url)))?;
+            
+            let response = check_status(response).await?;
+            let value = response.json::<Value>().await
+                .map_err(|e| self.request_error(e, format!("Invalid response returned for action {}", action_name)))?;
+            
+            Ok(value)
+        }
+        
+        /// Invoke an action through the streaming endpoint, passing each progress event to `on_progress`
+        pub async fn invoke_action_streaming<F>(&self, action_name: &str, params: Value, mut on_progress: F) -> Result<Value>
+        where
+            F: FnMut(Value),
+        {
+            use futures::StreamExt;
+            
+            let url = format!("{}/actions/{}/stream", self.endpoint, action_name);
+            let response = self.http.post(&url).json(&params).send().await
+                .map_err(|e| self.request_error(e, format!("Failed to invoke action {} at {}", action_name, url)))?;
+            
+            let response = check_status(response).await?;
+            let mut body = response.bytes_stream();
+            let mut buffer = String::new();
+            let mut event_name = String::new();
+            let mut data = String::new();
+            
+            while let Some(chunk) = body.next().await {
+                let chunk = chunk
+                    .map_err(|e| self.request_error(e, format!("Stream for action {} was interrupted", action_name)))?;
+                buffer.push_str(&String::from_utf8_lossy(&chunk));
+                
+                // Process every complete line; an empty line dispatches the event
+                while let Some(pos) = buffer.find('\n') {
+                    let line: String = buffer.drain(..=pos).collect();
+                    let line = line.trim_end_matches(['\r', '\n']);
+                    
+                    if let Some(value) = line.strip_prefix("event:") {
+                        event_name = value.trim().to_string();
+                    } else if let Some(value) = line.strip_prefix("data:") {
+                        if !data.is_empty() {
+                            data.push('\n');
+                        }
+                        data.push_str(value.trim_start());
+                    } else if line.is_empty() && !data.is_empty() {
+                        let value: Value = serde_json::from_str(&data)
+                            .with_context(|| format!("Invalid event returned for action {}", action_name))?;
+                        data.clear();
+                        
+                        match event_name.as_str() {
+                            "progress" => on_progress(value),
+                            "result" => return Ok(value),
+                            "error" => {
+                                let message = value["error"].as_str().unwrap_or("unknown error");
+                                return Err(anyhow::anyhow!("Action {} failed: {}", action_name, message));
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            
+            Err(anyhow::anyhow!("Stream for action {} ended without a result", action_name))
+        }
+    }
+    
+    /// Turn a non-2xx response into an error carrying the server's error message
+    async fn check_status(response: reqwest::Response) -> Result<reqwest::Response> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<Value>(&body)
+            .ok()
+            .and_then(|v| v["error"].as_str().map(|s| s.to_string()))
+            .unwrap_or(body);
+        
+        Err(anyhow::anyhow!("MCP server returned {}: {}", status, message))
+    }
+    
+    pub struct ClientConfig {
+        endpoint: String,
+        timeout: std::time::Duration,
+        auth_token: Option<String>,
+        auth_disabled: bool,
+    }
+    
+    impl Default for ClientConfig {
+        /// Talking to http://localhost:8000 with a 30 second timeout and no token
+        fn default() -> Self {
+            ClientConfig {
+                endpoint: "http://localhost:8000".to_string(),
+                timeout: std::time::Duration::from_secs(30),
+                auth_token: None,
+                auth_disabled: false,
+            }
+        }
+    }
+    
+    impl ClientConfig {
+        pub fn new() -> Self {
+            Self::default()
+        }
+        
+        pub fn with_auth_token(mut self, token: &str) -> Self {
+            self.auth_token = Some(token.to_string());
+            self
+        }
+        
+        pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+            self.endpoint = endpoint.to_string();
+            self
+        }
+        
+        pub fn with_timeout(mut self, timeout: std::time::Duration) -> Self {
+            self.timeout = timeout;
+            self
+        }
+        
+        pub fn with_auth_disabled(mut self) -> Self {
+            self.auth_disabled = true;
+            self
+        }
+    }
+    
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct ActionInfo {
+        pub name: String,
+        pub description: String,
+        #[serde(default)]
+        pub parameters: Vec<super::server::context_action::Parameter>,
+    }
+}
+
+// Shared types
+
+/// Event sent from a running action to a streaming caller
+pub enum StreamEvent {
+    Progress(Value),
+    Result(Value),
+    Error(String),
+}
+
+/// Sink for progress reported by a running action
+///
+/// Progress is dropped when the caller did not ask for a streaming response.
+#[derive(Clone)]
+pub struct Progress {
+    tx: Option<mpsc::UnboundedSender<StreamEvent>>,
+}
+
+impl Progress {
+    pub fn new(tx: mpsc::UnboundedSender<StreamEvent>) -> Self {
+        Progress { tx: Some(tx) }
+    }
+    
+    pub fn disabled() -> Self {
+        Progress { tx: None }
+    }
+    
+    /// Report a progress event to the caller, if it is listening
+    pub fn report(&self, event: Value) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(StreamEvent::Progress(event));
+        }
+    }
+}
+
+/// Async handler invoked with the action's parameters and a progress sink
+pub type ActionHandler = Arc<dyn Fn(Value, Progress) -> BoxFuture<'static, Result<Value>> + Send + Sync>;
+
+#[derive(Clone)]
+pub struct ContextAction {
+    pub name: String,
+    pub description: String,
+    pub parameters: Vec<server::context_action::Parameter>,
+    pub handler: ActionHandler,
+    /// Never run at the same time as the server's other exclusive actions
+    pub exclusive: bool,
+    /// The server's turn for exclusive actions, set once registered
+    turn: Option<Arc<tokio::sync::Mutex<()>>>,
+}
+
+impl ContextAction {
+    pub fn new(
+        name: &str,
+        description: &str,
+        parameters: Vec<server::context_action::Parameter>,
+        handler: ActionHandler,
+    ) -> Self {
+        ContextAction {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters,
+            handler,
+            exclusive: false,
+            turn: None,
+        }
+    }
+    
+    /// Run this action only while no other exclusive action is running, e.g.
+    /// because they all drive the same browser
+    pub fn exclusive(mut self) -> Self {
+        self.exclusive = true;
+        self
+    }
+    
+    /// Validate incoming parameters against the declared schema
+    ///
+    /// Missing required parameters and values of the wrong type are reported;
+    /// `null` is treated the same as an absent parameter.
+    pub fn validate(&self, params: &Value) -> std::result::Result<(), Vec<server::context_action::ParameterError>> {
+        use server::context_action::ParameterError;
+        
+        let Some(object) = params.as_object() else {
+            return Err(vec![ParameterError {
+                name: "".to_string(),
+                problem: "parameters must be a JSON object".to_string(),
+            }]);
+        };
+        
+        let mut errors = Vec::new();
+        for parameter in &self.parameters {
+            match object.get(&parameter.name) {
+                None | Some(Value::Null) => {
+                    if parameter.required {
+                        errors.push(ParameterError {
+                            name: parameter.name.clone(),
+                            problem: "missing required parameter".to_string(),
+                        });
+                    }
+                }
+                Some(value) => {
+                    if !parameter.parameter_type.matches(value) {
+                        errors.push(ParameterError {
+                            name: parameter.name.clone(),
+                            problem: format!("expected {:?}", parameter.parameter_type).to_lowercase(),
+                        });
+                    }
+                }
+            }
+        }
+        
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
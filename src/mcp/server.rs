@@ -9,23 +9,52 @@ use super::actions;
 pub struct MCPServer {
     server: Option<Server>,
     shutdown_tx: Option<oneshot::Sender<()>>,
+    auth_token: Option<String>,
+    local_addr: Option<SocketAddr>,
 }
 
-impl MCPServer {
-    /// Create a new MCP server
-    pub fn new() -> Self {
+impl Default for MCPServer {
+    /// A stopped server without an auth token
+    fn default() -> Self {
         MCPServer {
             server: None,
             shutdown_tx: None,
+            auth_token: None,
+            local_addr: None,
         }
     }
+}
+
+impl MCPServer {
+    /// Create a new MCP server
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Require clients to send `Authorization: Bearer <token>` on every request
+    pub fn with_auth_token(mut self, token: &str) -> Self {
+        self.auth_token = Some(token.to_string());
+        self
+    }
 
-    /// Start the MCP server on the specified address
-    pub async fn start(&mut self, addr: SocketAddr) -> Result<()> {
+    /// Start the MCP server on the specified address and return the bound address
+    ///
+    /// Port 0 binds an OS-chosen port; the returned address reports which one.
+    /// Without an auth token the server only accepts a loopback address, since it
+    /// can drive a real browser on behalf of anyone who can reach it.
+    pub async fn start(&mut self, addr: SocketAddr) -> Result<SocketAddr> {
         // Create server config
-        let config = ServerConfig::new()
-            .with_addr(addr)
-            .with_auth_disabled(); // For simplicity; in production, use proper auth
+        let config = ServerConfig::new().with_addr(addr);
+        let config = match &self.auth_token {
+            Some(token) => config.with_auth_token(token),
+            None if addr.ip().is_loopback() => config.with_auth_disabled(),
+            None => {
+                return Err(anyhow::anyhow!(
+                    "Refusing to start MCP server on non-loopback address {} without an auth token (use --mcp-token)",
+                    addr
+                ));
+            }
+        };
         
         // Create server
         let mut server = Server::new(config);
@@ -33,6 +62,11 @@ impl MCPServer {
         // Register context actions
         actions::register_actions(&mut server)?;
         
+        // Bind now so address errors are reported to the caller
+        let listener = server.bind().await?;
+        let local_addr = listener.local_addr()?;
+        self.local_addr = Some(local_addr);
+        
         // Create shutdown channel
         let (tx, rx) = oneshot::channel();
         self.shutdown_tx = Some(tx);
@@ -44,7 +78,10 @@ impl MCPServer {
         let server_handle = server.clone();
         tokio::spawn(async move {
             tokio::select! {
-                _ = server_handle.serve() => {
+                result = server_handle.serve_on(listener) => {
+                    if let Err(e) = result {
+                        eprintln!("MCP server error: {:#}", e);
+                    }
                     println!("MCP server stopped");
                 }
                 _ = rx => {
@@ -54,7 +91,12 @@ impl MCPServer {
             }
         });
         
-        Ok(())
+        Ok(local_addr)
+    }
+
+    /// Address the server is listening on, once started
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
     }
 
     /// Stop the MCP server
@@ -68,6 +110,7 @@ impl MCPServer {
         }
         
         self.server = None;
+        self.local_addr = None;
         
         Ok(())
     }
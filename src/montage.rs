@@ -0,0 +1,105 @@
+// Contact sheets assembled from batch captures
+//
+// Every tile is sized to the largest capture; smaller captures are drawn in
+// the tile's top-left corner on the sheet background.
+
+use anyhow::{Context, Result};
+use image::{imageops, ImageFormat, Rgba, RgbaImage};
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::capture;
+use crate::label;
+
+/// Space around and between tiles, in pixels
+const PADDING: u32 = 16;
+/// Font scale of the captions under tiles
+const CAPTION_SCALE: u32 = 2;
+const BACKGROUND: Rgba<u8> = Rgba([238, 238, 238, 255]);
+const CAPTION_COLOR: Rgba<u8> = Rgba([40, 40, 40, 255]);
+
+/// One capture on a contact sheet
+pub struct Tile {
+    pub image: RgbaImage,
+    /// Text drawn under the tile, e.g. the page URL
+    pub caption: Option<String>,
+}
+
+/// Lay `tiles` out in a grid `columns` wide, in order
+pub fn build_montage(tiles: &[Tile], columns: u32) -> RgbaImage {
+    let columns = columns.clamp(1, (tiles.len() as u32).max(1));
+    let rows = (tiles.len() as u32).div_ceil(columns);
+
+    let tile_width = tiles.iter().map(|tile| tile.image.width()).max().unwrap_or(0);
+    let tile_height = tiles.iter().map(|tile| tile.image.height()).max().unwrap_or(0);
+    let caption_height = if tiles.iter().any(|tile| tile.caption.is_some()) {
+        label::text_size("", CAPTION_SCALE).1 + PADDING / 2
+    } else {
+        0
+    };
+
+    let cell_width = tile_width + PADDING;
+    let cell_height = tile_height + caption_height + PADDING;
+    let mut sheet = RgbaImage::from_pixel(
+        columns * cell_width + PADDING,
+        rows * cell_height + PADDING,
+        BACKGROUND,
+    );
+
+    for (i, tile) in tiles.iter().enumerate() {
+        let left = PADDING + (i as u32 % columns) * cell_width;
+        let top = PADDING + (i as u32 / columns) * cell_height;
+        imageops::overlay(&mut sheet, &tile.image, left as i64, top as i64);
+
+        if let Some(caption) = &tile.caption {
+            let caption = fit_caption(caption, tile_width);
+            label::draw_text(&mut sheet, &caption, left, top + tile_height + PADDING / 2, CAPTION_SCALE, CAPTION_COLOR);
+        }
+    }
+
+    sheet
+}
+
+/// Lay `frames` out side by side, with no padding, as a sprite sheet
+///
+/// Every frame must be the same size, so that frame `i` starts `i` frame
+/// widths from the left.
+pub fn build_sprite_sheet(frames: &[RgbaImage]) -> Result<RgbaImage> {
+    let Some(first) = frames.first() else {
+        return Err(anyhow::anyhow!("A sprite sheet needs at least one frame"));
+    };
+    let (width, height) = first.dimensions();
+    if let Some((index, frame)) = frames.iter().enumerate().find(|(_, frame)| frame.dimensions() != (width, height)) {
+        return Err(anyhow::anyhow!(
+            "Frame {} is {}x{}, but the first frame is {}x{}; every state must be the same size",
+            index, frame.width(), frame.height(), width, height
+        ));
+    }
+    
+    let mut sheet = RgbaImage::new(width * frames.len() as u32, height);
+    for (i, frame) in frames.iter().enumerate() {
+        imageops::replace(&mut sheet, frame, (i as u32 * width) as i64, 0);
+    }
+    Ok(sheet)
+}
+
+/// Write a contact sheet to `path` as a PNG
+pub fn save_montage(sheet: &RgbaImage, path: &Path, no_clobber: bool) -> Result<()> {
+    let file = capture::create_output_file(path, no_clobber)?;
+    sheet.write_to(&mut BufWriter::new(file), ImageFormat::Png)
+        .with_context(|| format!("Failed to write montage to {}", path.display()))?;
+    Ok(())
+}
+
+/// Shorten `caption` with a trailing `...` so it fits in `width` pixels
+fn fit_caption(caption: &str, width: u32) -> String {
+    if label::text_size(caption, CAPTION_SCALE).0 <= width {
+        return caption.to_string();
+    }
+
+    let mut fitted: String = caption.to_string();
+    while !fitted.is_empty() && label::text_size(&format!("{}...", fitted), CAPTION_SCALE).0 > width {
+        fitted.pop();
+    }
+    format!("{}...", fitted)
+}
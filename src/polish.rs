@@ -0,0 +1,92 @@
+// Presentation framing for screenshots
+//
+// The capture gets rounded corners and is laid on a padded background with a
+// soft drop shadow under it, as marketing screenshot tools do.
+
+use image::{imageops, GrayImage, Luma, Rgba, RgbaImage};
+
+/// Opacity of the drop shadow where it is darkest
+const SHADOW_OPACITY: f32 = 0.35;
+
+/// How a screenshot is framed for presentation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Polish {
+    /// Space around the screenshot, in pixels
+    pub padding: u32,
+    /// Radius of the rounded corners, in pixels
+    pub radius: u32,
+    /// How far the drop shadow spreads, in pixels; 0 for none
+    pub shadow: u32,
+    pub background: Rgba<u8>,
+}
+
+impl Default for Polish {
+    /// 64px of light grey padding, 12px corners and a 24px shadow
+    fn default() -> Self {
+        Polish {
+            padding: 64,
+            radius: 12,
+            shadow: 24,
+            background: Rgba([229, 231, 235, 255]),
+        }
+    }
+}
+
+impl Polish {
+    /// Frame `image`, which ends up `padding` pixels in from every edge
+    pub fn apply(&self, image: &RgbaImage) -> RgbaImage {
+        let (width, height) = image.dimensions();
+        let radius = self.radius.min(width / 2).min(height / 2);
+        let mut canvas = RgbaImage::from_pixel(width + 2 * self.padding, height + 2 * self.padding, self.background);
+
+        // Shape of the rounded screenshot, antialiased at the corners
+        let mut rounded = image.clone();
+        let mut shape = GrayImage::new(canvas.width(), canvas.height());
+        for (x, y, pixel) in rounded.enumerate_pixels_mut() {
+            let coverage = corner_coverage(x, y, width, height, radius);
+            pixel.0[3] = (pixel.0[3] as f32 * coverage).round() as u8;
+            // The shadow falls a little below the screenshot
+            let shadow_y = y + self.padding + self.shadow / 3;
+            if shadow_y < shape.height() {
+                shape.put_pixel(x + self.padding, shadow_y, Luma([(255.0 * coverage) as u8]));
+            }
+        }
+
+        if self.shadow > 0 {
+            let shadow = imageops::fast_blur(&shape, self.shadow as f32 / 2.0);
+            for (pixel, Luma([alpha])) in canvas.pixels_mut().zip(shadow.pixels()) {
+                let darken = 1.0 - *alpha as f32 / 255.0 * SHADOW_OPACITY;
+                for channel in &mut pixel.0[..3] {
+                    *channel = (*channel as f32 * darken).round() as u8;
+                }
+                // A transparent background still shows the shadow
+                pixel.0[3] = pixel.0[3].max((*alpha as f32 * SHADOW_OPACITY) as u8);
+            }
+        }
+
+        imageops::overlay(&mut canvas, &rounded, self.padding as i64, self.padding as i64);
+        canvas
+    }
+}
+
+/// How much of the pixel at (`x`, `y`) lies inside a `width`x`height`
+/// rectangle with corners of `radius`, from 0 to 1
+fn corner_coverage(x: u32, y: u32, width: u32, height: u32, radius: u32) -> f32 {
+    let r = radius as f32;
+    let center_x = if x < radius {
+        r
+    } else if x >= width - radius {
+        (width - radius) as f32
+    } else {
+        return 1.0;
+    };
+    let center_y = if y < radius {
+        r
+    } else if y >= height - radius {
+        (height - radius) as f32
+    } else {
+        return 1.0;
+    };
+    let distance = ((x as f32 + 0.5 - center_x).powi(2) + (y as f32 + 0.5 - center_y).powi(2)).sqrt();
+    (r - distance + 0.5).clamp(0.0, 1.0)
+}
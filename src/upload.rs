@@ -0,0 +1,212 @@
+// Uploading captures to S3-compatible storage or any URL that accepts a PUT
+//
+// Automated pipelines usually move captures somewhere else straight away, so
+// `--output` may name the destination directly instead of a local file.
+
+use anyhow::{Context, Result};
+use std::fmt;
+use url::Url;
+
+/// Where a capture is uploaded to instead of being kept as a local file
+#[derive(Debug, Clone, PartialEq)]
+pub enum UploadTarget {
+    /// An object in an S3 bucket, e.g. `s3://captures/home.png`
+    S3 { bucket: String, key: String },
+    /// An http or https URL the capture is sent to with a PUT request
+    Put(Url),
+}
+
+impl UploadTarget {
+    /// The upload target `output` names, or `None` if it is a local path
+    pub fn from_output(output: &str) -> Result<Option<Self>> {
+        if let Some(location) = output.strip_prefix("s3://") {
+            let (bucket, key) = location.split_once('/')
+                .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+                .ok_or_else(|| anyhow::anyhow!("Invalid S3 output {}. Expected s3://BUCKET/KEY", output))?;
+            return Ok(Some(UploadTarget::S3 { bucket: bucket.to_string(), key: key.to_string() }));
+        }
+        if output.starts_with("http://") || output.starts_with("https://") {
+            let url = Url::parse(output).with_context(|| format!("Invalid output URL {}", output))?;
+            return Ok(Some(UploadTarget::Put(url)));
+        }
+        Ok(None)
+    }
+
+    /// Extension of the object or URL path, e.g. `jpg` for `s3://captures/home.jpg`
+    pub fn extension(&self) -> Option<&str> {
+        let path = match self {
+            UploadTarget::S3 { key, .. } => key.as_str(),
+            UploadTarget::Put(url) => url.path(),
+        };
+        let name = path.rsplit('/').next().unwrap_or_default();
+        name.rsplit_once('.').map(|(_, extension)| extension).filter(|extension| !extension.is_empty())
+    }
+
+    /// Upload `data`, sent with the given content type
+    pub async fn upload(&self, data: Vec<u8>, content_type: &str) -> Result<()> {
+        let client = reqwest::Client::new();
+        let request = match self {
+            UploadTarget::S3 { bucket, key } => {
+                let credentials = S3Credentials::from_env()?;
+                let url = credentials.object_url(bucket, key)?;
+                let payload_hash = hex(ring::digest::digest(&ring::digest::SHA256, &data).as_ref());
+                let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+                let mut headers = vec![
+                    ("content-type", content_type.to_string()),
+                    ("host", host_header(&url)),
+                    ("x-amz-content-sha256", payload_hash.clone()),
+                    ("x-amz-date", amz_date.clone()),
+                ];
+                if let Some(token) = &credentials.session_token {
+                    headers.push(("x-amz-security-token", token.clone()));
+                }
+                let authorization = authorization("PUT", &url, &headers, &payload_hash, &credentials, &amz_date);
+
+                let mut request = client.put(url.as_str()).header("authorization", authorization);
+                for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+                    request = request.header(name, value);
+                }
+                request
+            }
+            UploadTarget::Put(url) => client.put(url.as_str()).header("content-type", content_type),
+        };
+
+        let response = request.body(data).send().await
+            .with_context(|| format!("Failed to upload to {}", self))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let body = body.trim();
+            return Err(if body.is_empty() {
+                anyhow::anyhow!("Upload to {} failed with {}", self, status)
+            } else {
+                anyhow::anyhow!("Upload to {} failed with {}: {}", self, status, body.chars().take(500).collect::<String>())
+            });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for UploadTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadTarget::S3 { bucket, key } => write!(f, "s3://{}/{}", bucket, key),
+            UploadTarget::Put(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+/// Content type to upload a capture with, from its file extension
+pub fn content_type(extension: &str) -> &'static str {
+    match extension.to_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+/// S3 credentials and location, from the standard AWS environment variables
+///
+/// `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY` are required, with
+/// `AWS_SESSION_TOKEN` for temporary credentials. The region comes from
+/// `AWS_REGION` or `AWS_DEFAULT_REGION` (default: us-east-1), and
+/// `AWS_ENDPOINT_URL_S3` or `AWS_ENDPOINT_URL` points at an S3-compatible
+/// service such as MinIO instead of AWS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct S3Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+    pub region: String,
+    pub endpoint: Option<Url>,
+}
+
+impl S3Credentials {
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+    }
+
+    /// Read the variables through `lookup`, e.g. from a map in tests
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let required = |name: &str| lookup(name)
+            .ok_or_else(|| anyhow::anyhow!("{} must be set to upload to S3", name));
+        let endpoint = lookup("AWS_ENDPOINT_URL_S3").or_else(|| lookup("AWS_ENDPOINT_URL"))
+            .map(|endpoint| Url::parse(&endpoint).with_context(|| format!("Invalid S3 endpoint {}", endpoint)))
+            .transpose()?;
+        Ok(S3Credentials {
+            access_key_id: required("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: required("AWS_SECRET_ACCESS_KEY")?,
+            session_token: lookup("AWS_SESSION_TOKEN"),
+            region: lookup("AWS_REGION").or_else(|| lookup("AWS_DEFAULT_REGION")).unwrap_or_else(|| "us-east-1".to_string()),
+            endpoint,
+        })
+    }
+
+    /// URL of an object: virtual-hosted style on AWS, path style on a custom endpoint
+    pub fn object_url(&self, bucket: &str, key: &str) -> Result<Url> {
+        let key = key.split('/').map(uri_encode).collect::<Vec<_>>().join("/");
+        let url = match &self.endpoint {
+            Some(endpoint) => format!("{}/{}/{}", endpoint.as_str().trim_end_matches('/'), uri_encode(bucket), key),
+            None => format!("https://{}.s3.{}.amazonaws.com/{}", bucket, self.region, key),
+        };
+        Url::parse(&url).with_context(|| format!("Invalid S3 object URL {}", url))
+    }
+}
+
+/// `Authorization` header for an S3 request, signed with AWS Signature Version 4
+///
+/// `headers` are the lowercase names and values of every header to sign,
+/// including `host` and `x-amz-date`, and `amz_date` is the request time as
+/// in `x-amz-date`. The URL must not have a query string.
+pub fn authorization(method: &str, url: &Url, headers: &[(&str, String)], payload_hash: &str, credentials: &S3Credentials, amz_date: &str) -> String {
+    let mut headers = headers.to_vec();
+    headers.sort_by(|a, b| a.0.cmp(b.0));
+    let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_request = format!("{}\n{}\n\n{}\n{}\n{}", method, url.path(), canonical_headers, signed_headers, payload_hash);
+
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/s3/aws4_request", date, credentials.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date, scope, hex(ring::digest::digest(&ring::digest::SHA256, canonical_request.as_bytes()).as_ref())
+    );
+
+    let key = [date, credentials.region.as_str(), "s3", "aws4_request"].iter()
+        .fold(format!("AWS4{}", credentials.secret_access_key).into_bytes(), |key, part| hmac(&key, part.as_bytes()));
+    let signature = hex(&hmac(&key, string_to_sign.as_bytes()));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    ring::hmac::sign(&ring::hmac::Key::new(ring::hmac::HMAC_SHA256, key), data).as_ref().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Percent-encode everything but the characters S3 leaves unreserved
+fn uri_encode(segment: &str) -> String {
+    segment.bytes().map(|byte| match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+        _ => format!("%{:02X}", byte),
+    }).collect()
+}
+
+/// `Host` header for `url`, with the port when it isn't the scheme's default
+fn host_header(url: &Url) -> String {
+    let host = url.host_str().unwrap_or_default();
+    match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
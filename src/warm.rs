@@ -0,0 +1,178 @@
+// Warm browser sessions kept open by `weblook serve`
+//
+// `weblook serve` starts ChromeDriver and a browser session and records them in
+// a small state file. Captures that find a live session there attach to it and
+// reuse its tab instead of launching a new Chrome; otherwise they start one as
+// usual.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use thirtyfour::session::handle::SessionHandle;
+use thirtyfour::{SessionId, WebDriver};
+
+use crate::capture::{self, BrowserSession, ChromeDriverManager, ViewportSize};
+
+/// ChromeDriver port used by `weblook serve`, the same one captures use
+const SERVE_PORT: u16 = 9515;
+
+/// A browser session kept open between captures
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct WarmSession {
+    /// Port of the ChromeDriver serving the session
+    pub port: u16,
+    pub session_id: String,
+    /// Process id of the `weblook serve` keeping the session open
+    pub pid: u32,
+}
+
+impl WarmSession {
+    /// Location of the state file, in a directory only the current user can use
+    ///
+    /// That is `$XDG_RUNTIME_DIR` when it is set, or else a private directory
+    /// in the temp dir (see [`private_dir`]). A file in the shared temp dir
+    /// could be planted by another user to point captures at a session they
+    /// control.
+    pub fn state_path() -> Result<PathBuf> {
+        let dir = match std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => private_dir(&std::env::temp_dir())?,
+        };
+        Ok(dir.join("weblook-session.json"))
+    }
+
+    /// Read the state file, if there is one owned by the current user
+    pub fn load() -> Option<Self> {
+        let path = Self::state_path().ok()?;
+        if !owned_by_current_user(&path) {
+            return None;
+        }
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Write the state file
+    pub fn save(&self) -> Result<()> {
+        let path = Self::state_path()?;
+        std::fs::write(&path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Remove the state file
+    pub fn remove() {
+        if let Ok(path) = Self::state_path() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    /// Attach to the session, failing if it is no longer alive
+    pub async fn attach(&self) -> Result<WebDriver> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(120))
+            .build()?;
+        let handle = SessionHandle::new(
+            Arc::new(client),
+            format!("http://localhost:{}", self.port).as_str(),
+            SessionId::from(self.session_id.clone()),
+        )?;
+        let driver = WebDriver { handle: Arc::new(handle) };
+
+        // Any command fails once the session or ChromeDriver has gone away;
+        // also leave any iframe a previous capture switched into
+        if let Err(e) = driver.enter_default_frame().await {
+            // Don't let the handle try to quit a session that isn't ours to end
+            let _ = driver.leak();
+            return Err(anyhow::Error::new(e).context("The warm browser session is no longer running"));
+        }
+        Ok(driver)
+    }
+}
+
+/// A `weblook-<uid>` directory in `base` that only the current user can use,
+/// created with mode 0700 if it doesn't exist yet
+///
+/// A directory that is owned by someone else, open to other users or not a
+/// directory at all is refused. Elsewhere than Unix, `base` is returned as is,
+/// the temp dir being per user there.
+#[cfg(unix)]
+pub fn private_dir(base: &Path) -> Result<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    let uid = current_uid();
+    let dir = base.join(format!("weblook-{}", uid));
+    match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => {
+            return Err(anyhow::Error::new(e).context(format!("Failed to create {}", dir.display())));
+        }
+        _ => {}
+    }
+    let metadata = std::fs::symlink_metadata(&dir)
+        .with_context(|| format!("Failed to inspect {}", dir.display()))?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(anyhow::anyhow!("{} is not a directory private to the current user", dir.display()));
+    }
+    Ok(dir)
+}
+
+#[cfg(not(unix))]
+pub fn private_dir(base: &Path) -> Result<PathBuf> {
+    Ok(base.to_path_buf())
+}
+
+/// Whether `path` exists and belongs to the current user
+#[cfg(unix)]
+pub fn owned_by_current_user(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.uid() == current_uid())
+}
+
+#[cfg(not(unix))]
+pub fn owned_by_current_user(path: &Path) -> bool {
+    path.exists()
+}
+
+#[cfg(unix)]
+fn current_uid() -> u32 {
+    // SAFETY: geteuid has no preconditions and cannot fail
+    unsafe { libc::geteuid() }
+}
+
+/// A warm session being served; end it with [`WarmServer::stop`]
+pub struct WarmServer {
+    session: BrowserSession,
+    pub info: WarmSession,
+}
+
+impl WarmServer {
+    /// Quit the browser, stop ChromeDriver and remove the state file
+    pub async fn stop(self) -> Result<()> {
+        WarmSession::remove();
+        self.session.quit().await
+    }
+}
+
+/// Start ChromeDriver and a browser session and record them for later captures
+pub async fn serve(debug: bool) -> Result<WarmServer> {
+    if let Some(existing) = WarmSession::load()
+        && let Ok(driver) = existing.attach().await
+    {
+        let _ = driver.leak();
+        return Err(anyhow::anyhow!("weblook serve is already running (pid {})", existing.pid));
+    }
+
+    let mut chromedriver = ChromeDriverManager::new(SERVE_PORT, debug);
+    chromedriver.start()?;
+    let viewport = "1280x720".parse::<ViewportSize>()?;
+    let driver = capture::setup_webdriver(viewport, Default::default(), SERVE_PORT, Default::default(), None, &[], &serde_json::Map::new()).await?;
+
+    let info = WarmSession {
+        port: SERVE_PORT,
+        session_id: driver.session_id().to_string(),
+        pid: std::process::id(),
+    };
+    let session = BrowserSession::new(driver, chromedriver);
+    info.save()?;
+    Ok(WarmServer { session, info })
+}
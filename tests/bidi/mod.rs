@@ -0,0 +1,2 @@
+// WebDriver BiDi event tests, against a local WebSocket server instead of ChromeDriver
+mod test_events;
@@ -0,0 +1,93 @@
+use futures::{SinkExt, StreamExt};
+use serde_json::json;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+use weblook::bidi::{BidiEvents, PageEvents, EVENTS};
+
+fn event(method: &str, params: serde_json::Value) -> serde_json::Value {
+    json!({ "type": "event", "method": method, "params": params })
+}
+
+/// Test that console entries, responses and top-level loads are recorded
+#[test]
+fn test_apply_events() {
+    let mut page = PageEvents::default();
+    page.apply("top", &event("log.entryAdded", json!({ "type": "console", "level": "warn", "text": "Slow", "timestamp": 0 })));
+    page.apply("top", &event("log.entryAdded", json!({ "type": "javascript", "level": "error", "text": "Uncaught TypeError" })));
+    page.apply("top", &event("network.responseCompleted", json!({ "context": "frame" })));
+    page.apply("top", &event("network.responseCompleted", json!({ "context": "top" })));
+    assert_eq!(page.console, vec![
+        "[1970-01-01T00:00:00.000Z] [WARNING] Slow".to_string(),
+        "[unknown] [ERROR] Uncaught TypeError".to_string(),
+    ]);
+    assert_eq!(page.responses, 2);
+    
+    // Frames finish loading before the page does
+    page.apply("top", &event("browsingContext.load", json!({ "context": "frame" })));
+    assert!(!page.loaded);
+    page.apply("top", &event("browsingContext.domContentLoaded", json!({ "context": "top" })));
+    assert!(page.dom_content_loaded && !page.loaded);
+    page.apply("top", &event("browsingContext.load", json!({ "context": "top" })));
+    assert!(page.loaded);
+    
+    // Command replies are not events
+    let before = page.clone();
+    page.apply("top", &json!({ "type": "success", "id": 2, "method": "log.entryAdded", "result": {} }));
+    assert_eq!(page, before);
+}
+
+/// Accept one connection, answer its subscription with `reply` and send `events`
+async fn serve(reply: serde_json::Value, events: Vec<serde_json::Value>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let Some(Ok(Message::Text(subscribe))) = socket.next().await else {
+            panic!("expected a subscription");
+        };
+        let subscribe: serde_json::Value = serde_json::from_str(subscribe.as_str()).unwrap();
+        assert_eq!(subscribe["method"], "session.subscribe");
+        assert_eq!(subscribe["params"]["events"], json!(EVENTS));
+        
+        let mut reply = reply;
+        reply["id"] = subscribe["id"].clone();
+        socket.send(Message::Text(reply.to_string().into())).await.unwrap();
+        for event in events {
+            socket.send(Message::Text(event.to_string().into())).await.unwrap();
+        }
+        // Keep the connection open until the client goes away
+        while let Some(Ok(_)) = socket.next().await {}
+    });
+    format!("ws://{}/session/test", addr)
+}
+
+/// Test that events sent after subscribing are followed until cleared
+#[tokio::test]
+async fn test_connect_and_wait() {
+    let url = serve(json!({ "type": "success", "result": {} }), vec![
+        event("network.responseCompleted", json!({ "context": "top" })),
+        event("log.entryAdded", json!({ "level": "info", "text": "Ready", "timestamp": 0 })),
+        event("browsingContext.load", json!({ "context": "top" })),
+    ]).await;
+    let events = BidiEvents::connect(&url, "top".to_string()).await.unwrap();
+    
+    assert!(events.wait_for(Duration::from_secs(5), |page| page.loaded).await);
+    let page = events.snapshot();
+    assert_eq!(page.responses, 1);
+    assert_eq!(page.console, vec!["[1970-01-01T00:00:00.000Z] [INFO] Ready".to_string()]);
+    
+    events.clear();
+    assert_eq!(events.snapshot(), PageEvents::default());
+    assert!(!events.wait_for(Duration::from_millis(100), |page| page.responses > 0).await);
+}
+
+/// Test that a refused subscription fails the connection with ChromeDriver's message
+#[tokio::test]
+async fn test_subscription_error() {
+    let url = serve(json!({ "type": "error", "error": "unknown command", "message": "BiDi is not supported" }), vec![]).await;
+    let err = BidiEvents::connect(&url, "top".to_string()).await.err().unwrap();
+    assert_eq!(format!("{:#}", err), "Could not subscribe to WebDriver BiDi events: unknown command: BiDi is not supported");
+}
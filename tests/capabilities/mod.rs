@@ -0,0 +1,2 @@
+// Tests of the capabilities reported by `weblook capabilities`
+mod test_capabilities;
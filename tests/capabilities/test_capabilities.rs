@@ -0,0 +1,36 @@
+use weblook::capabilities::capabilities;
+use weblook::capture::StdoutFormat;
+use weblook::combine::CombineFormat;
+
+/// Test that the version and features come from how the crate was built
+#[test]
+fn test_build_details() {
+    let capabilities = capabilities();
+    assert_eq!(capabilities.name, "weblook");
+    assert_eq!(capabilities.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(capabilities.features.get("mcp_experimental"), Some(&cfg!(feature = "mcp_experimental")));
+    assert_eq!(capabilities.backends, vec!["chrome"]);
+}
+
+/// Test that every listed format is one the matching option accepts
+#[test]
+fn test_formats_parse() {
+    let formats = capabilities().formats;
+    assert_eq!(formats.screenshot, vec!["png", "jpeg", "webp"]);
+    assert_eq!(formats.recording, vec!["gif", "webp"]);
+    for format in formats.stdout {
+        assert!(format.parse::<StdoutFormat>().is_ok(), "{} should parse", format);
+    }
+    for format in formats.combine {
+        assert!(format.parse::<CombineFormat>().is_ok(), "{} should parse", format);
+    }
+}
+
+/// Test that the JSON keeps the field names scripts rely on
+#[test]
+fn test_json_shape() {
+    let json = serde_json::to_value(capabilities()).unwrap();
+    assert!(json["version"].is_string());
+    assert!(json["features"]["mcp_experimental"].is_boolean());
+    assert_eq!(json["formats"]["combine"], serde_json::json!(["pdf", "tiff"]));
+}
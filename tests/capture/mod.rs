@@ -0,0 +1,21 @@
+// Capture tests that don't need a running browser
+mod test_chromedriver;
+mod test_flow;
+mod test_label;
+mod test_manifest;
+mod test_montage;
+mod test_options;
+mod test_recording;
+mod test_redirects;
+
+use std::path::PathBuf;
+
+use weblook::capture::CaptureOptions;
+
+/// Capture options as the command line would build them by default
+pub fn base_options() -> CaptureOptions {
+    CaptureOptions {
+        output_path: PathBuf::from("shots/weblook.png"),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,36 @@
+use weblook::capture::{parse_driver_status, ChromeDriverManager};
+
+/// Test that only a successful status reporting `ready` counts as ready
+#[test]
+fn test_parse_driver_status() {
+    let ready = b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"value\":{\"ready\":true,\"message\":\"ChromeDriver ready for new sessions.\"}}";
+    assert!(parse_driver_status(ready).unwrap());
+    
+    let busy = b"HTTP/1.1 200 OK\r\n\r\n{\"value\":{\"ready\":false}}";
+    assert!(!parse_driver_status(busy).unwrap());
+    
+    let missing = b"HTTP/1.1 404 Not Found\r\n\r\n<html>Not Found</html>";
+    assert!(!parse_driver_status(missing).unwrap());
+    
+    assert!(parse_driver_status(b"HTTP/1.1 200 OK\r\n\r\nnot json").is_err());
+    assert!(parse_driver_status(b"").is_err());
+}
+
+/// Test that readiness is read from the server's `/status`, not just an open port
+#[test]
+fn test_is_running_asks_status() {
+    let mut server = mockito::Server::new();
+    let port = server.socket_address().port();
+    let manager = ChromeDriverManager::new(port, false);
+    
+    let busy = server.mock("GET", "/status")
+        .with_body(r#"{"value":{"ready":false,"message":"starting"}}"#)
+        .create();
+    assert!(!manager.is_running());
+    busy.remove();
+    
+    server.mock("GET", "/status")
+        .with_body(r#"{"value":{"ready":true,"message":"ready"}}"#)
+        .create();
+    assert!(manager.is_running());
+}
@@ -0,0 +1,40 @@
+use anyhow::Result;
+use std::io::Write;
+
+use weblook::capture::{load_flow, FlowStep};
+
+/// Test that a flow file deserializes into the expected steps
+#[test]
+fn test_load_flow() -> Result<()> {
+    let mut file = tempfile::NamedTempFile::new()?;
+    write!(file, r##"[
+        {{ "action": "goto", "url": "https://example.com/login" }},
+        {{ "action": "fill", "selector": "#user", "value": "me" }},
+        {{ "action": "click", "selector": "button[type=submit]" }},
+        {{ "action": "wait", "seconds": 1.5 }},
+        {{ "action": "wait_for", "selector": "#dashboard" }}
+    ]"##)?;
+    
+    let steps = load_flow(file.path().to_str().unwrap())?;
+    
+    assert_eq!(steps, vec![
+        FlowStep::Goto { url: "https://example.com/login".to_string() },
+        FlowStep::Fill { selector: "#user".to_string(), value: "me".to_string() },
+        FlowStep::Click { selector: "button[type=submit]".to_string() },
+        FlowStep::Wait { seconds: 1.5 },
+        FlowStep::WaitFor { selector: "#dashboard".to_string(), timeout: 10 },
+    ]);
+    
+    Ok(())
+}
+
+/// Test that unknown actions are rejected
+#[test]
+fn test_load_flow_unknown_action() -> Result<()> {
+    let mut file = tempfile::NamedTempFile::new()?;
+    write!(file, r#"[{{ "action": "teleport" }}]"#)?;
+    
+    assert!(load_flow(file.path().to_str().unwrap()).is_err());
+    
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use image::{Rgba, RgbaImage};
+
+use weblook::label::{parse_color, Label, LabelPosition};
+
+/// Test that label colours parse in short, long and alpha forms
+#[test]
+fn test_parse_color() {
+    assert_eq!(parse_color("#fff").unwrap(), Rgba([255, 255, 255, 255]));
+    assert_eq!(parse_color("#ff8000").unwrap(), Rgba([255, 128, 0, 255]));
+    assert_eq!(parse_color("#00000080").unwrap(), Rgba([0, 0, 0, 128]));
+    
+    assert!(parse_color("#12").is_err());
+    assert!(parse_color("#gggggg").is_err());
+}
+
+/// Test that a label is drawn in the requested corner only
+#[test]
+fn test_label_draws_in_corner() {
+    let mut image = RgbaImage::from_pixel(640, 480, Rgba([255, 255, 255, 255]));
+    let label = Label {
+        text: "STAGING".to_string(),
+        position: "top-left".parse::<LabelPosition>().unwrap(),
+        background: Rgba([255, 0, 0, 255]),
+    };
+    label.draw(&mut image);
+    
+    // The background box starts just inside the top-left corner
+    assert_eq!(*image.get_pixel(10, 10), Rgba([255, 0, 0, 255]));
+    assert_eq!(*image.get_pixel(0, 0), Rgba([255, 255, 255, 255]));
+    assert_eq!(*image.get_pixel(630, 470), Rgba([255, 255, 255, 255]));
+    
+    // White text is drawn inside the red box
+    let text_pixels = (14..28).flat_map(|y| (14..60).map(move |x| (x, y)))
+        .filter(|&(x, y)| *image.get_pixel(x, y) == Rgba([255, 255, 255, 255]))
+        .count();
+    assert!(text_pixels > 0);
+}
@@ -0,0 +1,117 @@
+use anyhow::Result;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use weblook::capture::{load_manifest, OutputTemplate};
+
+use super::base_options;
+
+/// Test that manifest entries override the command line options they set
+#[test]
+fn test_manifest_entries_override_defaults() -> Result<()> {
+    let mut file = tempfile::NamedTempFile::new()?;
+    write!(file, r##"[
+        {{ "url": "https://example.com", "output": "home.png" }},
+        {{ "url": "https://example.com/pricing", "size": "1920x1080", "wait": "500ms", "selector": "#plans" }},
+        {{ "url": "https://example.com/about", "viewport": "800x600", "wait": 2 }}
+    ]"##)?;
+    
+    let entries = load_manifest(file.path().to_str().unwrap())?;
+    assert_eq!(entries.len(), 3);
+    let base = base_options();
+    
+    let home = entries[0].to_options(&base)?;
+    assert_eq!(home.output_path, PathBuf::from("home.png"));
+    assert_eq!(home.size, "1280x720");
+    assert_eq!(home.wait, Duration::from_secs(10));
+    
+    let pricing = entries[1].to_options(&base)?;
+    assert_eq!(pricing.url, "https://example.com/pricing");
+    assert_eq!(pricing.output_path, PathBuf::from("shots/example.com-pricing.png"));
+    assert_eq!(pricing.size, "1920x1080");
+    assert_eq!(pricing.wait, Duration::from_millis(500));
+    assert_eq!(pricing.selector.as_deref(), Some("#plans"));
+    assert_eq!(pricing.frame, None);
+    
+    let about = entries[2].to_options(&base)?;
+    assert_eq!(about.size, "800x600");
+    assert_eq!(about.wait, Duration::from_secs(2));
+    
+    Ok(())
+}
+
+/// Test that output templates fill in placeholders with path-safe values
+#[test]
+fn test_output_template() -> Result<()> {
+    let date = chrono::NaiveDate::from_ymd_opt(2026, 10, 18).unwrap();
+    let template: OutputTemplate = "shots/{date}/{host}-{path}-{size}-{index}.png".parse()?;
+    let url = url::Url::parse("https://docs.example.com:8080/guide/intro?tab=2")?;
+    assert_eq!(template.expand(&url, "1280x720", 3, date), PathBuf::from("shots/2026-10-18/docs.example.com-guide-intro-1280x720-3.png"));
+    
+    let root = url::Url::parse("https://example.com/")?;
+    assert_eq!(template.expand(&root, "../../etc", 0, date), PathBuf::from("shots/2026-10-18/example.com-index-..-..-etc-0.png"));
+    
+    assert!("{hostname}.png".parse::<OutputTemplate>().is_err());
+    assert!("{host.png".parse::<OutputTemplate>().is_err());
+    assert!("".parse::<OutputTemplate>().is_err());
+    Ok(())
+}
+
+/// Test that entries with their own output ignore the output template
+#[test]
+fn test_manifest_output_template() -> Result<()> {
+    let mut file = tempfile::NamedTempFile::new()?;
+    write!(file, r#"[{{ "url": "https://example.com", "output": "home.png" }}, {{ "url": "https://example.com/pricing", "size": "800x600" }}]"#)?;
+    
+    let entries = load_manifest(file.path().to_str().unwrap())?;
+    let template: OutputTemplate = "out/{index}-{path}-{size}.png".parse()?;
+    let base = base_options();
+    
+    assert_eq!(entries[0].to_templated_options(&base, &template, 0)?.output_path, PathBuf::from("home.png"));
+    assert_eq!(entries[1].to_templated_options(&base, &template, 1)?.output_path, PathBuf::from("out/1-pricing-800x600.png"));
+    Ok(())
+}
+
+/// Test that unknown manifest fields are rejected
+#[test]
+fn test_load_manifest_unknown_field() -> Result<()> {
+    let mut file = tempfile::NamedTempFile::new()?;
+    write!(file, r#"[{{ "url": "https://example.com", "delay": 5 }}]"#)?;
+    
+    assert!(load_manifest(file.path().to_str().unwrap()).is_err());
+    
+    Ok(())
+}
+
+/// Test that each manifest entry saves its text next to its own output
+#[test]
+fn test_manifest_text_follows_output() -> Result<()> {
+    let mut file = tempfile::NamedTempFile::new()?;
+    write!(file, r#"[{{ "url": "https://example.com", "output": "home.png" }}, {{ "url": "https://example.com/pricing" }}]"#)?;
+    
+    let entries = load_manifest(file.path().to_str().unwrap())?;
+    let mut base = base_options();
+    base.text_path = Some(PathBuf::from("page.txt"));
+    
+    assert_eq!(entries[0].to_options(&base)?.text_path, Some(PathBuf::from("home.txt")));
+    assert_eq!(entries[1].to_options(&base)?.text_path, Some(PathBuf::from("shots/example.com-pricing.txt")));
+    
+    Ok(())
+}
+
+/// Test that each manifest entry saves its performance metrics next to its own output
+#[test]
+fn test_manifest_perf_follows_output() -> Result<()> {
+    let mut file = tempfile::NamedTempFile::new()?;
+    write!(file, r#"[{{ "url": "https://example.com", "output": "home.png" }}, {{ "url": "https://example.com/pricing" }}]"#)?;
+    
+    let entries = load_manifest(file.path().to_str().unwrap())?;
+    let mut base = base_options();
+    base.perf_path = Some(PathBuf::from("perf.json"));
+    
+    assert_eq!(entries[0].to_options(&base)?.perf_path, Some(PathBuf::from("home.perf.json")));
+    assert_eq!(entries[1].to_options(&base)?.perf_path, Some(PathBuf::from("shots/example.com-pricing.perf.json")));
+    
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use image::{Rgba, RgbaImage};
+
+use weblook::montage::{build_montage, build_sprite_sheet, Tile};
+
+fn tile(width: u32, height: u32, color: Rgba<u8>, caption: Option<&str>) -> Tile {
+    Tile {
+        image: RgbaImage::from_pixel(width, height, color),
+        caption: caption.map(str::to_string),
+    }
+}
+
+/// Test that tiles are sized to the largest capture and laid out in rows
+#[test]
+fn test_montage_grid() {
+    let red = Rgba([255, 0, 0, 255]);
+    let blue = Rgba([0, 0, 255, 255]);
+    let tiles = vec![
+        tile(100, 50, red, None),
+        tile(80, 60, red, None),
+        tile(100, 60, blue, None),
+    ];
+    
+    let sheet = build_montage(&tiles, 2);
+    // Two 100x60 tiles per row, two rows, with 16px padding around every tile
+    assert_eq!(sheet.dimensions(), (2 * 116 + 16, 2 * 76 + 16));
+    
+    // The third tile starts the second row
+    assert_eq!(*sheet.get_pixel(16, 16 + 76), blue);
+    // Smaller captures are padded rather than stretched
+    assert_ne!(*sheet.get_pixel(16, 16 + 55), red);
+    assert_ne!(*sheet.get_pixel(16 + 116 + 90, 16), red);
+}
+
+/// Test that sprite sheets place equally sized frames side by side
+#[test]
+fn test_sprite_sheet() {
+    let red = Rgba([255, 0, 0, 255]);
+    let blue = Rgba([0, 0, 255, 255]);
+    let frames = vec![RgbaImage::from_pixel(30, 20, red), RgbaImage::from_pixel(30, 20, blue)];
+    
+    let sheet = build_sprite_sheet(&frames).unwrap();
+    assert_eq!(sheet.dimensions(), (60, 20));
+    assert_eq!(*sheet.get_pixel(29, 19), red);
+    assert_eq!(*sheet.get_pixel(30, 0), blue);
+    
+    let mismatched = vec![RgbaImage::from_pixel(30, 20, red), RgbaImage::from_pixel(30, 25, blue)];
+    let err = build_sprite_sheet(&mismatched).unwrap_err();
+    assert_eq!(err.to_string(), "Frame 1 is 30x25, but the first frame is 30x20; every state must be the same size");
+    assert!(build_sprite_sheet(&[]).is_err());
+}
+
+/// Test that captions add room under every row and columns are capped by the tile count
+#[test]
+fn test_montage_captions() {
+    let white = Rgba([255, 255, 255, 255]);
+    let tiles = vec![
+        tile(40, 30, white, Some("https://example.com/a-very-long-page-name")),
+        tile(40, 30, white, None),
+    ];
+    
+    let sheet = build_montage(&tiles, 5);
+    assert_eq!(sheet.width(), 2 * 56 + 16);
+    assert!(sheet.height() > 30 + 32);
+    
+    // The caption is drawn under the tile but never spills past it
+    let caption_rows = 16 + 30..sheet.height() - 16;
+    let drawn = |x_range: std::ops::Range<u32>| {
+        caption_rows.clone().any(|y| x_range.clone().any(|x| sheet.get_pixel(x, y).0[0] < 100))
+    };
+    assert!(drawn(16..56));
+    assert!(!drawn(56..72));
+}
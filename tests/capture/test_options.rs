@@ -0,0 +1,616 @@
+use std::time::Duration;
+
+use weblook::Session;
+use weblook::capture::{check_console_log, check_screenshot, chrome_prefs, console_log_path, console_log_section, format_from_extension, output_format, numbered_path, size_path, unused_path, encode_screenshot, expand_env, favicon_path, file_name_for_title, file_name_for_url, styles_path, normalize_text, parse_duration, parse_user_agents, perform_capture, scaled_size, CaptureError, ChromePref, ConsoleLogMode, DialogAction, DomainFilter, DomainPattern, FontSubstitute, Jitter, MediaType, RecordingFormat, MissingVar, ReadyState, ScrollTarget, StdoutFormat, StyleQuery, ViewportSize, WindowPosition};
+
+use super::base_options;
+
+/// Test that durations accept units and bare seconds
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("10").unwrap(), Duration::from_secs(10));
+    assert_eq!(parse_duration("1.5").unwrap(), Duration::from_millis(1500));
+    assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+    assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+    assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+    assert_eq!(parse_duration("1m30s").unwrap(), Duration::from_secs(90));
+    
+    assert!(parse_duration("").is_err());
+    assert!(parse_duration("-1").is_err());
+    assert!(parse_duration("10 parsecs").is_err());
+    assert!(parse_duration("ms").is_err());
+}
+
+/// Test that jitter parses as a range or a maximum, and samples within it
+#[test]
+fn test_jitter() {
+    let jitter = "500ms-2s".parse::<Jitter>().unwrap();
+    assert_eq!(jitter, Jitter { min: Duration::from_millis(500), max: Duration::from_secs(2) });
+    for _ in 0..100 {
+        let delay = jitter.sample();
+        assert!(delay >= jitter.min && delay <= jitter.max);
+    }
+    assert_eq!("3s".parse::<Jitter>().unwrap(), Jitter { min: Duration::ZERO, max: Duration::from_secs(3) });
+    
+    assert!("2s-1s".parse::<Jitter>().is_err());
+    assert!("1s-".parse::<Jitter>().is_err());
+}
+
+/// Test that window positions parse as X,Y, allowing negative coordinates
+#[test]
+fn test_parse_window_position() {
+    assert_eq!("1920,0".parse::<WindowPosition>().unwrap(), WindowPosition { x: 1920, y: 0 });
+    assert_eq!("-1280, 200".parse::<WindowPosition>().unwrap(), WindowPosition { x: -1280, y: 200 });
+    
+    assert!("1920".parse::<WindowPosition>().is_err());
+    assert!("1920,top".parse::<WindowPosition>().is_err());
+}
+
+/// Test that scroll targets parse as pixel offsets or selectors
+#[test]
+fn test_parse_scroll_target() {
+    assert_eq!("800".parse::<ScrollTarget>().unwrap(), ScrollTarget::Pixels(800));
+    assert_eq!("800px".parse::<ScrollTarget>().unwrap(), ScrollTarget::Pixels(800));
+    assert_eq!("#pricing".parse::<ScrollTarget>().unwrap(), ScrollTarget::Selector("#pricing".to_string()));
+    
+    assert!("-10".parse::<ScrollTarget>().is_err());
+    assert!("".parse::<ScrollTarget>().is_err());
+}
+
+/// Test that media types parse case-insensitively
+#[test]
+fn test_parse_media_type() {
+    assert_eq!("print".parse::<MediaType>().unwrap(), MediaType::Print);
+    assert_eq!("Screen".parse::<MediaType>().unwrap(), MediaType::Screen);
+    
+    assert!("speech".parse::<MediaType>().is_err());
+}
+
+/// Test that file names derived from URLs keep the host and path
+#[test]
+fn test_file_name_for_url() {
+    let url = url::Url::parse("https://example.com/docs/intro?lang=en").unwrap();
+    assert_eq!(file_name_for_url(&url, "png"), "example.com-docs-intro.png");
+    
+    let url = url::Url::parse("https://example.com/").unwrap();
+    assert_eq!(file_name_for_url(&url, "gif"), "example.com.gif");
+}
+
+/// Test that captures are named after the page title, or the URL for untitled pages
+#[test]
+fn test_file_name_for_title() {
+    let url = url::Url::parse("https://example.com/pricing").unwrap();
+    assert_eq!(file_name_for_title("Pricing | Example, Inc.", &url, "png"), "Pricing-Example-Inc.png");
+    assert_eq!(file_name_for_title("  ", &url, "png"), "example.com-pricing.png");
+    assert_eq!(file_name_for_title("料金", &url, "gif"), "example.com-pricing.gif");
+    assert_eq!(file_name_for_title(&"a b ".repeat(100), &url, "png").len(), 99 + ".png".len());
+}
+
+/// Test that existing files are numbered around rather than replaced
+#[test]
+fn test_unused_path() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("Home.png");
+    assert_eq!(unused_path(&path), path);
+    std::fs::write(&path, b"").unwrap();
+    assert_eq!(unused_path(&path), dir.path().join("Home-2.png"));
+    std::fs::write(dir.path().join("Home-2.png"), b"").unwrap();
+    assert_eq!(unused_path(&path), dir.path().join("Home-3.png"));
+}
+
+/// Test that console logs are shared or split between pages as asked
+#[test]
+fn test_console_log_path() {
+    let output = std::path::Path::new("shots/home.png");
+    assert_eq!(console_log_path("logs/console.log", ConsoleLogMode::Replace, output), std::path::Path::new("logs/console.log"));
+    assert_eq!(console_log_path("logs/console.log", ConsoleLogMode::Append, output), std::path::Path::new("logs/console.log"));
+    assert_eq!(console_log_path("logs/console.log", ConsoleLogMode::PerPage, output), std::path::Path::new("shots/home-console.log"));
+    assert_eq!("per-page".parse::<ConsoleLogMode>().unwrap(), ConsoleLogMode::PerPage);
+    assert!("split".parse::<ConsoleLogMode>().is_err());
+}
+
+/// Test that appended console logs are headed by their page's URL
+#[test]
+fn test_console_log_section() {
+    assert_eq!(
+        console_log_section("https://example.com/", "[t] [INFO] hi\n"),
+        "==> https://example.com/ <==\n[t] [INFO] hi\n\n"
+    );
+}
+
+/// Test that several pages can't replace one console log
+#[test]
+fn test_check_console_log() {
+    let mut options = base_options();
+    assert!(check_console_log(&options, 3).is_ok());
+    options.console_log = Some("console.log".to_string());
+    assert!(check_console_log(&options, 1).is_ok());
+    assert!(check_console_log(&options, 3).is_err());
+    options.console_log_mode = ConsoleLogMode::Append;
+    assert!(check_console_log(&options, 3).is_ok());
+}
+
+/// Test that favicons are named after the output they are saved next to
+#[test]
+fn test_favicon_path() {
+    assert_eq!(favicon_path(std::path::Path::new("shots/home.png"), "ico"), std::path::Path::new("shots/home-favicon.ico"));
+    assert_eq!(favicon_path(std::path::Path::new("-"), "svg"), std::path::Path::new("weblook-favicon.svg"));
+}
+
+/// Test that computed styles are saved next to the output
+#[test]
+fn test_styles_path() {
+    assert_eq!(styles_path(std::path::Path::new("shots/home.png")), std::path::Path::new("shots/home-styles.json"));
+    assert_eq!(styles_path(std::path::Path::new("-")), std::path::Path::new("weblook-styles.json"));
+}
+
+/// Test parsing of --styles queries
+#[test]
+fn test_parse_style_query() {
+    let query: StyleQuery = "h1=color, Font-Size".parse().unwrap();
+    assert_eq!(query, StyleQuery { selector: "h1".to_string(), properties: vec!["color".to_string(), "font-size".to_string()] });
+    
+    // The selector ends at the last =, so attribute selectors keep theirs
+    let query: StyleQuery = "input[type=text]=*".parse().unwrap();
+    assert_eq!(query, StyleQuery { selector: "input[type=text]".to_string(), properties: Vec::new() });
+    
+    assert!("h1".parse::<StyleQuery>().is_err());
+    assert!("=color".parse::<StyleQuery>().is_err());
+    assert!("h1=".parse::<StyleQuery>().is_err());
+    assert!("h1=color,,margin".parse::<StyleQuery>().is_err());
+    assert!("h1=color;x".parse::<StyleQuery>().is_err());
+}
+
+/// Test --pref parsing, with JSON values and plain strings
+#[test]
+fn test_parse_chrome_pref() {
+    let pref: ChromePref = "profile.password_manager_enabled=false".parse().unwrap();
+    assert_eq!(pref, ChromePref { key: "profile.password_manager_enabled".to_string(), value: serde_json::json!(false) });
+    let pref: ChromePref = "profile.default_content_setting_values.notifications=2".parse().unwrap();
+    assert_eq!(pref.value, serde_json::json!(2));
+    let pref: ChromePref = "intl.accept_languages=de-DE,de".parse().unwrap();
+    assert_eq!(pref.value, serde_json::json!("de-DE,de"));
+    let pref: ChromePref = "download.default_directory=\"/tmp/a=b\"".parse().unwrap();
+    assert_eq!(pref.value, serde_json::json!("/tmp/a=b"));
+    
+    assert!("profile.password_manager_enabled".parse::<ChromePref>().is_err());
+    assert!("=false".parse::<ChromePref>().is_err());
+    assert!("profile..enabled=false".parse::<ChromePref>().is_err());
+}
+
+/// Test --font-substitute parsing, with and without quoted names
+#[test]
+fn test_parse_font_substitute() {
+    let substitute: FontSubstitute = "Brand Sans=DejaVu Sans".parse().unwrap();
+    assert_eq!(substitute, FontSubstitute { family: "Brand Sans".to_string(), replacement: "DejaVu Sans".to_string() });
+    let substitute: FontSubstitute = "'Brand Serif' = \"Liberation Serif\"".parse().unwrap();
+    assert_eq!(substitute, FontSubstitute { family: "Brand Serif".to_string(), replacement: "Liberation Serif".to_string() });
+    
+    assert!("Brand Sans".parse::<FontSubstitute>().is_err());
+    assert!("Brand Sans=".parse::<FontSubstitute>().is_err());
+    assert!("=Arial".parse::<FontSubstitute>().is_err());
+}
+
+/// Test that dotted --pref keys nest into one preferences object
+#[test]
+fn test_chrome_prefs() {
+    let prefs: Vec<ChromePref> = [
+        "profile.password_manager_enabled=false",
+        "profile.default_content_setting_values.notifications=2",
+        "credentials_enable_service=false",
+        "credentials_enable_service=true",
+    ].iter().map(|pref| pref.parse().unwrap()).collect();
+    assert_eq!(serde_json::Value::Object(chrome_prefs(&prefs).unwrap()), serde_json::json!({
+        "profile": {
+            "password_manager_enabled": false,
+            "default_content_setting_values": { "notifications": 2 },
+        },
+        "credentials_enable_service": true,
+    }));
+    
+    let conflicting: Vec<ChromePref> = ["profile=1", "profile.enabled=false"].iter().map(|pref| pref.parse().unwrap()).collect();
+    assert!(chrome_prefs(&conflicting).is_err());
+    let conflicting: Vec<ChromePref> = ["profile.enabled=false", "profile=1"].iter().map(|pref| pref.parse().unwrap()).collect();
+    assert!(chrome_prefs(&conflicting).is_err());
+}
+
+/// Test that several images from one capture get numbered files next to the output
+#[test]
+fn test_numbered_path() {
+    assert_eq!(numbered_path(std::path::Path::new("shots/card.png"), 0), std::path::Path::new("shots/card-0.png"));
+    assert_eq!(numbered_path(std::path::Path::new("card"), 12), std::path::Path::new("card-12.png"));
+}
+
+/// Test that capturing each match needs a selector and a file to number
+#[tokio::test]
+async fn test_each_validation() {
+    let mut options = base_options();
+    options.url = "https://example.com".to_string();
+    options.each = true;
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert_eq!(err.to_string(), "Capturing each match needs a CSS selector");
+    
+    options.frame = Some(".card".to_string());
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert_eq!(err.to_string(), "Capturing each match needs a CSS selector");
+    
+    options.frame = None;
+    options.selector = Some(".card".to_string());
+    options.output_path = "-".into();
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert!(matches!(err, CaptureError::InvalidOptions(_)));
+    assert_eq!(err.to_string(), "Capturing each match writes numbered files, so it cannot write to stdout");
+    
+    // A selector made of digits stays a CSS selector, not a frame index
+    options.selector = Some("0".to_string());
+    let err = perform_capture(options).await.unwrap_err();
+    assert_eq!(err.to_string(), "Capturing each match writes numbered files, so it cannot write to stdout");
+}
+
+/// Test that only `${NAME}` references to variables are expanded
+#[test]
+fn test_expand_env() {
+    let lookup = |name: &str| (name == "TOKEN").then(|| "s3cret".to_string());
+    assert_eq!(expand_env("auth('${TOKEN}')", MissingVar::Error, lookup).unwrap(), "auth('s3cret')");
+    assert_eq!(expand_env("$TOKEN ${a + b} `$${TOKEN}` ${", MissingVar::Error, lookup).unwrap(), "$TOKEN ${a + b} `${TOKEN}` ${");
+    assert_eq!(expand_env("x=${UNSET}", MissingVar::Empty, lookup).unwrap(), "x=");
+    let err = expand_env("x=${UNSET}", MissingVar::Error, lookup).unwrap_err();
+    assert_eq!(err.to_string(), "Environment variable UNSET is not set");
+    assert!("strict".parse::<MissingVar>().is_err());
+}
+
+/// Test that saved page text keeps block breaks but not stray whitespace
+#[test]
+fn test_normalize_text() {
+    assert_eq!(normalize_text("Title\n\n\n  Some   text\there \nMore"), "Title\n\nSome text here\nMore\n");
+    assert_eq!(normalize_text(" \n\t\n"), "");
+}
+
+/// Test that invalid options are reported as such before a browser is started
+#[tokio::test]
+async fn test_invalid_options_error() {
+    let mut options = base_options();
+    options.url = "https://example.com".to_string();
+    options.size = "wide".to_string();
+    
+    let err = perform_capture(options).await.unwrap_err();
+    assert!(matches!(err, CaptureError::InvalidOptions(_)));
+    assert_eq!(err.to_string(), "Invalid viewport size format. Expected WIDTHxHEIGHT");
+}
+
+/// Test that a session checks its options before starting a browser
+#[tokio::test]
+async fn test_session_invalid_options() {
+    let mut options = base_options();
+    options.size = "wide".to_string();
+    assert!(matches!(Session::start(&options).await, Err(CaptureError::InvalidOptions(_))));
+    
+    let mut options = base_options();
+    options.allow_domains = vec!["example.com".to_string()];
+    options.deny_domains = vec!["ads.example.com".to_string()];
+    assert!(matches!(Session::start(&options).await, Err(CaptureError::InvalidOptions(_))));
+}
+
+/// Test that a missing or unusable Chrome binary is reported before ChromeDriver starts
+#[tokio::test]
+async fn test_chrome_binary_validation() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut options = base_options();
+    options.url = "https://example.com".to_string();
+    
+    options.chrome_binary = Some(dir.path().join("chrome-beta"));
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert!(matches!(err, CaptureError::InvalidOptions(_)));
+    assert!(err.to_string().ends_with("chrome-beta does not exist"), "{}", err);
+    
+    options.chrome_binary = Some(dir.path().to_path_buf());
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert!(err.to_string().contains("is a directory"), "{}", err);
+    
+    // A file without the executable bit isn't a browser either
+    #[cfg(unix)]
+    {
+        options.chrome_binary = Some(dir.path().join("chrome-beta"));
+        std::fs::write(dir.path().join("chrome-beta"), "").unwrap();
+        assert!(matches!(Session::start(&options).await, Err(CaptureError::InvalidOptions(_))));
+    }
+}
+
+/// Test that an invalid mask colour is reported before a browser is started
+#[tokio::test]
+async fn test_mask_color_validation() {
+    let mut options = base_options();
+    options.url = "https://example.com".to_string();
+    options.masks = vec![".account-number".to_string()];
+    options.mask_color = Some("red".to_string());
+    let err = perform_capture(options).await.unwrap_err();
+    assert!(matches!(err, CaptureError::InvalidOptions(_)));
+    assert_eq!(err.to_string(), "Invalid colour red. Expected #rgb, #rrggbb or #rrggbbaa");
+}
+
+/// Test that sprite sheet states need a script and numbered files for their frames
+#[tokio::test]
+async fn test_states_validation() {
+    let mut options = base_options();
+    options.url = "https://example.com".to_string();
+    options.states = Some(4);
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert_eq!(err.to_string(), "A sprite sheet needs a script to advance to each state");
+    
+    options.state_js = Some("document.body.dataset.state = arguments[0];".to_string());
+    options.state_frames = true;
+    options.output_path = "-".into();
+    let err = perform_capture(options).await.unwrap_err();
+    assert!(matches!(err, CaptureError::InvalidOptions(_)));
+    assert_eq!(err.to_string(), "State frames are saved as numbered files, so they cannot be written to stdout");
+}
+
+/// Test that the output file's extension picks the format it is written in
+#[test]
+fn test_output_format() {
+    assert_eq!(format_from_extension(std::path::Path::new("shot.JPG")), Some(StdoutFormat::Jpeg));
+    assert_eq!(format_from_extension(std::path::Path::new("shot.txt")), None);
+    assert_eq!(format_from_extension(std::path::Path::new("shot")), None);
+    
+    let mut options = base_options();
+    let format = |options: &weblook::CaptureOptions, path: &str| output_format(&weblook::CaptureOptions { output_path: path.into(), ..options.clone() });
+    assert_eq!(format(&options, "shot.png"), StdoutFormat::Png);
+    assert_eq!(format(&options, "shot.jpeg"), StdoutFormat::Jpeg);
+    assert_eq!(format(&options, "shot.webp"), StdoutFormat::Webp);
+    // Unknown and missing extensions, and GIF screenshots, fall back to PNG
+    assert_eq!(format(&options, "shot"), StdoutFormat::Png);
+    assert_eq!(format(&options, "shot.txt"), StdoutFormat::Png);
+    assert_eq!(format(&options, "shot.gif"), StdoutFormat::Png);
+    options.stdout_format = Some(StdoutFormat::Jpeg);
+    assert_eq!(format(&options, "-"), StdoutFormat::Jpeg);
+    
+    options.stdout_format = None;
+    options.is_recording = true;
+    assert_eq!(format(&options, "demo.gif"), StdoutFormat::Gif);
+    assert_eq!(format(&options, "demo.webp"), StdoutFormat::Webp);
+    assert_eq!(format(&options, "demo.png"), StdoutFormat::Gif);
+    assert_eq!(format(&options, "-"), StdoutFormat::Gif);
+    // An explicit WebP recording format wins over a .gif extension
+    options.recording_format = RecordingFormat::Webp;
+    assert_eq!(format(&options, "demo.gif"), StdoutFormat::Webp);
+}
+
+/// Test that screenshots of a resize sequence are named after their size
+#[test]
+fn test_size_path() {
+    let size = ViewportSize { width: 375, height: 667 };
+    assert_eq!(size_path(std::path::Path::new("shots/home.png"), size), std::path::Path::new("shots/home-375x667.png"));
+    assert_eq!(size_path(std::path::Path::new("home"), size), std::path::Path::new("home-375x667.png"));
+}
+
+/// Test that a resize sequence writes files, and only of screenshots
+#[tokio::test]
+async fn test_resize_sequence_validation() {
+    let mut options = base_options();
+    options.url = "https://example.com".to_string();
+    options.resize_settle = Some(Duration::from_secs(1));
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert_eq!(err.to_string(), "A resize settle delay needs a resize sequence");
+    
+    options.resize_sequence = vec![ViewportSize { width: 1280, height: 720 }, ViewportSize { width: 375, height: 0 }];
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert_eq!(err.to_string(), "Every size of a resize sequence must be at least 1x1");
+    
+    options.resize_sequence[1].height = 667;
+    options.pdf = Some("a4".to_string());
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert!(matches!(err, CaptureError::InvalidOptions(_)));
+    assert_eq!(err.to_string(), "A resize sequence cannot be combined with recordings, PDFs, capturing each match or states");
+    
+    options.pdf = None;
+    options.output_path = "-".into();
+    let err = perform_capture(options).await.unwrap_err();
+    assert_eq!(err.to_string(), "A resize sequence writes a file per size, so it cannot write to stdout");
+}
+
+/// Test that time can't be frozen in a recording
+#[tokio::test]
+async fn test_freeze_time_not_recorded() {
+    let mut options = base_options();
+    options.url = "https://example.com".to_string();
+    options.is_recording = true;
+    options.freeze_time = Some(Duration::from_millis(1500));
+    let err = perform_capture(options).await.unwrap_err();
+    assert!(matches!(err, CaptureError::InvalidOptions(_)));
+    assert_eq!(err.to_string(), "Time cannot be frozen while recording");
+}
+
+/// Test that domain patterns match exact hosts or, with a wildcard, their subdomains
+#[test]
+fn test_domain_pattern_matches() {
+    let exact: DomainPattern = "Example.com".parse().unwrap();
+    assert!(exact.matches("example.com"));
+    assert!(exact.matches("EXAMPLE.COM."));
+    assert!(!exact.matches("www.example.com"));
+    
+    let wildcard: DomainPattern = "*.example.com".parse().unwrap();
+    assert!(wildcard.matches("cdn.example.com"));
+    assert!(wildcard.matches("a.b.example.com"));
+    assert!(!wildcard.matches("example.com"));
+    assert!(!wildcard.matches("badexample.com"));
+    
+    assert!("".parse::<DomainPattern>().is_err());
+    assert!("https://example.com".parse::<DomainPattern>().is_err());
+    assert!("ads.*.com".parse::<DomainPattern>().is_err());
+}
+
+/// Test that domain filters permit the right hosts and map to Chrome resolver rules
+#[test]
+fn test_domain_filter() {
+    let allow = DomainFilter::Allow(vec!["example.com".parse().unwrap(), "*.example.com".parse().unwrap()]);
+    assert!(allow.permits("www.example.com"));
+    assert!(!allow.permits("ads.tracker.net"));
+    assert_eq!(allow.resolver_rules(), "MAP * ~NOTFOUND, EXCLUDE example.com, EXCLUDE *.example.com");
+    
+    let deny = DomainFilter::Deny(vec!["*.doubleclick.net".parse().unwrap()]);
+    assert!(deny.permits("example.com"));
+    assert!(!deny.permits("ad.doubleclick.net"));
+    assert_eq!(deny.resolver_rules(), "MAP *.doubleclick.net ~NOTFOUND");
+}
+
+/// Test that a capture whose own URL would be blocked is rejected up front
+#[tokio::test]
+async fn test_blocked_url_is_invalid() {
+    let mut options = base_options();
+    options.url = "https://example.com".to_string();
+    options.deny_domains = vec!["example.com".to_string()];
+    
+    let err = perform_capture(options).await.unwrap_err();
+    assert!(matches!(err, CaptureError::InvalidOptions(_)));
+    assert_eq!(err.to_string(), "example.com would be blocked by the domain filter");
+}
+
+/// Test that a referer must be an absolute http or https URL
+#[tokio::test]
+async fn test_invalid_referer() {
+    let mut options = base_options();
+    options.url = "https://example.com".to_string();
+    
+    options.referer = Some("example.com/search".to_string());
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert!(matches!(err, CaptureError::InvalidOptions(_)));
+    assert_eq!(err.to_string(), "Invalid referer example.com/search");
+    
+    options.referer = Some("file:///etc/hosts".to_string());
+    let err = perform_capture(options).await.unwrap_err();
+    assert_eq!(err.to_string(), "Invalid referer file:///etc/hosts. Expected an http or https URL");
+}
+
+/// Test that a capture can only be limited to one of a frame, an element or the region between selectors
+#[tokio::test]
+async fn test_frame_and_clip_between_conflict() {
+    let mut options = base_options();
+    options.url = "https://example.com".to_string();
+    options.frame = Some("0".to_string());
+    options.clip_between = Some(("header".to_string(), "footer".to_string()));
+    
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert!(matches!(err, CaptureError::InvalidOptions(_)));
+    
+    options.clip_between = None;
+    options.selector = Some("main".to_string());
+    let err = perform_capture(options).await.unwrap_err();
+    assert!(matches!(err, CaptureError::InvalidOptions(_)));
+}
+
+/// Test that transparent backgrounds are only allowed for formats with an alpha channel
+#[tokio::test]
+async fn test_transparent_needs_alpha() {
+    let mut options = base_options();
+    options.url = "https://example.com".to_string();
+    options.transparent = true;
+    options.output_path = "shot.jpg".into();
+    
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert!(matches!(err, CaptureError::InvalidOptions(_)));
+    assert_eq!(err.to_string(), "A transparent background needs PNG or WebP output, not JPEG");
+    
+    options.output_path = "demo.gif".into();
+    options.is_recording = true;
+    let err = perform_capture(options).await.unwrap_err();
+    assert_eq!(err.to_string(), "A transparent background needs PNG or WebP output, not GIF");
+}
+
+/// Test that the device pixel ratio is positive and keeps GIF frames within 16 bits
+#[tokio::test]
+async fn test_device_scale_limits() {
+    let mut options = base_options();
+    options.url = "https://example.com".to_string();
+    options.dpr = Some(0.0);
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert!(matches!(err, CaptureError::InvalidOptions(_)));
+    
+    options.dpr = Some(60.0);
+    options.output_path = "demo.gif".into();
+    options.is_recording = true;
+    let err = perform_capture(options).await.unwrap_err();
+    assert_eq!(err.to_string(), "GIF frames can be at most 65535 pixels wide and tall, but these would be 76800x43200");
+    assert_eq!(scaled_size(ViewportSize { width: 1280, height: 720 }, 1.5), ViewportSize { width: 1920, height: 1080 });
+}
+
+/// Test that stdout formats must suit the capture and only apply to stdout
+#[tokio::test]
+async fn test_stdout_format_validation() {
+    let mut options = base_options();
+    options.url = "https://example.com".to_string();
+    options.stdout_format = Some(StdoutFormat::Jpeg);
+    
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert_eq!(err.to_string(), "A stdout format only applies when writing to stdout (output -)");
+    
+    options.output_path = "-".into();
+    options.is_recording = true;
+    let err = perform_capture(options.clone()).await.unwrap_err();
+    assert!(matches!(err, CaptureError::InvalidOptions(_)));
+    assert_eq!(err.to_string(), "Recordings can only be written to stdout as GIF or WebP");
+    
+    options.is_recording = false;
+    options.stdout_format = Some(StdoutFormat::Gif);
+    let err = perform_capture(options).await.unwrap_err();
+    assert_eq!(err.to_string(), "Screenshots can only be written to stdout as PNG, JPEG or WebP");
+}
+
+/// Test that screenshots are re-encoded in the requested stdout format
+#[test]
+fn test_encode_screenshot() {
+    let mut png = Vec::new();
+    image::RgbaImage::from_pixel(8, 8, image::Rgba([10, 20, 30, 255]))
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    
+    assert_eq!(encode_screenshot(&png, StdoutFormat::Png).unwrap(), png);
+    assert!(encode_screenshot(&png, StdoutFormat::Jpeg).unwrap().starts_with(&[0xFF, 0xD8]));
+    let webp = encode_screenshot(&png, StdoutFormat::Webp).unwrap();
+    assert_eq!(&webp[..4], b"RIFF");
+    assert_eq!(&webp[8..12], b"WEBP");
+}
+
+/// Test that empty and truncated screenshots are caught rather than saved
+#[test]
+fn test_check_screenshot() {
+    let mut png = Vec::new();
+    image::RgbaImage::from_pixel(8, 8, image::Rgba([10, 20, 30, 255]))
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    
+    assert!(check_screenshot(&png).is_ok());
+    let err = check_screenshot(&[]).unwrap_err();
+    assert_eq!(err.to_string(), "The browser returned a broken screenshot (0 bytes); its renderer may have crashed");
+    assert!(check_screenshot(&png[..png.len() / 2]).is_err());
+}
+
+/// Test that user agent files skip blank lines and surrounding whitespace
+#[test]
+fn test_parse_user_agents() {
+    let content = "Mozilla/5.0 (X11; Linux x86_64) Firefox/125.0\n\n  Mozilla/5.0 (iPhone) Safari/604.1  \r\n";
+    assert_eq!(parse_user_agents(content), vec![
+        "Mozilla/5.0 (X11; Linux x86_64) Firefox/125.0".to_string(),
+        "Mozilla/5.0 (iPhone) Safari/604.1".to_string(),
+    ]);
+    assert!(parse_user_agents("\n  \n").is_empty());
+}
+
+/// Test parsing of --ready-state values and their loading order
+#[test]
+fn test_parse_ready_state() {
+    assert_eq!("interactive".parse::<ReadyState>().unwrap(), ReadyState::Interactive);
+    assert_eq!("Complete".parse::<ReadyState>().unwrap(), ReadyState::Complete);
+    assert!("loading".parse::<ReadyState>().is_err());
+    
+    assert!(ReadyState::Loading < ReadyState::Interactive);
+    assert!(ReadyState::Interactive < ReadyState::Complete);
+}
+
+/// Test parsing of --dialog values, which default to dismissing
+#[test]
+fn test_parse_dialog_action() {
+    assert_eq!("accept".parse::<DialogAction>().unwrap(), DialogAction::Accept);
+    assert_eq!("Dismiss".parse::<DialogAction>().unwrap(), DialogAction::Dismiss);
+    assert_eq!(DialogAction::default(), DialogAction::Dismiss);
+    assert!("ignore".parse::<DialogAction>().is_err());
+}
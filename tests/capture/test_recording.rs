@@ -0,0 +1,263 @@
+use weblook::capture::{clip_range, fit_frames, keyframe_path, resize_frame, write_webp_to_buffer, CaptureInfo, ClipTime, DelayRamp, FrameFit, FrameStorage, ImageEffects, RecordingFormat, RecordingSettings, RecordingStats, ResizeFilter, write_gif_to_buffer};
+
+/// Test that the GIF frame delay follows the playback rate, not the capture rate
+#[test]
+fn test_gif_frame_delay_uses_playback_fps() {
+    let realtime = RecordingSettings { duration_secs: 1, capture_fps: 10, playback_fps: 10, gif_width: None, colors: 256, resize_filter: ResizeFilter::Lanczos3, frame_fit: FrameFit::Pad, format: RecordingFormat::Gif, keyframes: Vec::new(), clip: None, max_frames: None, delay_ramp: DelayRamp::Linear, on_change: false, effects: ImageEffects::default() };
+    assert_eq!(realtime.gif_frame_delay(), 10);
+    
+    let slow_motion = RecordingSettings { duration_secs: 1, capture_fps: 10, playback_fps: 5, gif_width: None, colors: 256, resize_filter: ResizeFilter::Lanczos3, frame_fit: FrameFit::Pad, format: RecordingFormat::Gif, keyframes: Vec::new(), clip: None, max_frames: None, delay_ramp: DelayRamp::Linear, on_change: false, effects: ImageEffects::default() };
+    assert_eq!(slow_motion.gif_frame_delay(), 20);
+    
+    let rounded = RecordingSettings { duration_secs: 1, capture_fps: 10, playback_fps: 3, gif_width: None, colors: 256, resize_filter: ResizeFilter::Lanczos3, frame_fit: FrameFit::Pad, format: RecordingFormat::Gif, keyframes: Vec::new(), clip: None, max_frames: None, delay_ramp: DelayRamp::Linear, on_change: false, effects: ImageEffects::default() };
+    assert_eq!(rounded.gif_frame_delay(), 33);
+}
+
+/// Test that frames are downscaled to the target width with the aspect ratio kept
+#[test]
+fn test_resize_frame_keeps_aspect_ratio() {
+    let img = image::DynamicImage::new_rgba8(1280, 720);
+    
+    let resized = resize_frame(img.clone(), Some(640), ResizeFilter::Lanczos3);
+    assert_eq!((resized.width(), resized.height()), (640, 360));
+    
+    // Frames are never upscaled
+    let unchanged = resize_frame(img, Some(1920), ResizeFilter::Lanczos3);
+    assert_eq!((unchanged.width(), unchanged.height()), (1280, 720));
+}
+
+/// Test that nearest-neighbour resizing keeps hard pixel edges
+#[test]
+fn test_resize_frame_nearest_keeps_pixels_sharp() {
+    let mut img = image::RgbaImage::from_pixel(4, 2, image::Rgba([0, 0, 0, 255]));
+    for y in 0..2 {
+        img.put_pixel(2, y, image::Rgba([255, 255, 255, 255]));
+        img.put_pixel(3, y, image::Rgba([255, 255, 255, 255]));
+    }
+    
+    let resized = resize_frame(image::DynamicImage::ImageRgba8(img), Some(2), ResizeFilter::Nearest).to_rgba8();
+    assert_eq!(resized.get_pixel(0, 0).0, [0, 0, 0, 255]);
+    assert_eq!(resized.get_pixel(1, 0).0, [255, 255, 255, 255]);
+}
+
+/// Test parsing of --resize-filter values
+#[test]
+fn test_parse_resize_filter() {
+    assert_eq!("nearest".parse::<ResizeFilter>().unwrap(), ResizeFilter::Nearest);
+    assert_eq!("triangle".parse::<ResizeFilter>().unwrap(), ResizeFilter::Triangle);
+    assert_eq!("catmull".parse::<ResizeFilter>().unwrap(), ResizeFilter::CatmullRom);
+    assert_eq!("Lanczos3".parse::<ResizeFilter>().unwrap(), ResizeFilter::Lanczos3);
+    assert!("bicubic".parse::<ResizeFilter>().is_err());
+}
+
+/// Test that grayscale and invert change the colours but keep transparency
+#[test]
+fn test_image_effects() {
+    let img = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba([200, 100, 0, 128])));
+    
+    let unchanged = ImageEffects::default().apply(img.clone()).to_rgba8();
+    assert_eq!(unchanged.get_pixel(0, 0).0, [200, 100, 0, 128]);
+    
+    let inverted = ImageEffects { grayscale: false, invert: true }.apply(img.clone()).to_rgba8();
+    assert_eq!(inverted.get_pixel(0, 0).0, [55, 155, 255, 128]);
+    
+    let [r, g, b, a] = ImageEffects { grayscale: true, invert: false }.apply(img.clone()).to_rgba8().get_pixel(0, 0).0;
+    assert!(r == g && g == b && r > 0 && r < 200);
+    assert_eq!(a, 128);
+    
+    // Grayscale comes first, so inverting it gives the inverted grey
+    let both = ImageEffects { grayscale: true, invert: true }.apply(img).to_rgba8();
+    assert_eq!(both.get_pixel(0, 0).0, [255 - r, 255 - g, 255 - b, 128]);
+}
+
+/// Test that frames of differing sizes are padded, cropped or rejected
+#[test]
+fn test_fit_frames() {
+    let frames = || vec![image::RgbaImage::new(100, 50), image::RgbaImage::new(100, 80)];
+    
+    let (padded, changed) = fit_frames(frames(), FrameFit::Pad).unwrap();
+    assert!(changed);
+    assert!(padded.iter().all(|f| f.dimensions() == (100, 80)));
+    
+    let (cropped, changed) = fit_frames(frames(), FrameFit::Crop).unwrap();
+    assert!(changed);
+    assert!(cropped.iter().all(|f| f.dimensions() == (100, 50)));
+    
+    let err = fit_frames(frames(), FrameFit::Error).unwrap_err();
+    assert!(err.to_string().contains("Recorded frame 1 is 100x80"));
+}
+
+/// Test that frames encode to an animated WebP
+#[test]
+fn test_write_webp_to_buffer() {
+    let frames = vec![
+        image::RgbaImage::from_pixel(16, 16, image::Rgba([255, 0, 0, 255])),
+        image::RgbaImage::from_pixel(16, 16, image::Rgba([0, 0, 255, 255])),
+    ];
+    
+    let mut buffer = Vec::new();
+    write_webp_to_buffer(&frames, &[10, 10], &mut buffer).unwrap();
+    assert_eq!(&buffer[0..4], b"RIFF");
+    assert_eq!(&buffer[8..12], b"WEBP");
+    
+    let decoded = webp::AnimDecoder::new(&buffer).decode().unwrap();
+    assert!(decoded.has_animation());
+    assert_eq!(decoded.len(), 2);
+}
+
+/// Test that GIF frames are limited to the requested number of colours
+#[test]
+fn test_write_gif_to_buffer_limits_colors() {
+    let gradient = image::RgbaImage::from_fn(64, 4, |x, _| image::Rgba([x as u8 * 4, 0, 255 - x as u8 * 4, 255]));
+    let colors_used = |colors| {
+        let mut buffer = Vec::new();
+        write_gif_to_buffer(std::slice::from_ref(&gradient), &[10], colors, &mut buffer).unwrap();
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(buffer.as_slice()).unwrap();
+        let frame = decoder.read_next_frame().unwrap().unwrap();
+        assert!(frame.palette.as_ref().is_some_and(|palette| palette.len() <= colors as usize * 3));
+        frame.buffer.iter().copied().collect::<std::collections::HashSet<u8>>().len()
+    };
+    
+    assert!(colors_used(8) <= 8);
+    assert_eq!(colors_used(256), 64);
+    
+    // Frames with few colours keep them exactly
+    let flat = image::RgbaImage::from_pixel(8, 8, image::Rgba([12, 34, 56, 255]));
+    let mut buffer = Vec::new();
+    write_gif_to_buffer(&[flat], &[10], 2, &mut buffer).unwrap();
+    let decoded = image::load_from_memory(&buffer).unwrap().to_rgba8();
+    assert!(decoded.pixels().all(|pixel| *pixel == image::Rgba([12, 34, 56, 255])));
+}
+
+/// Test that a capture summary prints as a single parseable line
+#[test]
+fn test_capture_info_display() {
+    let screenshot = CaptureInfo {
+        output_path: "weblook.png".into(),
+        final_url: Some("https://example.com/".to_string()),
+        user_agent: None,
+        dimensions: Some((1280, 720)),
+        bytes: Some(84213),
+        recording: None,
+        elements: None,
+        sizes: None,
+        favicon: None,
+        styles: None,
+        redirects: None,
+    };
+    assert_eq!(screenshot.to_string(), "weblook.png 1280x720 84213 bytes");
+    
+    let recording = CaptureInfo {
+        output_path: "weblook.gif".into(),
+        final_url: None,
+        user_agent: None,
+        dimensions: Some((640, 360)),
+        bytes: Some(1024),
+        recording: Some(RecordingStats { frames: 50, duration: std::time::Duration::from_secs(5) }),
+        elements: None,
+        sizes: None,
+        favicon: None,
+        styles: None,
+        redirects: None,
+    };
+    assert_eq!(recording.to_string(), "weblook.gif 640x360 1024 bytes 50 frames 5.0s");
+}
+
+/// Test that keyframe offsets map to frames and derived file names
+#[test]
+fn test_keyframes() {
+    let settings = RecordingSettings { duration_secs: 5, capture_fps: 10, playback_fps: 10, gif_width: None, colors: 256, resize_filter: ResizeFilter::Lanczos3, frame_fit: FrameFit::Pad, format: RecordingFormat::Gif, keyframes: Vec::new(), clip: None, max_frames: None, delay_ramp: DelayRamp::Linear, on_change: false, effects: ImageEffects::default() };
+    assert_eq!(settings.keyframe_index(std::time::Duration::ZERO), 0);
+    assert_eq!(settings.keyframe_index(std::time::Duration::from_millis(2500)), 25);
+    // The very end of the recording maps to the last frame
+    assert_eq!(settings.keyframe_index(std::time::Duration::from_secs(5)), 49);
+    
+    let path = keyframe_path(std::path::Path::new("out/demo.gif"), std::time::Duration::from_millis(2500));
+    assert_eq!(path, std::path::PathBuf::from("out/demo-keyframe-2.5s.png"));
+    
+    let path = keyframe_path(std::path::Path::new("-"), std::time::Duration::from_secs(3));
+    assert_eq!(path, std::path::PathBuf::from("weblook-keyframe-3s.png"));
+}
+
+/// Test that the frame cap limits the number of frames captured
+#[test]
+fn test_max_frames_caps_total_frames() {
+    let uncapped = RecordingSettings { duration_secs: 600, capture_fps: 30, playback_fps: 30, gif_width: None, colors: 256, resize_filter: ResizeFilter::Lanczos3, frame_fit: FrameFit::Pad, format: RecordingFormat::Gif, keyframes: Vec::new(), clip: None, max_frames: None, delay_ramp: DelayRamp::Linear, on_change: false, effects: ImageEffects::default() };
+    assert_eq!(uncapped.total_frames(), 18000);
+    
+    let capped = RecordingSettings { max_frames: Some(500), ..uncapped };
+    assert_eq!(capped.total_frames(), 500);
+    // Keyframes past the cap map to the last captured frame
+    assert_eq!(capped.keyframe_index(std::time::Duration::from_secs(60)), 499);
+}
+
+/// Test that the ease ramp slows the ends of an animation and linear keeps delays constant
+#[test]
+fn test_frame_delays_follow_ramp() {
+    let linear = RecordingSettings { duration_secs: 1, capture_fps: 10, playback_fps: 10, gif_width: None, colors: 256, resize_filter: ResizeFilter::Lanczos3, frame_fit: FrameFit::Pad, format: RecordingFormat::Gif, keyframes: Vec::new(), clip: None, max_frames: None, delay_ramp: DelayRamp::Linear, on_change: false, effects: ImageEffects::default() };
+    assert_eq!(linear.frame_delays(5), vec![10; 5]);
+    
+    let ease = RecordingSettings { delay_ramp: DelayRamp::Ease, ..linear };
+    assert_eq!(ease.frame_delays(5), vec![20, 15, 10, 15, 20]);
+    assert_eq!(ease.frame_delays(1), vec![20]);
+    
+    assert_eq!("ease".parse::<DelayRamp>().unwrap(), DelayRamp::Ease);
+    assert!("bounce".parse::<DelayRamp>().is_err());
+}
+
+/// Test that frame storage is chosen by name, defaulting to disk
+#[test]
+fn test_parse_frame_storage() {
+    assert_eq!("memory".parse::<FrameStorage>().unwrap(), FrameStorage::Memory);
+    assert_eq!("Disk".parse::<FrameStorage>().unwrap(), FrameStorage::Disk);
+    assert_eq!(FrameStorage::default(), FrameStorage::Disk);
+    assert!("ram".parse::<FrameStorage>().is_err());
+}
+
+/// Test that on-change frames last until the next change, scaled by the playback rate
+#[test]
+fn test_elapsed_delays_follow_changes() {
+    use std::time::Duration;
+    let realtime = RecordingSettings { duration_secs: 5, capture_fps: 10, playback_fps: 10, gif_width: None, colors: 256, resize_filter: ResizeFilter::Lanczos3, frame_fit: FrameFit::Pad, format: RecordingFormat::Gif, keyframes: Vec::new(), clip: None, max_frames: None, delay_ramp: DelayRamp::Linear, on_change: true, effects: ImageEffects::default() };
+    let times = [Duration::ZERO, Duration::from_millis(1200), Duration::from_millis(1205), Duration::from_millis(3000)];
+    assert_eq!(realtime.elapsed_delays(&times, Duration::from_secs(5)), vec![120, 2, 180, 200]);
+    assert!(realtime.elapsed_delays(&[], Duration::from_secs(5)).is_empty());
+    
+    let slow_motion = RecordingSettings { playback_fps: 5, ..realtime };
+    assert_eq!(slow_motion.elapsed_delays(&times[..2], Duration::from_secs(2)), vec![240, 160]);
+}
+
+/// Test --clip-time parsing, which needs the start before the end
+#[test]
+fn test_parse_clip_time() {
+    use std::time::Duration;
+    assert_eq!("2s-5.5s".parse::<ClipTime>().unwrap(), ClipTime { start: Duration::from_secs(2), end: Duration::from_millis(5500) });
+    assert_eq!("0-1m".parse::<ClipTime>().unwrap(), ClipTime { start: Duration::ZERO, end: Duration::from_secs(60) });
+    
+    assert!("5s-2s".parse::<ClipTime>().is_err());
+    assert!("3s-3s".parse::<ClipTime>().is_err());
+    assert!("3s".parse::<ClipTime>().is_err());
+    assert!("a-b".parse::<ClipTime>().is_err());
+}
+
+/// Test that a clip keeps the frames shown during it, including the one on screen at its start
+#[test]
+fn test_clip_range() {
+    use std::time::Duration;
+    let clip = ClipTime { start: Duration::from_secs(1), end: Duration::from_secs(2) };
+    
+    // Ten frames a second
+    let fixed: Vec<Duration> = (0..30).map(|i| Duration::from_millis(i * 100)).collect();
+    assert_eq!(clip_range(&fixed, clip), 10..20);
+    
+    // Frames kept only when the page changed
+    let changes = [Duration::ZERO, Duration::from_millis(800), Duration::from_millis(1500), Duration::from_millis(2500)];
+    assert_eq!(clip_range(&changes, clip), 1..3);
+    
+    // A clip before the first frame or after the last keeps only what was on screen
+    let late = ClipTime { start: Duration::from_secs(5), end: Duration::from_secs(6) };
+    assert_eq!(clip_range(&changes, late), 3..4);
+    assert!(clip_range(&[], clip).is_empty());
+}
@@ -0,0 +1,63 @@
+use serde_json::{json, Value};
+
+use weblook::capture::{redirect_chain, CaptureInfo, RedirectHop};
+
+fn request(id: &str, frame: &str, kind: &str, url: &str, redirect: Option<(&str, u16)>) -> Value {
+    let mut params = json!({ "requestId": id, "frameId": frame, "type": kind, "request": { "url": url } });
+    if let Some((from, status)) = redirect {
+        params["redirectResponse"] = json!({ "url": from, "status": status });
+    }
+    json!({ "method": "Network.requestWillBeSent", "params": params })
+}
+
+fn hop(url: &str, status: Option<u16>) -> RedirectHop {
+    RedirectHop { url: url.to_string(), status }
+}
+
+/// Test that HTTP redirects and script navigations of the top frame make up the chain
+#[test]
+fn test_redirect_chain() {
+    let events = [
+        request("1", "main", "Document", "http://go.example/promo", None),
+        request("1", "main", "Document", "https://go.example/promo", Some(("http://go.example/promo", 301))),
+        request("1", "main", "Document", "https://shop.example/landing", Some(("https://go.example/promo", 302))),
+        json!({ "method": "Network.responseReceived", "params": { "requestId": "1", "type": "Document" } }),
+        request("2", "main", "Script", "https://shop.example/app.js", None),
+        request("3", "ad", "Document", "https://ads.example/frame", None),
+        request("4", "main", "Document", "https://shop.example/sale", None),
+        request("4", "main", "Document", "https://shop.example/sale", None),
+    ];
+    assert_eq!(redirect_chain(&events), vec![
+        hop("http://go.example/promo", Some(301)),
+        hop("https://go.example/promo", Some(302)),
+        hop("https://shop.example/landing", None),
+        hop("https://shop.example/sale", None),
+    ]);
+}
+
+/// Test that a page that wasn't redirected is a chain of one, and no requests an empty one
+#[test]
+fn test_redirect_chain_without_redirects() {
+    let events = [request("1", "main", "Document", "https://example.com/", None)];
+    assert_eq!(redirect_chain(&events), vec![hop("https://example.com/", None)]);
+    assert!(redirect_chain(&[]).is_empty());
+}
+
+/// Test that the capture summary counts the redirects
+#[test]
+fn test_capture_info_counts_redirects() {
+    let info = CaptureInfo {
+        output_path: "weblook.png".into(),
+        final_url: None,
+        user_agent: None,
+        dimensions: Some((1280, 720)),
+        bytes: Some(84213),
+        recording: None,
+        elements: None,
+        sizes: None,
+        favicon: None,
+        styles: None,
+        redirects: Some(vec![hop("http://example.com/", Some(301)), hop("https://example.com/", None)]),
+    };
+    assert_eq!(info.to_string(), "weblook.png 1280x720 84213 bytes 1 redirects");
+}
@@ -0,0 +1,2 @@
+// Change tracking tests against a mock HTTP server, without a browser
+mod test_changes;
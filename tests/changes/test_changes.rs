@@ -0,0 +1,79 @@
+use weblook::capture::CaptureOptions;
+use weblook::changes::{fetch_validators, ChangeState, PageStatus, Validators};
+
+use crate::capture::base_options;
+
+fn options(url: String) -> CaptureOptions {
+    CaptureOptions { url, ..base_options() }
+}
+
+/// Test that a page is only unchanged when it sends the recorded validators
+#[test]
+fn test_change_state_status() {
+    let etag = Validators { etag: Some("\"v1\"".to_string()), last_modified: None };
+    let mut state = ChangeState::default();
+    assert_eq!(state.status("https://example.com/", Some(etag.clone())), PageStatus::Changed(Some(etag.clone())));
+    
+    state.record("https://example.com/", Some(etag.clone()));
+    assert_eq!(state.status("https://example.com/", Some(etag.clone())), PageStatus::Unchanged);
+    
+    let newer = Validators { etag: Some("\"v2\"".to_string()), last_modified: None };
+    assert_eq!(state.status("https://example.com/", Some(newer.clone())), PageStatus::Changed(Some(newer)));
+    // Without caching headers the page is always captured, and forgotten
+    assert_eq!(state.status("https://example.com/", None), PageStatus::Changed(None));
+    state.record("https://example.com/", None);
+    assert!(state.pages.is_empty());
+}
+
+/// Test that the state file is created on first use and read back by the next run
+#[test]
+fn test_change_state_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("state.json");
+    
+    let mut state = ChangeState::load(&path).unwrap();
+    assert!(state.pages.is_empty());
+    let validators = Validators { etag: None, last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()) };
+    state.record("https://example.com/", Some(validators.clone()));
+    state.save().unwrap();
+    
+    let reloaded = ChangeState::load(&path).unwrap();
+    assert_eq!(reloaded.status("https://example.com/", Some(validators)), PageStatus::Unchanged);
+    
+    std::fs::write(&path, "not json").unwrap();
+    assert!(ChangeState::load(&path).is_err());
+}
+
+/// Test that validators come from a HEAD request, or a GET if HEAD isn't allowed
+#[tokio::test]
+async fn test_fetch_validators() {
+    let mut server = mockito::Server::new_async().await;
+    let head = server.mock("HEAD", "/page")
+        .with_header("etag", "\"abc\"")
+        .with_header("last-modified", "Wed, 21 Oct 2015 07:28:00 GMT")
+        .create_async()
+        .await;
+    let validators = fetch_validators(&options(format!("{}/page", server.url()))).await.unwrap().unwrap();
+    head.assert_async().await;
+    assert_eq!(validators.etag.as_deref(), Some("\"abc\""));
+    assert_eq!(validators.last_modified.as_deref(), Some("Wed, 21 Oct 2015 07:28:00 GMT"));
+    
+    server.mock("HEAD", "/no-head").with_status(405).create_async().await;
+    server.mock("GET", "/no-head").with_header("etag", "W/\"def\"").create_async().await;
+    let validators = fetch_validators(&options(format!("{}/no-head", server.url()))).await.unwrap().unwrap();
+    assert_eq!(validators.etag.as_deref(), Some("W/\"def\""));
+    
+    server.mock("HEAD", "/uncached").create_async().await;
+    assert_eq!(fetch_validators(&options(format!("{}/uncached", server.url()))).await.unwrap(), None);
+    
+    server.mock("HEAD", "/missing").with_status(404).create_async().await;
+    assert!(fetch_validators(&options(format!("{}/missing", server.url()))).await.is_err());
+}
+
+/// Test that a page that can't be checked is captured anyway
+#[tokio::test]
+async fn test_check_unreachable_page() {
+    let state = ChangeState::default();
+    let status = state.check(&options("http://127.0.0.1:1/".to_string())).await;
+    assert_eq!(status, PageStatus::Changed(None));
+}
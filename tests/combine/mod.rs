@@ -0,0 +1,2 @@
+// Document tests on images built in memory, without a browser
+mod test_combine;
@@ -0,0 +1,69 @@
+use image::{Rgba, RgbaImage};
+use std::io::{Cursor, Read};
+use weblook::combine::{write_pdf, write_tiff, CombineFormat};
+
+/// Test parsing of --combine formats
+#[test]
+fn test_parse_combine_format() {
+    assert_eq!("pdf".parse::<CombineFormat>().unwrap(), CombineFormat::Pdf);
+    assert_eq!("TIFF".parse::<CombineFormat>().unwrap(), CombineFormat::Tiff);
+    assert_eq!("tif".parse::<CombineFormat>().unwrap(), CombineFormat::Tiff);
+    assert!("zip".parse::<CombineFormat>().is_err());
+    assert_eq!(CombineFormat::Pdf.default_path(), std::path::PathBuf::from("combined.pdf"));
+}
+
+/// Test that every page of a PDF is listed, sized to its capture, and that
+/// the cross-reference table points at each object
+#[test]
+fn test_write_pdf() {
+    let pages = [
+        RgbaImage::from_pixel(96, 48, Rgba([255, 0, 0, 255])),
+        RgbaImage::from_pixel(64, 192, Rgba([0, 0, 255, 0])),
+    ];
+    let pdf = write_pdf(&pages).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(text.starts_with("%PDF-1.4"));
+    assert!(text.ends_with("%%EOF\n"));
+    assert!(text.contains("/Count 2"));
+    // 96 CSS pixels are an inch, or 72 points
+    assert!(text.contains("/MediaBox [0 0 72.00 36.00]"));
+    assert!(text.contains("/MediaBox [0 0 48.00 144.00]"));
+    
+    let startxref: usize = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+    let xref = std::str::from_utf8(&pdf[startxref..]).unwrap();
+    assert!(xref.starts_with("xref\n0 9\n"));
+    for (object, entry) in xref.lines().skip(3).take(8).enumerate() {
+        let offset: usize = entry[..10].parse().unwrap();
+        assert!(pdf[offset..].starts_with(format!("{} 0 obj", object + 1).as_bytes()));
+    }
+    
+    // The transparent page is flattened onto white
+    let stream = pdf.windows(10).rposition(|w| w == b">>\nstream\n").unwrap() + 10;
+    let mut pixels = Vec::new();
+    flate2::read::ZlibDecoder::new(&pdf[stream..]).read_to_end(&mut pixels).unwrap();
+    assert_eq!(pixels.len(), 64 * 192 * 3);
+    assert!(pixels.iter().all(|&channel| channel == 255));
+    
+    assert!(write_pdf(&[]).is_err());
+}
+
+/// Test that a TIFF holds every page, with its size and transparency
+#[test]
+fn test_write_tiff() {
+    let pages = [
+        RgbaImage::from_pixel(20, 10, Rgba([255, 0, 0, 255])),
+        RgbaImage::from_pixel(8, 30, Rgba([0, 0, 255, 128])),
+    ];
+    let tiff = write_tiff(&pages).unwrap();
+    
+    let mut decoder = tiff::decoder::Decoder::new(Cursor::new(tiff)).unwrap();
+    assert_eq!(decoder.dimensions().unwrap(), (20, 10));
+    assert!(decoder.more_images());
+    decoder.next_image().unwrap();
+    assert_eq!(decoder.dimensions().unwrap(), (8, 30));
+    let tiff::decoder::DecodingResult::U8(pixels) = decoder.read_image().unwrap() else {
+        panic!("expected 8-bit pixels");
+    };
+    assert_eq!(&pixels[..4], &[0, 0, 255, 128]);
+    assert!(!decoder.more_images());
+}
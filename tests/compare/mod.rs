@@ -0,0 +1,2 @@
+// Comparison tests on images built in memory, without a browser
+mod test_compare;
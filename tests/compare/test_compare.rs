@@ -0,0 +1,56 @@
+use image::{Rgba, RgbaImage};
+use std::path::Path;
+use weblook::compare::{compare_images, compare_path, parse_similarity};
+
+/// Test that identical images are fully similar, with nothing marked in red
+#[test]
+fn test_compare_identical() {
+    let image = RgbaImage::from_pixel(10, 10, Rgba([30, 60, 90, 255]));
+    let comparison = compare_images(&image, &image);
+    assert_eq!(comparison.similarity, 100.0);
+    assert_eq!(comparison.diff.dimensions(), (10, 10));
+    assert!(comparison.diff.pixels().all(|pixel| *pixel != Rgba([255, 0, 0, 255])));
+}
+
+/// Test that differing pixels are counted and marked in red
+#[test]
+fn test_compare_changed_pixels() {
+    let a = RgbaImage::from_pixel(10, 10, Rgba([255, 255, 255, 255]));
+    let mut b = a.clone();
+    for x in 0..10 {
+        b.put_pixel(x, 0, Rgba([0, 0, 0, 255]));
+    }
+    let comparison = compare_images(&a, &b);
+    assert_eq!(comparison.similarity, 90.0);
+    assert_eq!(*comparison.diff.get_pixel(3, 0), Rgba([255, 0, 0, 255]));
+    assert_ne!(*comparison.diff.get_pixel(3, 1), Rgba([255, 0, 0, 255]));
+}
+
+/// Test that area covered by only one of the images counts as different
+#[test]
+fn test_compare_different_sizes() {
+    let a = RgbaImage::from_pixel(10, 10, Rgba([0, 0, 0, 255]));
+    let b = RgbaImage::from_pixel(10, 20, Rgba([0, 0, 0, 255]));
+    let comparison = compare_images(&a, &b);
+    assert_eq!(comparison.similarity, 50.0);
+    assert_eq!(comparison.diff.dimensions(), (10, 20));
+    assert_eq!(*comparison.diff.get_pixel(5, 15), Rgba([255, 0, 0, 255]));
+}
+
+/// Test the names of the files written next to the output
+#[test]
+fn test_compare_path() {
+    assert_eq!(compare_path(Path::new("shots/home.png"), "diff"), Path::new("shots/home-diff.png"));
+    assert_eq!(compare_path(Path::new("home.webp"), "side-by-side"), Path::new("home-side-by-side.png"));
+}
+
+/// Test --min-similarity parsing
+#[test]
+fn test_parse_similarity() {
+    assert_eq!(parse_similarity("99.5").unwrap(), 99.5);
+    assert_eq!(parse_similarity("98%").unwrap(), 98.0);
+    assert_eq!(parse_similarity("0").unwrap(), 0.0);
+    assert!(parse_similarity("100.1").is_err());
+    assert!(parse_similarity("-1").is_err());
+    assert!(parse_similarity("most").is_err());
+}
@@ -0,0 +1,3 @@
+// Doctor tests that don't depend on what is installed
+mod test_browsers;
+mod test_versions;
@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use weblook::doctor::{backends, BinaryKind, FoundBinary};
+
+fn binary(family: &'static str, kind: BinaryKind, version: &str) -> FoundBinary {
+    FoundBinary { family, kind, path: PathBuf::from(format!("/usr/bin/{}", family)), version: Some(version.to_string()) }
+}
+
+/// Test that the Chrome backend needs a ChromeDriver and Chrome of the same major version
+#[test]
+fn test_chrome_backend() {
+    let matching = [binary("chrome", BinaryKind::Driver, "120.0.1"), binary("chrome", BinaryKind::Browser, "120.0.2")];
+    assert!(backends(&matching)[0].usable);
+    
+    let mismatched = [binary("chrome", BinaryKind::Driver, "119.0.1"), binary("chrome", BinaryKind::Browser, "120.0.2")];
+    let chrome = &backends(&mismatched)[0];
+    assert!(!chrome.usable);
+    assert_eq!(chrome.detail, "ChromeDriver 119 cannot drive Chrome 120");
+    
+    let browser_only = [binary("chrome", BinaryKind::Browser, "120.0.2")];
+    assert_eq!(backends(&browser_only)[0].detail, "ChromeDriver not found");
+}
+
+/// Test that Firefox is reported but never usable
+#[test]
+fn test_firefox_backend() {
+    let found = [binary("firefox", BinaryKind::Driver, "0.34.0"), binary("firefox", BinaryKind::Browser, "121.0")];
+    let firefox = &backends(&found)[1];
+    assert_eq!(firefox.name, "firefox");
+    assert!(!firefox.usable);
+}
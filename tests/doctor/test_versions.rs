@@ -0,0 +1,21 @@
+use weblook::doctor::{major_version, parse_version};
+
+/// Test that versions are found in ChromeDriver and Chrome `--version` output
+#[test]
+fn test_parse_version() {
+    assert_eq!(
+        parse_version("ChromeDriver 120.0.6099.109 (3419140ab665596f21b385ce136419fde0924272-refs/branch-heads/6099@{#1483})").as_deref(),
+        Some("120.0.6099.109")
+    );
+    assert_eq!(parse_version("Google Chrome 120.0.6099.109 ").as_deref(), Some("120.0.6099.109"));
+    assert_eq!(parse_version("Chromium 121.0.6167.85 built on Debian 12.4").as_deref(), Some("121.0.6167.85"));
+    
+    assert_eq!(parse_version("chromedriver: command not found"), None);
+}
+
+/// Test that the major version is taken from a dotted version
+#[test]
+fn test_major_version() {
+    assert_eq!(major_version("120.0.6099.109"), Some(120));
+    assert_eq!(major_version("abc"), None);
+}
@@ -0,0 +1,2 @@
+// Tests of capturing a page in several locales
+mod test_locales;
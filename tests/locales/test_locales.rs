@@ -0,0 +1,44 @@
+use std::path::{Path, PathBuf};
+
+use weblook::locales::{check_locales, locale_options, locale_path, parse_locale};
+
+use crate::capture::base_options;
+
+/// Test that language tags are accepted, with underscores taken as hyphens
+#[test]
+fn test_parse_locale() {
+    assert_eq!(parse_locale("en").unwrap(), "en");
+    assert_eq!(parse_locale("fr-CA").unwrap(), "fr-CA");
+    assert_eq!(parse_locale("pt_BR").unwrap(), "pt-BR");
+    assert_eq!(parse_locale(" zh-Hant-TW ").unwrap(), "zh-Hant-TW");
+    
+    assert!(parse_locale("").is_err());
+    assert!(parse_locale("e").is_err());
+    assert!(parse_locale("en-").is_err());
+    assert!(parse_locale("en/../x").is_err());
+    assert!(parse_locale("12").is_err());
+}
+
+/// Test that each locale's capture is named after it, next to the output
+#[test]
+fn test_locale_path() {
+    assert_eq!(locale_path(Path::new("shots/home.png"), "fr-CA"), PathBuf::from("shots/home-fr-CA.png"));
+    assert_eq!(locale_path(Path::new("demo.gif"), "de"), PathBuf::from("demo-de.gif"));
+    assert_eq!(locale_path(Path::new("page"), "en"), PathBuf::from("page-en"));
+}
+
+/// Test that each locale gets its own options and that stdout and repeats are refused
+#[test]
+fn test_locale_options() {
+    let options = base_options();
+    let french = locale_options(&options, "fr");
+    assert_eq!(french.locale.as_deref(), Some("fr"));
+    assert_eq!(french.output_path, locale_path(&options.output_path, "fr"));
+    assert_eq!(french.url, options.url);
+    
+    let locales = ["en".to_string(), "fr".to_string()];
+    assert!(check_locales(&options, &locales).is_ok());
+    assert!(check_locales(&options, &["en".to_string(), "en".to_string()]).is_err());
+    let piped = weblook::CaptureOptions { output_path: PathBuf::from("-"), ..base_options() };
+    assert!(check_locales(&piped, &locales).is_err());
+}
@@ -7,3 +7,6 @@ mod test_client;
 
 #[cfg(feature = "mcp_experimental")]
 mod test_integration;
+
+#[cfg(feature = "mcp_experimental")]
+mod test_actions;
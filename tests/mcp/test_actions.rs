@@ -0,0 +1,22 @@
+use serde_json::json;
+use std::path::PathBuf;
+
+use weblook::capture::perform_capture;
+use weblook::mcp::actions::screenshot_options;
+
+/// Test that a capture_screenshot selector made of digits is captured as a CSS
+/// selector rather than taken as an iframe index
+#[tokio::test]
+async fn test_screenshot_selector_is_css() {
+    let params = json!({ "url": "https://example.com", "selector": "1" });
+    let mut options = screenshot_options(&params, PathBuf::from("shot.png"));
+    assert_eq!(options.selector.as_deref(), Some("1"));
+    assert_eq!(options.frame, None);
+    
+    // Capturing each match only accepts a CSS selector, so this fails on the
+    // output instead
+    options.each = true;
+    options.output_path = "-".into();
+    let err = perform_capture(options).await.unwrap_err();
+    assert_eq!(err.to_string(), "Capturing each match writes numbered files, so it cannot write to stdout");
+}
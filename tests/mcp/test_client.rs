@@ -4,6 +4,7 @@ use std::net::SocketAddr;
 use std::time::Duration;
 use tokio::time::sleep;
 
+use weblook::mcp::client::{TimeoutError, DEFAULT_TIMEOUT};
 use weblook::mcp::{MCPClient, MCPServer};
 
 /// Test that the client can connect to a server and get available actions
@@ -21,7 +22,7 @@ async fn test_client_get_actions() -> Result<()> {
     sleep(Duration::from_millis(100)).await;
     
     // Create a client to connect to the server
-    let client = MCPClient::new(&format!("http://127.0.0.1:{}", port)).await?;
+    let client = MCPClient::new(&format!("http://127.0.0.1:{}", port), DEFAULT_TIMEOUT, None).await?;
     
     // Get available actions
     let actions = client.get_available_actions().await?;
@@ -39,19 +40,22 @@ async fn test_client_get_actions() -> Result<()> {
 /// Test that the client can invoke an action and handle the response
 #[tokio::test]
 async fn test_client_invoke_action() -> Result<()> {
-    // Create a server on a specific port
-    let port = 9880;
-    let addr: SocketAddr = format!("127.0.0.1:{}", port).parse()?;
-    let mut server = MCPServer::new();
-    
-    // Start the server
-    server.start(addr).await?;
-    
-    // Give it a moment to initialize
-    sleep(Duration::from_millis(100)).await;
+    // Mock the server side of the wire format
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/actions/capture_screenshot")
+        .match_body(mockito::Matcher::Json(json!({
+            "url": "http://example.com",
+            "wait": 1
+        })))
+        .with_status(200)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"image_data":"iVBORw0KGgo=","format":"png"}"#)
+        .create_async()
+        .await;
     
     // Create a client to connect to the server
-    let client = MCPClient::new(&format!("http://127.0.0.1:{}", port)).await?;
+    let client = MCPClient::new(&server.url(), DEFAULT_TIMEOUT, None).await?;
     
     // Invoke the capture_screenshot action
     let params = json!({
@@ -62,11 +66,62 @@ async fn test_client_invoke_action() -> Result<()> {
     let response = client.invoke_action("capture_screenshot", params).await?;
     
     // Verify the response
+    mock.assert_async().await;
     assert!(response.get("image_data").is_some());
     assert_eq!(response.get("format").and_then(|v| v.as_str()), Some("png"));
     
-    // Stop the server
-    server.stop().await?;
+    Ok(())
+}
+
+/// Test that error responses surface the server's error message
+#[tokio::test]
+async fn test_client_invoke_action_error() -> Result<()> {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("POST", "/actions/capture_screenshot")
+        .with_status(500)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"error":"Failed to start ChromeDriver"}"#)
+        .create_async()
+        .await;
+    
+    let client = MCPClient::new(&server.url(), DEFAULT_TIMEOUT, None).await?;
+    
+    let err = client
+        .invoke_action("capture_screenshot", json!({"url": "http://example.com"}))
+        .await
+        .unwrap_err();
+    
+    let message = err.to_string();
+    assert!(message.contains("500"));
+    assert!(message.contains("Failed to start ChromeDriver"));
+    
+    Ok(())
+}
+
+/// Test that a server exceeding the configured timeout yields a TimeoutError
+#[tokio::test]
+async fn test_client_timeout() -> Result<()> {
+    // Accept connections but never answer them
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+    
+    let timeout = Duration::from_millis(200);
+    let client = MCPClient::new(&format!("http://127.0.0.1:{}", port), timeout, None).await?;
+    
+    let err = client
+        .invoke_action("slow_action", json!({}))
+        .await
+        .unwrap_err();
+    
+    let timeout_err = err.downcast_ref::<TimeoutError>().expect("expected a timeout error");
+    assert_eq!(timeout_err.timeout, timeout);
     
     Ok(())
 }
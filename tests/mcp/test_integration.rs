@@ -3,6 +3,7 @@ use std::net::SocketAddr;
 use std::time::Duration;
 use tokio::time::sleep;
 
+use weblook::mcp::client::DEFAULT_TIMEOUT;
 use weblook::mcp::{MCPClient, MCPServer};
 
 /// Test basic integration between server and client
@@ -18,27 +19,188 @@ async fn test_basic_integration() -> Result<()> {
     sleep(Duration::from_secs(1)).await;
     
     // Create a client to connect to the server
-    let client = MCPClient::new(&format!("http://127.0.0.1:{}", port)).await?;
+    let client = MCPClient::new(&format!("http://127.0.0.1:{}", port), DEFAULT_TIMEOUT, None).await?;
     
     // Get available actions
     let actions = client.get_available_actions().await?;
     assert!(actions.contains(&"capture_screenshot".to_string()));
     assert!(actions.contains(&"record_interaction".to_string()));
     
-    // Invoke the capture_screenshot action
-    let params = serde_json::json!({
-        "url": "http://example.com",
-        "wait": 1
-    });
+    // Invoking an unknown action reports the server's error
+    let err = client
+        .invoke_action("invalid_action", serde_json::json!({}))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Action not found: invalid_action"));
     
-    let response = client.invoke_action("capture_screenshot", params).await?;
+    // Stop the server
+    server.stop().await?;
+    
+    Ok(())
+}
+
+/// Test that progress events and the final result are streamed to the client
+#[tokio::test]
+async fn test_streaming_progress() -> Result<()> {
+    use std::sync::Arc;
+    use weblook::mcp::mcp_sdk::client::{Client, ClientConfig};
+    use weblook::mcp::mcp_sdk::server::{Server, ServerConfig};
+    use weblook::mcp::mcp_sdk::ContextAction;
+
+    // Serve a custom action that reports progress before returning
+    let port = 9883;
+    let addr: SocketAddr = format!("127.0.0.1:{}", port).parse()?;
+    let mut server = Server::new(ServerConfig::new().with_addr(addr).with_auth_disabled());
+    server.register_action(ContextAction::new(
+        "count",
+        "Count to three",
+        vec![],
+        Arc::new(|_params, progress| {
+            Box::pin(async move {
+                for step in 1..=3 {
+                    progress.report(serde_json::json!({ "step": step }));
+                }
+                Ok(serde_json::json!({ "result": "done" }))
+            })
+        }),
+    ))?;
+    let server_handle = server.clone();
+    tokio::spawn(async move { server_handle.serve().await });
     
-    // Verify the response
-    assert!(response.get("image_data").is_some());
-    assert_eq!(response.get("format").and_then(|v| v.as_str()), Some("png"));
+    // Give the server time to start
+    sleep(Duration::from_millis(100)).await;
+    
+    let config = ClientConfig::new()
+        .with_endpoint(&format!("http://127.0.0.1:{}", port))
+        .with_timeout(DEFAULT_TIMEOUT);
+    let client = Client::new(config).await?;
+    
+    let mut steps = Vec::new();
+    let response = client
+        .invoke_action_streaming("count", serde_json::json!({}), |event| {
+            steps.push(event["step"].as_u64().unwrap_or_default());
+        })
+        .await?;
+    
+    assert_eq!(steps, vec![1, 2, 3]);
+    assert_eq!(response["result"], "done");
+    
+    server.shutdown().await?;
+    
+    Ok(())
+}
+
+/// Test that a shutdown requested before the server starts serving isn't lost
+#[tokio::test]
+async fn test_early_shutdown() -> Result<()> {
+    use weblook::mcp::mcp_sdk::server::{Server, ServerConfig};
+
+    let addr: SocketAddr = "127.0.0.1:0".parse()?;
+    let server = Server::new(ServerConfig::new().with_addr(addr).with_auth_disabled());
+    server.shutdown().await?;
+    
+    tokio::time::timeout(Duration::from_secs(5), server.serve()).await??;
+    
+    Ok(())
+}
+
+/// Test that overlapping requests for exclusive actions run one at a time
+#[tokio::test]
+async fn test_exclusive_actions_do_not_overlap() -> Result<()> {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use weblook::mcp::mcp_sdk::client::{Client, ClientConfig};
+    use weblook::mcp::mcp_sdk::server::{Server, ServerConfig};
+    use weblook::mcp::mcp_sdk::ContextAction;
+
+    // Two actions that note how many exclusive handlers are running at once
+    let running = Arc::new(AtomicUsize::new(0));
+    let most = Arc::new(AtomicUsize::new(0));
+    let port = 9885;
+    let addr: SocketAddr = format!("127.0.0.1:{}", port).parse()?;
+    let mut server = Server::new(ServerConfig::new().with_addr(addr).with_auth_disabled());
+    for name in ["first", "second"] {
+        let (running, most) = (running.clone(), most.clone());
+        server.register_action(ContextAction::new(
+            name,
+            "Hold the turn for a while",
+            vec![],
+            Arc::new(move |_params, _progress| {
+                let (running, most) = (running.clone(), most.clone());
+                Box::pin(async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most.fetch_max(now, Ordering::SeqCst);
+                    sleep(Duration::from_millis(200)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    Ok(serde_json::json!({ "result": "done" }))
+                })
+            }),
+        ).exclusive())?;
+    }
+    let server_handle = server.clone();
+    tokio::spawn(async move { server_handle.serve().await });
+    
+    // Give the server time to start
+    sleep(Duration::from_millis(100)).await;
+    
+    let config = ClientConfig::new()
+        .with_endpoint(&format!("http://127.0.0.1:{}", port))
+        .with_auth_disabled()
+        .with_timeout(DEFAULT_TIMEOUT);
+    let client = Client::new(config).await?;
+    let (first, second) = tokio::join!(
+        client.invoke_action("first", serde_json::json!({})),
+        client.invoke_action("second", serde_json::json!({})),
+    );
+    assert_eq!(first?["result"], "done");
+    assert_eq!(second?["result"], "done");
+    assert_eq!(most.load(Ordering::SeqCst), 1);
+    
+    server.shutdown().await?;
+    
+    Ok(())
+}
+
+/// Test that a token-protected server rejects clients without the token
+#[tokio::test]
+async fn test_bearer_token_auth() -> Result<()> {
+    let port = 9884;
+    let addr: SocketAddr = format!("127.0.0.1:{}", port).parse()?;
+    let mut server = MCPServer::new().with_auth_token("s3cret");
+    server.start(addr).await?;
+    
+    // Give the server time to start
+    sleep(Duration::from_millis(100)).await;
+    
+    let endpoint = format!("http://127.0.0.1:{}", port);
+    
+    // Requests without a token are rejected
+    let client = MCPClient::new(&endpoint, DEFAULT_TIMEOUT, None).await?;
+    let err = client.get_available_actions().await.unwrap_err();
+    assert!(err.to_string().contains("401"));
+    
+    // Requests with the wrong token are rejected
+    let client = MCPClient::new(&endpoint, DEFAULT_TIMEOUT, Some("wrong")).await?;
+    assert!(client.get_available_actions().await.is_err());
+    
+    // Requests with the right token succeed
+    let client = MCPClient::new(&endpoint, DEFAULT_TIMEOUT, Some("s3cret")).await?;
+    let actions = client.get_available_actions().await?;
+    assert!(actions.contains(&"capture_screenshot".to_string()));
     
-    // Stop the server
     server.stop().await?;
     
     Ok(())
 }
+
+/// Test that the server refuses to listen publicly without a token
+#[tokio::test]
+async fn test_non_loopback_requires_token() -> Result<()> {
+    let addr: SocketAddr = "0.0.0.0:0".parse()?;
+    let mut server = MCPServer::new();
+    
+    let err = server.start(addr).await.unwrap_err();
+    assert!(err.to_string().contains("without an auth token"));
+    
+    Ok(())
+}
@@ -3,6 +3,7 @@ use std::net::SocketAddr;
 use std::time::Duration;
 use tokio::time::sleep;
 
+use weblook::mcp::client::DEFAULT_TIMEOUT;
 use weblook::mcp::MCPServer;
 
 /// Test that the MCP server starts and stops correctly
@@ -24,6 +25,28 @@ async fn test_server_start_stop() -> Result<()> {
     Ok(())
 }
 
+/// Test that binding port 0 reports the OS-assigned port
+#[tokio::test]
+async fn test_server_ephemeral_port() -> Result<()> {
+    let addr: SocketAddr = "127.0.0.1:0".parse()?;
+    let mut server = MCPServer::new();
+    
+    // Start the server and discover the bound port
+    let bound = server.start(addr).await?;
+    assert_ne!(bound.port(), 0);
+    assert_eq!(server.local_addr(), Some(bound));
+    
+    // The reported address accepts connections
+    let client = weblook::mcp::MCPClient::new(&format!("http://{}", bound), DEFAULT_TIMEOUT, None).await?;
+    let actions = client.get_available_actions().await?;
+    assert!(actions.contains(&"capture_screenshot".to_string()));
+    
+    // Stop the server
+    server.stop().await?;
+    
+    Ok(())
+}
+
 /// Test that the server exposes the expected context actions
 #[tokio::test]
 async fn test_server_actions() -> Result<()> {
@@ -39,7 +62,7 @@ async fn test_server_actions() -> Result<()> {
     sleep(Duration::from_millis(100)).await;
     
     // Create a client to connect to the server
-    let client = weblook::mcp::MCPClient::new(&format!("http://127.0.0.1:{}", port)).await?;
+    let client = weblook::mcp::MCPClient::new(&format!("http://127.0.0.1:{}", port), DEFAULT_TIMEOUT, None).await?;
     
     // Get available actions
     let actions = client.get_available_actions().await?;
@@ -47,6 +70,77 @@ async fn test_server_actions() -> Result<()> {
     // Check that the expected actions are available
     assert!(actions.contains(&"capture_screenshot".to_string()));
     assert!(actions.contains(&"record_interaction".to_string()));
+    assert!(actions.contains(&"capture_pdf".to_string()));
+    assert!(actions.contains(&"extract_text".to_string()));
+    
+    // Stop the server
+    server.stop().await?;
+    
+    Ok(())
+}
+
+/// Send a raw HTTP/1.1 request to the server and return the full response text
+async fn raw_request(port: u16, request: &str) -> Result<String> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port)).await?;
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
+
+/// Test that the server answers real HTTP requests on the actions endpoints
+#[tokio::test]
+async fn test_server_http_endpoints() -> Result<()> {
+    // Create a server on a specific port
+    let port = 9877;
+    let addr: SocketAddr = format!("127.0.0.1:{}", port).parse()?;
+    let mut server = MCPServer::new();
+    
+    // Start the server
+    server.start(addr).await?;
+    
+    // Give it a moment to initialize
+    sleep(Duration::from_millis(100)).await;
+    
+    // List actions
+    let response = raw_request(
+        port,
+        "GET /actions HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    ).await?;
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("\"capture_screenshot\""));
+    assert!(response.contains("\"record_interaction\""));
+    
+    // Unknown actions are reported as 404 with an error body
+    let body = "{}";
+    let response = raw_request(
+        port,
+        &format!(
+            "POST /actions/invalid_action HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        ),
+    ).await?;
+    assert!(response.starts_with("HTTP/1.1 404"));
+    assert!(response.contains("Action not found: invalid_action"));
+    
+    // Parameters are validated against the declared schema
+    let body = r#"{"wait":"soon","selector":5}"#;
+    let response = raw_request(
+        port,
+        &format!(
+            "POST /actions/capture_screenshot HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        ),
+    ).await?;
+    assert!(response.starts_with("HTTP/1.1 400"));
+    assert!(response.contains("url (missing required parameter)"));
+    assert!(response.contains("wait (expected integer)"));
+    assert!(response.contains("selector (expected string)"));
     
     // Stop the server
     server.stop().await?;
@@ -1,4 +1,26 @@
 #[cfg(feature = "mcp_experimental")]
 pub mod mcp;
 
+pub mod capabilities;
+
+pub mod bidi;
+
+pub mod capture;
+
+pub mod changes;
+
+pub mod combine;
+
+pub mod compare;
+
+pub mod doctor;
+
+pub mod locales;
+
+pub mod polish;
+
+pub mod upload;
+
+pub mod warm;
+
 // Add other test modules here as needed
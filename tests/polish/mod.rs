@@ -0,0 +1,2 @@
+// Framing tests on images built in memory, without a browser
+mod test_polish;
@@ -0,0 +1,38 @@
+use image::{Rgba, RgbaImage};
+use weblook::polish::Polish;
+
+/// Test that the screenshot is padded on every side and keeps its pixels
+#[test]
+fn test_polish_pads_screenshot() {
+    let image = RgbaImage::from_pixel(40, 30, Rgba([200, 0, 100, 255]));
+    let polish = Polish { padding: 10, radius: 0, shadow: 0, ..Polish::default() };
+    let framed = polish.apply(&image);
+    assert_eq!(framed.dimensions(), (60, 50));
+    assert_eq!(*framed.get_pixel(0, 0), polish.background);
+    assert_eq!(*framed.get_pixel(10, 10), Rgba([200, 0, 100, 255]));
+    assert_eq!(*framed.get_pixel(49, 39), Rgba([200, 0, 100, 255]));
+}
+
+/// Test that rounded corners show the background through them
+#[test]
+fn test_polish_rounds_corners() {
+    let image = RgbaImage::from_pixel(40, 30, Rgba([200, 0, 100, 255]));
+    let polish = Polish { padding: 10, radius: 8, shadow: 0, ..Polish::default() };
+    let framed = polish.apply(&image);
+    assert_eq!(*framed.get_pixel(10, 10), polish.background);
+    assert_eq!(*framed.get_pixel(49, 39), polish.background);
+    // The middle of each edge is untouched
+    assert_eq!(*framed.get_pixel(30, 10), Rgba([200, 0, 100, 255]));
+}
+
+/// Test that the shadow darkens the background, more below than above
+#[test]
+fn test_polish_shadow_falls_below() {
+    let image = RgbaImage::from_pixel(40, 30, Rgba([255, 255, 255, 255]));
+    let polish = Polish { padding: 20, radius: 0, shadow: 12, ..Polish::default() };
+    let framed = polish.apply(&image);
+    let above = framed.get_pixel(40, 17).0[0];
+    let below = framed.get_pixel(40, 52).0[0];
+    assert!(below < above, "{} should be darker than {}", below, above);
+    assert!(below < polish.background.0[0]);
+}
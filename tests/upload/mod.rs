@@ -0,0 +1,2 @@
+// Upload tests that don't need network access or credentials
+mod test_upload;
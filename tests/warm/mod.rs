@@ -0,0 +1,3 @@
+// Warm session state tests that don't start a browser
+#[cfg(unix)]
+mod test_state;
@@ -0,0 +1,37 @@
+use std::os::unix::fs::PermissionsExt;
+
+use weblook::warm::{owned_by_current_user, private_dir};
+
+/// Test that the state directory is created private and reused while it stays so
+#[test]
+fn test_private_dir() {
+    let base = tempfile::tempdir().unwrap();
+    let dir = private_dir(base.path()).unwrap();
+    assert!(dir.starts_with(base.path()));
+    assert_eq!(std::fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+    assert_eq!(private_dir(base.path()).unwrap(), dir);
+    
+    // Other users must not be able to plant files in it
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o777)).unwrap();
+    assert!(private_dir(base.path()).is_err());
+}
+
+/// Test that a file in place of the directory is refused
+#[test]
+fn test_private_dir_not_a_directory() {
+    let base = tempfile::tempdir().unwrap();
+    let dir = private_dir(base.path()).unwrap();
+    std::fs::remove_dir(&dir).unwrap();
+    std::fs::write(&dir, b"").unwrap();
+    assert!(private_dir(base.path()).is_err());
+}
+
+/// Test that only existing files of the current user are trusted
+#[test]
+fn test_owned_by_current_user() {
+    let base = tempfile::tempdir().unwrap();
+    let path = base.path().join("weblook-session.json");
+    assert!(!owned_by_current_user(&path));
+    std::fs::write(&path, b"{}").unwrap();
+    assert!(owned_by_current_user(&path));
+}